@@ -201,12 +201,13 @@ impl SceneRenderPass for HighlightRenderPass {
                 z_far: ctx.camera.projection().z_far(),
                 view_matrix: ctx.camera.view_matrix(),
                 projection_matrix: ctx.camera.projection_matrix(),
+                viewport: ctx.viewport,
             };
 
             let mut render_bundle_storage =
                 RenderDataBundleStorage::new_empty(observer_info.clone());
 
-            let frustum = ctx.camera.frustum();
+            let frustum = observer_info.frustum();
             let mut render_context = RenderContext {
                 elapsed_time: ctx.elapsed_time,
                 observer_info: &observer_info,
@@ -244,6 +245,7 @@ impl SceneRenderPass for HighlightRenderPass {
                     fallback_resources: ctx.fallback_resources,
                     ambient_light: Default::default(),
                     scene_depth: Some(&ctx.depth_texture),
+                    polygon_offset: None,
                     viewport: ctx.viewport,
                     uniform_memory_allocator: ctx.uniform_memory_allocator,
                 },
@@ -271,6 +273,8 @@ impl SceneRenderPass for HighlightRenderPass {
                     }),
                     stencil_op: Default::default(),
                     scissor_box: None,
+                    polygon_offset: None,
+                    depth_clamp: false,
                 },
                 &[ResourceBindGroup {
                     bindings: &[
@@ -151,6 +151,8 @@ impl SceneRenderPass for OverlayRenderPass {
                     }),
                     stencil_op: Default::default(),
                     scissor_box: None,
+                    polygon_offset: None,
+                    depth_clamp: false,
                 },
                 &[ResourceBindGroup {
                     bindings: &[
@@ -244,7 +244,8 @@ fn make_shape_gizmo(
             ColliderShape::Triangle(_) => Box::new(TriangleShapeGizmo::new(root, visible, scene)),
             ColliderShape::Trimesh(_)
             | ColliderShape::Heightfield(_)
-            | ColliderShape::Polyhedron(_) => Box::new(DummyShapeGizmo),
+            | ColliderShape::Polyhedron(_)
+            | ColliderShape::ConvexHull(_) => Box::new(DummyShapeGizmo),
         }
     } else if let Some(collider) = scene
         .graph
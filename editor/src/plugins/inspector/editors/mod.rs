@@ -42,7 +42,10 @@ use crate::plugins::{
 use crate::{
     fyrox::{
         asset::{untyped::UntypedResource, Resource},
-        core::pool::{ErasedHandle, Handle},
+        core::{
+            algebra::Vector3,
+            pool::{ErasedHandle, Handle},
+        },
         gui::{
             self,
             font::FontResource,
@@ -76,9 +79,9 @@ use crate::{
                 Projection, SkyBox,
             },
             collider::{
-                BallShape, BitMask, CapsuleShape, ColliderShape, ConeShape, ConvexPolyhedronShape,
-                CuboidShape, CylinderShape, GeometrySource, HeightfieldShape, InteractionGroups,
-                SegmentShape, TriangleShape, TrimeshShape,
+                BallShape, BitMask, CapsuleShape, ColliderShape, ConeShape, ConvexHullShape,
+                ConvexPolyhedronShape, CuboidShape, CylinderShape, GeometrySource,
+                HeightfieldShape, InteractionGroups, SegmentShape, TriangleShape, TrimeshShape,
             },
             dim2,
             graph::physics::CoefficientCombineRule,
@@ -275,6 +278,9 @@ pub fn make_property_editors_container(sender: MessageSender) -> PropertyEditorD
     container.insert(EnumPropertyEditorDefinition::<LodGroup>::new_optional());
     container.insert(InheritablePropertyEditorDefinition::<Option<LodGroup>>::new());
 
+    container.insert(EnumPropertyEditorDefinition::<Vector3<f32>>::new_optional());
+    container.insert(InheritablePropertyEditorDefinition::<Option<Vector3<f32>>>::new());
+
     {
         use crate::fyrox::scene::animation::spritesheet::prelude::*;
         container.register_inheritable_enum::<Status, _>();
@@ -433,6 +439,7 @@ pub fn make_property_editors_container(sender: MessageSender) -> PropertyEditorD
     container.register_inheritable_inspectable::<dim2::collider::HeightfieldShape>();
     container.register_inheritable_inspectable::<dim2::collider::TileMapShape>();
     container.register_inheritable_inspectable::<ConvexPolyhedronShape>();
+    container.register_inheritable_inspectable::<ConvexHullShape>();
     container.insert(SpriteSheetFramesContainerEditorDefinition);
 
     container.insert(SurfaceDataPropertyEditorDefinition {
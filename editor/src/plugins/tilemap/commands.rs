@@ -551,7 +551,7 @@ impl SetBrushPageCommand {
     fn swap(&mut self) {
         let mut brush = self.brush.data_ref();
         swap_hash_map_entry(brush.pages.entry(self.position), &mut self.page);
-        brush.change_count.set();
+        brush.mark_changed();
     }
 }
 
@@ -686,7 +686,7 @@ impl MoveBrushPageCommand {
             swap_hash_map_entry(brush.pages.entry(*p + self.end_offset), &mut self.data[i]);
         }
         std::mem::swap(&mut self.start_offset, &mut self.end_offset);
-        brush.change_count.set();
+        brush.mark_changed();
     }
 }
 
@@ -794,13 +794,13 @@ impl MoveBrushTileCommand {
             return;
         };
         for (i, p) in self.tiles.iter().enumerate() {
-            swap_hash_map_entry(page.tiles.entry(*p + self.start_offset), &mut self.data[i]);
+            swap_hash_map_entry(page.tiles_mut().entry(*p + self.start_offset), &mut self.data[i]);
         }
         for (i, p) in self.tiles.iter().enumerate() {
-            swap_hash_map_entry(page.tiles.entry(*p + self.end_offset), &mut self.data[i]);
+            swap_hash_map_entry(page.tiles_mut().entry(*p + self.end_offset), &mut self.data[i]);
         }
         std::mem::swap(&mut self.start_offset, &mut self.end_offset);
-        brush.change_count.set();
+        brush.mark_changed();
     }
 }
 
@@ -933,9 +933,9 @@ impl SetBrushTilesCommand {
     fn swap(&mut self) {
         let mut brush = self.brush.data_ref();
         if let Some(page) = brush.pages.get_mut(&self.page) {
-            page.tiles.swap_tiles(&mut self.tiles);
+            page.tiles_mut().swap_tiles(&mut self.tiles);
         }
-        brush.change_count.set();
+        brush.mark_changed();
     }
 }
 
@@ -1170,7 +1170,7 @@ impl ModifyBrushPageIconCommand {
             return;
         };
         std::mem::swap(&mut self.icon, &mut page.icon);
-        brush.change_count.set();
+        brush.mark_changed();
     }
 }
 
@@ -1198,7 +1198,7 @@ impl ModifyBrushTileSetCommand {
     fn swap(&mut self) {
         let mut brush = self.brush.data_ref();
         std::mem::swap(&mut self.tile_set, &mut brush.tile_set);
-        brush.change_count.set();
+        brush.mark_changed();
     }
 }
 
@@ -62,6 +62,7 @@ use crate::fyrox::{
     core::{
         algebra::{Matrix4, Vector2, Vector3},
         color::Color,
+        log::Log,
         math::{plane::Plane, Matrix4Ext},
         parking_lot::{Mutex, MutexGuard},
         pool::Handle,
@@ -89,6 +90,7 @@ use crate::fyrox::{
         debug::Line,
         node::Node,
         tilemap::{
+            brush::TileMapBrushResource,
             tileset::{TileSet, TileSetResource},
             RandomTileSource, Stamp, TileBook, TileCollider, TileDefinitionHandle, TileMap,
             TilePaletteStage,
@@ -178,6 +180,25 @@ fn make_drawing_mode_button(
     .build(ctx)
 }
 
+/// Log a warning if the active brush of the given tile map uses a different tile set
+/// than the tile map, since the handles of such a brush would refer to the wrong tiles.
+fn warn_on_brush_mismatch(tile_map: &TileMap) {
+    let (Some(brush), Some(tile_set)) = (tile_map.active_brush(), tile_map.tile_set()) else {
+        return;
+    };
+    let mut state = brush.state();
+    let Some(brush) = state.data() else {
+        return;
+    };
+    if !brush.is_compatible_with(tile_set) {
+        Log::warn(format!(
+            "The active brush of tile map {} does not use the tile set of the tile map. \
+            Tiles drawn with this brush will be wrong.",
+            tile_map.name()
+        ));
+    }
+}
+
 /// The possible drawing mode when the user is editing tiles.
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Visit, Reflect)]
 pub enum DrawingMode {
@@ -250,6 +271,9 @@ pub struct TileMapEditorPlugin {
     /// The plugin provides a service where it holds onto some messages and sends them
     /// in the next frame.
     delayed_messages: Vec<DelayedMessage>,
+    /// The active brush and the tile set of the selected tile map at the moment of the last
+    /// [`warn_on_brush_mismatch`] check, to check them again only when they change.
+    checked_brush: (Option<TileMapBrushResource>, Option<TileSetResource>),
 }
 
 /// This is the state that is shared between the plugin, the palette widgets, the interaction mode,
@@ -521,6 +545,8 @@ impl TileMapEditorPlugin {
     fn open_panel_for_tile_map(&mut self, editor: &mut Editor) {
         let resource = if let Some(tile_map) = self.get_tile_map_mut(editor) {
             if let Some(brush) = tile_map.active_brush() {
+                warn_on_brush_mismatch(tile_map);
+                self.checked_brush = (Some(brush.clone()), tile_map.tile_set().cloned());
                 TileBook::Brush(brush.clone())
             } else if let Some(tile_set) = tile_map.tile_set() {
                 TileBook::TileSet(tile_set.clone())
@@ -547,6 +573,21 @@ impl TileMapEditorPlugin {
             self.panel = Some(panel);
         }
     }
+    /// Check the active brush of the selected tile map whenever the brush or the tile set of
+    /// the tile map changes, for example when the user edits them in the inspector.
+    fn check_brush_change(&mut self, editor: &mut Editor) {
+        let Some(tile_map) = self.get_tile_map_mut(editor) else {
+            return;
+        };
+        let resources = (
+            tile_map.active_brush().cloned(),
+            tile_map.tile_set().cloned(),
+        );
+        if resources != self.checked_brush {
+            warn_on_brush_mismatch(tile_map);
+            self.checked_brush = resources;
+        }
+    }
     fn update_state(&mut self) {
         let state = self.state.lock();
         if match state.drawing_mode {
@@ -625,6 +666,8 @@ impl EditorPlugin for TileMapEditorPlugin {
     }
 
     fn on_sync_to_model(&mut self, editor: &mut Editor) {
+        self.check_brush_change(editor);
+
         let ui = editor.engine.user_interfaces.first_mut();
 
         let palette = self.state.lock().selection_palette();
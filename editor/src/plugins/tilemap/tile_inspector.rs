@@ -1202,10 +1202,10 @@ impl TileInspector {
             .map(|position| SetBrushPageCommand {
                 brush: brush.clone(),
                 position,
-                page: Some(TileMapBrushPage {
-                    icon: TileDefinitionHandle::new(0, 0, 0, -1),
-                    tiles: Tiles::default(),
-                }),
+                page: Some(TileMapBrushPage::new(
+                    TileDefinitionHandle::new(0, 0, 0, -1),
+                    Tiles::default(),
+                )),
             })
             .map(Command::new)
             .collect::<Vec<_>>();
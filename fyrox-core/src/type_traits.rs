@@ -20,6 +20,7 @@
 
 pub use fyrox_core_derive::ComponentProvider;
 pub use fyrox_core_derive::TypeUuidProvider;
+use nalgebra::Vector3;
 use std::any::{Any, TypeId};
 use std::path::PathBuf;
 use uuid::Uuid;
@@ -91,6 +92,15 @@ impl<T: TypeUuidProvider> TypeUuidProvider for Vec<T> {
     }
 }
 
+impl<T: TypeUuidProvider> TypeUuidProvider for Vector3<T> {
+    fn type_uuid() -> Uuid {
+        combine_uuids(
+            uuid::uuid!("2d3a9a0c-5a3f-4a37-9a69-0c3b2a1f7e45"),
+            T::type_uuid(),
+        )
+    }
+}
+
 #[inline]
 pub fn combine_uuids(a: Uuid, b: Uuid) -> Uuid {
     let mut combined_bytes = a.into_bytes();
@@ -229,6 +229,15 @@ impl<T> InheritableVariable<T> {
         self.flags.get().contains(VariableFlags::NEED_SYNC)
     }
 
+    /// Removes the [`VariableFlags::NEED_SYNC`] flag without syncing the data model. Use it when
+    /// the data model was synced by other means.
+    #[inline]
+    pub fn mark_synced(&self) {
+        let mut flags = self.flags.get();
+        flags.remove(VariableFlags::NEED_SYNC);
+        self.flags.set(flags);
+    }
+
     /// Returns a reference to the wrapped value.
     #[inline]
     pub fn get_value_ref(&self) -> &T {
@@ -79,6 +79,20 @@ pub enum FrameworkError {
     InvalidFrameBuffer,
     /// OpenGL failed to construct framebuffer.
     FailedToConstructFBO,
+    /// Means that an operation referenced a frame buffer attachment that does not exist.
+    InvalidAttachmentIndex {
+        /// Requested attachment index.
+        index: usize,
+        /// Actual amount of color attachments.
+        count: usize,
+    },
+    /// Means that a frame buffer attachment cannot be used in its current configuration.
+    InvalidAttachment {
+        /// Index of the color attachment, [`None`] means the depth/stencil attachment.
+        color_index: Option<usize>,
+        /// Description of the problem.
+        reason: String,
+    },
     /// Custom error. Usually used for internal errors.
     Custom(String),
     /// Graphics server disconnected.
@@ -151,6 +165,20 @@ impl Display for FrameworkError {
             FrameworkError::FailedToConstructFBO => {
                 write!(f, "OpenGL failed to construct framebuffer.")
             }
+            FrameworkError::InvalidAttachmentIndex { index, count } => {
+                write!(
+                    f,
+                    "Attachment index {index} is out of range, the frame buffer has {count} \
+                    color attachments."
+                )
+            }
+            FrameworkError::InvalidAttachment {
+                color_index,
+                reason,
+            } => match color_index {
+                Some(index) => write!(f, "Color attachment {index} is invalid: {reason}"),
+                None => write!(f, "Depth/stencil attachment is invalid: {reason}"),
+            },
             FrameworkError::Custom(v) => {
                 write!(f, "Custom error: {v}")
             }
@@ -30,8 +30,8 @@ use crate::{
     error::FrameworkError,
     geometry_buffer::{DrawCallStatistics, GeometryBuffer},
     gpu_program::{GpuProgram, UniformLocation},
-    gpu_texture::{CubeMapFace, GpuTexture},
-    DrawParameters, ElementRange,
+    gpu_texture::{CubeMapFace, GpuTexture, PixelKind},
+    DrawParameters, ElementRange, ScissorBox,
 };
 use std::{cell::RefCell, rc::Rc};
 
@@ -47,6 +47,83 @@ pub enum AttachmentKind {
     Depth,
 }
 
+/// Interpolation mode that is used when a blit operation has to scale the image.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub enum BlitFilter {
+    /// Takes the closest texel. This is the only mode that is valid for depth and stencil data.
+    #[default]
+    Nearest,
+    /// Linearly interpolates between neighbouring texels. Honored only by color attachments, see
+    /// [`FrameBuffer::blit_to_filtered`] for more info.
+    Linear,
+}
+
+/// Size of the color attachment at the given index of the frame buffer.
+fn color_attachment_size(
+    frame_buffer: &dyn FrameBuffer,
+    index: usize,
+) -> Result<(usize, usize), FrameworkError> {
+    let attachments = frame_buffer.color_attachments();
+    let attachment = attachments
+        .get(index)
+        .ok_or(FrameworkError::InvalidAttachmentIndex {
+            index,
+            count: attachments.len(),
+        })?;
+    let size = attachment.texture.borrow().kind().surface_size();
+    Ok(size)
+}
+
+/// Successively blits the color attachment of the source frame buffer into each of the given
+/// destination frame buffers with linear filtering, using the output of each level as the input
+/// of the next one. This is useful to produce a pyramid of downsampled images for bloom or mip
+/// generation; the levels are usually of decreasing size. Each blit covers the whole surface of
+/// its source and destination. The first color attachment of each level receives the image, and it
+/// is also the source of the next level. `src_attachment` is the color attachment of the source
+/// frame buffer to start with.
+///
+/// Returns the number of blits that were performed, which is one per level. Blits do not issue any
+/// draw calls, so there are no draw call statistics to report. An error is returned, before
+/// anything is blitted, if some frame buffer in the chain does not have the required color
+/// attachment (the back buffer has none).
+pub fn blit_downsample_chain(
+    source: &dyn FrameBuffer,
+    src_attachment: usize,
+    levels: &[&dyn FrameBuffer],
+) -> Result<usize, FrameworkError> {
+    let mut src_size = color_attachment_size(source, src_attachment)?;
+    let mut sizes = Vec::with_capacity(levels.len());
+    for level in levels {
+        sizes.push(color_attachment_size(*level, 0)?);
+    }
+    let mut src = source;
+    let mut src_attachment = src_attachment;
+    let mut blit_count = 0;
+    for (level, dst_size) in levels.iter().zip(sizes) {
+        src.blit_to_filtered(
+            *level,
+            0,
+            0,
+            src_size.0 as i32,
+            src_size.1 as i32,
+            0,
+            0,
+            dst_size.0 as i32,
+            dst_size.1 as i32,
+            true,
+            false,
+            false,
+            src_attachment,
+            BlitFilter::Linear,
+        )?;
+        blit_count += 1;
+        src = *level;
+        src_attachment = 0;
+        src_size = dst_size;
+    }
+    Ok(blit_count)
+}
+
 /// Frame buffer attachment.
 pub struct Attachment {
     /// Current kind of attachment. Tells the renderer how the texture should be used.
@@ -55,6 +132,75 @@ pub struct Attachment {
     pub texture: Rc<RefCell<dyn GpuTexture>>,
 }
 
+/// Checks that the given set of attachments could form a valid frame buffer. All attachments must
+/// have the same dimensions, color attachments must use pixel kinds that could be rendered to and
+/// the depth/stencil attachment (if any) must use a depth pixel kind (with stencil for
+/// [`AttachmentKind::DepthStencil`]).
+pub fn validate_attachments(
+    depth_attachment: Option<&Attachment>,
+    color_attachments: &[Attachment],
+) -> Result<(), FrameworkError> {
+    let mut size = None;
+    let mut check_size =
+        |color_index: Option<usize>, attachment: &Attachment| -> Result<(), FrameworkError> {
+            let attachment_size = attachment.texture.borrow().kind().surface_size();
+            match size {
+                None => {
+                    size = Some(attachment_size);
+                    Ok(())
+                }
+                Some(size) if size == attachment_size => Ok(()),
+                Some((width, height)) => Err(FrameworkError::InvalidAttachment {
+                    color_index,
+                    reason: format!(
+                        "size {}x{} does not match size {width}x{height} of other attachments",
+                        attachment_size.0, attachment_size.1
+                    ),
+                }),
+            }
+        };
+
+    if let Some(depth_attachment) = depth_attachment {
+        let pixel_kind = depth_attachment.texture.borrow().pixel_kind();
+        let reason = match depth_attachment.kind {
+            AttachmentKind::Color => Some("color attachment cannot be used as depth/stencil"),
+            AttachmentKind::DepthStencil if !pixel_kind.has_stencil() => {
+                Some("pixel kind has no stencil component")
+            }
+            AttachmentKind::Depth | AttachmentKind::DepthStencil if !pixel_kind.is_depth() => {
+                Some("pixel kind has no depth component")
+            }
+            _ => None,
+        };
+        if let Some(reason) = reason {
+            return Err(FrameworkError::InvalidAttachment {
+                color_index: None,
+                reason: format!("{reason} ({pixel_kind:?})"),
+            });
+        }
+        check_size(None, depth_attachment)?;
+    }
+
+    for (index, color_attachment) in color_attachments.iter().enumerate() {
+        let pixel_kind = color_attachment.texture.borrow().pixel_kind();
+        if color_attachment.kind != AttachmentKind::Color {
+            return Err(FrameworkError::InvalidAttachment {
+                color_index: Some(index),
+                reason: "only one depth/stencil attachment is allowed".to_string(),
+            });
+        }
+        if !pixel_kind.is_color_renderable() {
+            return Err(FrameworkError::InvalidAttachment {
+                color_index: Some(index),
+                reason: format!("pixel kind {pixel_kind:?} cannot be rendered to"),
+            });
+        }
+        check_size(Some(index), color_attachment)?;
+    }
+
+    Ok(())
+}
+
 /// Defines a range of data in a particular buffer.
 #[derive(Default)]
 pub enum BufferDataUsage {
@@ -165,9 +311,23 @@ pub trait FrameBuffer: Downcast {
     /// Returns an optional depth/stencil attachment.
     fn depth_attachment(&self) -> Option<&Attachment>;
 
+    /// Checks that the attachments of the frame buffer are compatible with each other, see
+    /// [`validate_attachments`] for more info. Frame buffers are validated on creation, so this
+    /// method is mostly useful after attachment textures were changed.
+    fn validate(&self) -> Result<(), FrameworkError> {
+        validate_attachments(self.depth_attachment(), self.color_attachments())
+    }
+
     /// Sets an active face of a cube map (only for frame buffers that using cube maps for rendering).
     fn set_cubemap_face(&mut self, attachment_index: usize, face: CubeMapFace);
 
+    /// Sets an active layer of a layered color attachment (a volume texture), so all subsequent
+    /// draw calls will render into the given layer only. This allows to render into multiple
+    /// slices of a single texture (for example, shadow cascades) without creating a separate frame
+    /// buffer for each slice. Returns an error if there's no such attachment, if the attachment
+    /// texture is not layered or if the layer is out of bounds.
+    fn set_layer(&mut self, attachment_index: usize, layer: usize) -> Result<(), FrameworkError>;
+
     /// Performs data transfer from one frame buffer to another with scaling. It copies a region
     /// defined by `src_x0`, `src_y0`, `src_x1`, `src_y1` coordinates from the frame buffer and
     /// "pastes" it to the other frame buffer into a region defined by `dst_x0`, `dst_y0`, `dst_x1`,
@@ -175,7 +335,12 @@ pub trait FrameBuffer: Downcast {
     /// be interpolated using nearest interpolation.
     ///
     /// This method can copy only specific parts of the image: `copy_color` tells the method to copy
-    /// the data from
+    /// the data from the color attachment with the `src_attachment` index (use `0` for the first
+    /// attachment), `copy_depth` and `copy_stencil` do the same for depth and stencil data.
+    /// `src_attachment` is ignored for the back buffer. An error is returned if `copy_color` is set
+    /// and the frame buffer has no color attachment with the `src_attachment` index.
+    ///
+    /// This is a shortcut for [`Self::blit_to_filtered`] with [`BlitFilter::Nearest`].
     fn blit_to(
         &self,
         dest: &dyn FrameBuffer,
@@ -190,20 +355,135 @@ pub trait FrameBuffer: Downcast {
         copy_color: bool,
         copy_depth: bool,
         copy_stencil: bool,
-    );
+        src_attachment: usize,
+    ) -> Result<(), FrameworkError> {
+        self.blit_to_filtered(
+            dest,
+            src_x0,
+            src_y0,
+            src_x1,
+            src_y1,
+            dst_x0,
+            dst_y0,
+            dst_x1,
+            dst_y1,
+            copy_color,
+            copy_depth,
+            copy_stencil,
+            src_attachment,
+            BlitFilter::Nearest,
+        )
+    }
+
+    /// The same as [`Self::blit_to`], but allows to select the interpolation mode that is used
+    /// when the source rectangle does not match the destination.
+    ///
+    /// The `filter` is honored only by color attachments ([`AttachmentKind::Color`]). Depth and
+    /// stencil data ([`AttachmentKind::Depth`] and [`AttachmentKind::DepthStencil`]) can only be
+    /// blitted with nearest interpolation, so the filter is ignored (and [`BlitFilter::Nearest`]
+    /// is used for the whole operation) if `copy_depth` or `copy_stencil` is set.
+    fn blit_to_filtered(
+        &self,
+        dest: &dyn FrameBuffer,
+        src_x0: i32,
+        src_y0: i32,
+        src_x1: i32,
+        src_y1: i32,
+        dst_x0: i32,
+        dst_y0: i32,
+        dst_x1: i32,
+        dst_y1: i32,
+        copy_color: bool,
+        copy_depth: bool,
+        copy_stencil: bool,
+        src_attachment: usize,
+        filter: BlitFilter,
+    ) -> Result<(), FrameworkError>;
 
     /// Clears the frame buffer in the given viewport with the given set of optional values. This
     /// method clears multiple attachments at once. What will be cleared defined by the provided
     /// values. If `color` is not [`None`], then all the color attachments will be cleared with the
     /// given color. The same applies to depth and stencil buffers.
+    ///
+    /// This is a shortcut for [`Self::clear_scissored`] without a scissor box.
     fn clear(
         &mut self,
         viewport: Rect<i32>,
         color: Option<Color>,
         depth: Option<f32>,
         stencil: Option<i32>,
+    ) {
+        self.clear_scissored(viewport, None, color, depth, stencil)
+    }
+
+    /// The same as [`Self::clear`], but allows to limit the cleared area with an optional scissor
+    /// box. Pixels outside the box are left untouched, which is useful to clear only a part of the
+    /// frame buffer (for example, a view of a single player in split-screen mode). If `scissor_box`
+    /// is [`None`], the whole viewport is cleared. The scissor test is disabled after the clear,
+    /// so it won't affect subsequent operations.
+    fn clear_scissored(
+        &mut self,
+        viewport: Rect<i32>,
+        scissor_box: Option<ScissorBox>,
+        color: Option<Color>,
+        depth: Option<f32>,
+        stencil: Option<i32>,
     );
 
+    /// Clears a single color attachment with the given `index` in the given viewport. Unlike
+    /// [`Self::clear`], this method does not touch other attachments, which is useful to clear
+    /// different attachments of a G-buffer with different values. Returns
+    /// [`FrameworkError::InvalidAttachmentIndex`] if the index is out of bounds of
+    /// [`Self::color_attachments`].
+    fn clear_attachment(
+        &mut self,
+        index: usize,
+        viewport: Rect<i32>,
+        color: Color,
+    ) -> Result<(), FrameworkError>;
+
+    /// Clears the depth/stencil attachment in the given viewport, color attachments are left
+    /// untouched. Only the parts for which the value is not [`None`] will be cleared. Returns an
+    /// error if the frame buffer has no depth/stencil attachment.
+    fn clear_depth_stencil(
+        &mut self,
+        viewport: Rect<i32>,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    ) -> Result<(), FrameworkError>;
+
+    /// Reads the pixels of the given `rect` of the color attachment with the `attachment_index`
+    /// and appends them to the `out` buffer. The rows are tightly packed (without any alignment)
+    /// and go from the bottom to the top of the rectangle. Returns the pixel kind of the attachment,
+    /// so the caller knows how to interpret the bytes (for example, four bytes per pixel for
+    /// [`PixelKind::RGBA8`] or a single `f32` per pixel for [`PixelKind::R32F`]).
+    ///
+    /// Returns [`FrameworkError::InvalidAttachmentIndex`] if there's no such color attachment (the
+    /// back buffer has no color attachments).
+    ///
+    /// ## Performance
+    ///
+    /// This method is a synchronous operation, that stalls the CPU until the GPU finishes all the
+    /// rendering commands that affect the attachment. It is fine for screenshots or tests, but
+    /// should be avoided in per-frame code. Use [`crate::read_buffer::AsyncReadBuffer`] for
+    /// non-blocking read back.
+    fn read_pixels(
+        &self,
+        attachment_index: usize,
+        rect: Rect<i32>,
+        out: &mut Vec<u8>,
+    ) -> Result<PixelKind, FrameworkError>;
+
+    /// Tells the video driver that the content of the specified attachments is no longer needed and
+    /// can be discarded. `attachments` is a list of color attachment indices, `depth` and `stencil`
+    /// tells whether the content of the depth and stencil buffers should be discarded as well. The
+    /// content of the discarded attachments is undefined after this call.
+    ///
+    /// This is just a hint, on desktop GPUs it does almost nothing, but on tiled (mobile) GPUs it
+    /// allows the driver to skip writing the attachments back to the memory. It is a no-op if the
+    /// video driver does not support invalidation.
+    fn invalidate(&mut self, attachments: &[usize], depth: bool, stencil: bool);
+
     /// Draws the specified geometry buffer using the given GPU program and a set of resources. This
     /// method the main method to draw anything.
     ///
@@ -241,3 +521,245 @@ pub trait FrameBuffer: Downcast {
         resources: &[ResourceBindGroup],
     ) -> DrawCallStatistics;
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{color::Color, math::Rect},
+        error::FrameworkError,
+        framebuffer::{
+            blit_downsample_chain, Attachment, AttachmentKind, BlitFilter, FrameBuffer,
+            ResourceBindGroup,
+        },
+        geometry_buffer::{DrawCallStatistics, GeometryBuffer},
+        gpu_program::GpuProgram,
+        gpu_texture::{
+            Coordinate, CubeMapFace, GpuTexture, GpuTextureKind, MagnificationFilter,
+            MinificationFilter, PixelKind, WrapMode,
+        },
+        DrawParameters, ElementRange, ScissorBox,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    /// A texture that only knows its size.
+    struct SizeTexture(GpuTextureKind);
+
+    impl GpuTexture for SizeTexture {
+        fn set_anisotropy(&mut self, _anisotropy: f32) {}
+        fn anisotropy(&self) -> f32 {
+            1.0
+        }
+        fn set_minification_filter(&mut self, _min_filter: MinificationFilter) {}
+        fn minification_filter(&self) -> MinificationFilter {
+            MinificationFilter::Linear
+        }
+        fn set_magnification_filter(&mut self, _mag_filter: MagnificationFilter) {}
+        fn magnification_filter(&self) -> MagnificationFilter {
+            MagnificationFilter::Linear
+        }
+        fn set_wrap(&mut self, _coordinate: Coordinate, _wrap: WrapMode) {}
+        fn wrap_mode(&self, _coordinate: Coordinate) -> WrapMode {
+            WrapMode::Repeat
+        }
+        fn set_border_color(&mut self, _color: Color) {}
+        fn set_data(
+            &mut self,
+            kind: GpuTextureKind,
+            _pixel_kind: PixelKind,
+            _mip_count: usize,
+            _data: Option<&[u8]>,
+        ) -> Result<(), FrameworkError> {
+            self.0 = kind;
+            Ok(())
+        }
+        fn get_image(&self, _level: usize) -> Vec<u8> {
+            Vec::new()
+        }
+        fn read_pixels(&self) -> Vec<u8> {
+            Vec::new()
+        }
+        fn kind(&self) -> GpuTextureKind {
+            self.0
+        }
+        fn pixel_kind(&self) -> PixelKind {
+            PixelKind::RGBA8
+        }
+        fn set_base_level(&mut self, _level: usize) {}
+        fn base_level(&self) -> usize {
+            0
+        }
+        fn set_max_level(&mut self, _level: usize) {}
+        fn max_level(&self) -> usize {
+            0
+        }
+        fn set_min_lod(&mut self, _min_lod: f32) {}
+        fn min_lod(&self) -> f32 {
+            0.0
+        }
+        fn set_max_lod(&mut self, _max_lod: f32) {}
+        fn max_lod(&self) -> f32 {
+            0.0
+        }
+        fn set_lod_bias(&mut self, _bias: f32) {}
+        fn lod_bias(&self) -> f32 {
+            0.0
+        }
+    }
+
+    type BlitLog = Rc<RefCell<Vec<((i32, i32), (i32, i32), usize, BlitFilter)>>>;
+
+    /// A frame buffer that records the blits it performs instead of doing them.
+    struct RecordingFrameBuffer {
+        color_attachments: Vec<Attachment>,
+        log: BlitLog,
+    }
+
+    impl RecordingFrameBuffer {
+        fn new(size: usize, log: &BlitLog) -> Self {
+            Self {
+                color_attachments: vec![Attachment {
+                    kind: AttachmentKind::Color,
+                    texture: Rc::new(RefCell::new(SizeTexture(GpuTextureKind::Rectangle {
+                        width: size,
+                        height: size,
+                    }))),
+                }],
+                log: log.clone(),
+            }
+        }
+    }
+
+    impl FrameBuffer for RecordingFrameBuffer {
+        fn color_attachments(&self) -> &[Attachment] {
+            &self.color_attachments
+        }
+        fn depth_attachment(&self) -> Option<&Attachment> {
+            None
+        }
+        fn set_cubemap_face(&mut self, _attachment_index: usize, _face: CubeMapFace) {}
+        fn set_layer(
+            &mut self,
+            _attachment_index: usize,
+            _layer: usize,
+        ) -> Result<(), FrameworkError> {
+            Ok(())
+        }
+        fn blit_to_filtered(
+            &self,
+            _dest: &dyn FrameBuffer,
+            _src_x0: i32,
+            _src_y0: i32,
+            src_x1: i32,
+            src_y1: i32,
+            _dst_x0: i32,
+            _dst_y0: i32,
+            dst_x1: i32,
+            dst_y1: i32,
+            _copy_color: bool,
+            _copy_depth: bool,
+            _copy_stencil: bool,
+            src_attachment: usize,
+            filter: BlitFilter,
+        ) -> Result<(), FrameworkError> {
+            self.log.borrow_mut().push((
+                (src_x1, src_y1),
+                (dst_x1, dst_y1),
+                src_attachment,
+                filter,
+            ));
+            Ok(())
+        }
+        fn clear_scissored(
+            &mut self,
+            _viewport: Rect<i32>,
+            _scissor_box: Option<ScissorBox>,
+            _color: Option<Color>,
+            _depth: Option<f32>,
+            _stencil: Option<i32>,
+        ) {
+        }
+        fn clear_attachment(
+            &mut self,
+            _index: usize,
+            _viewport: Rect<i32>,
+            _color: Color,
+        ) -> Result<(), FrameworkError> {
+            Ok(())
+        }
+        fn clear_depth_stencil(
+            &mut self,
+            _viewport: Rect<i32>,
+            _depth: Option<f32>,
+            _stencil: Option<i32>,
+        ) -> Result<(), FrameworkError> {
+            Ok(())
+        }
+        fn read_pixels(
+            &self,
+            _attachment_index: usize,
+            _rect: Rect<i32>,
+            _out: &mut Vec<u8>,
+        ) -> Result<PixelKind, FrameworkError> {
+            Ok(PixelKind::RGBA8)
+        }
+        fn invalidate(&mut self, _attachments: &[usize], _depth: bool, _stencil: bool) {}
+        fn draw(
+            &mut self,
+            _geometry: &dyn GeometryBuffer,
+            _viewport: Rect<i32>,
+            _program: &dyn GpuProgram,
+            _params: &DrawParameters,
+            _resources: &[ResourceBindGroup],
+            _element_range: ElementRange,
+        ) -> Result<DrawCallStatistics, FrameworkError> {
+            Ok(Default::default())
+        }
+        fn draw_instances(
+            &mut self,
+            _count: usize,
+            _geometry: &dyn GeometryBuffer,
+            _viewport: Rect<i32>,
+            _program: &dyn GpuProgram,
+            _params: &DrawParameters,
+            _resources: &[ResourceBindGroup],
+        ) -> DrawCallStatistics {
+            Default::default()
+        }
+    }
+
+    #[test]
+    fn test_blit_downsample_chain() {
+        let log = BlitLog::default();
+        let mut source = RecordingFrameBuffer::new(64, &log);
+        source.color_attachments.push(Attachment {
+            kind: AttachmentKind::Color,
+            texture: Rc::new(RefCell::new(SizeTexture(GpuTextureKind::Rectangle {
+                width: 128,
+                height: 128,
+            }))),
+        });
+        let levels = [32, 16, 8].map(|size| RecordingFrameBuffer::new(size, &log));
+        let levels = levels
+            .iter()
+            .map(|level| level as &dyn FrameBuffer)
+            .collect::<Vec<_>>();
+
+        assert_eq!(blit_downsample_chain(&source, 1, &levels).unwrap(), 3);
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                ((128, 128), (32, 32), 1, BlitFilter::Linear),
+                ((32, 32), (16, 16), 0, BlitFilter::Linear),
+                ((16, 16), (8, 8), 0, BlitFilter::Linear),
+            ]
+        );
+
+        log.borrow_mut().clear();
+        assert_eq!(blit_downsample_chain(&source, 0, &[]).unwrap(), 0);
+        assert!(matches!(
+            blit_downsample_chain(&source, 2, &levels),
+            Err(FrameworkError::InvalidAttachmentIndex { index: 2, count: 2 })
+        ));
+        assert!(log.borrow().is_empty());
+    }
+}
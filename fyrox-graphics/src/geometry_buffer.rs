@@ -59,6 +59,21 @@ pub struct DrawCallStatistics {
     pub triangles: usize,
 }
 
+impl std::ops::AddAssign for DrawCallStatistics {
+    fn add_assign(&mut self, rhs: Self) {
+        self.triangles += rhs.triangles;
+    }
+}
+
+impl std::ops::Add for DrawCallStatistics {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
 pub struct VertexBufferData<'a> {
     pub element_size: usize,
     pub bytes: Option<&'a [u8]>,
@@ -23,8 +23,8 @@ use crate::{
     core::{color::Color, math::Rect},
     error::FrameworkError,
     framebuffer::{
-        Attachment, AttachmentKind, BufferDataUsage, BufferLocation, FrameBuffer,
-        ResourceBindGroup, ResourceBinding, TextureShaderLocation,
+        validate_attachments, Attachment, AttachmentKind, BlitFilter, BufferDataUsage,
+        BufferLocation, FrameBuffer, ResourceBindGroup, ResourceBinding, TextureShaderLocation,
     },
     geometry_buffer::{DrawCallStatistics, GeometryBuffer},
     gl::{
@@ -32,10 +32,12 @@ use crate::{
         server::GlGraphicsServer, texture::GlTexture, ToGlConstant,
     },
     gpu_program::GpuProgram,
-    gpu_texture::{CubeMapFace, GpuTexture, GpuTextureKind, PixelElementKind},
-    ColorMask, DrawParameters, ElementRange,
+    gpu_texture::{
+        image_2d_size_bytes, CubeMapFace, GpuTexture, GpuTextureKind, PixelElementKind, PixelKind,
+    },
+    ColorMask, DrawParameters, ElementRange, ScissorBox,
 };
-use glow::HasContext;
+use glow::{HasContext, PixelPackData};
 use std::rc::Weak;
 
 pub struct GlFrameBuffer {
@@ -86,12 +88,90 @@ unsafe fn set_attachment(server: &GlGraphicsServer, gl_attachment_kind: u32, tex
     }
 }
 
+unsafe fn clear_color_attachment(
+    server: &GlGraphicsServer,
+    index: usize,
+    attachment: &Attachment,
+    color: Color,
+) {
+    match attachment.texture.borrow().pixel_kind().element_kind() {
+        PixelElementKind::Float | PixelElementKind::NormalizedUnsignedInteger => {
+            let fvalues = color.as_frgba();
+            server
+                .gl
+                .clear_buffer_f32_slice(glow::COLOR, index as u32, &fvalues.data.0[0])
+        }
+        PixelElementKind::Integer => {
+            let values = [
+                color.r as i32,
+                color.g as i32,
+                color.b as i32,
+                color.a as i32,
+            ];
+            server
+                .gl
+                .clear_buffer_i32_slice(glow::COLOR, index as u32, &values);
+        }
+        PixelElementKind::UnsignedInteger => {
+            let values = [
+                color.r as u32,
+                color.g as u32,
+                color.b as u32,
+                color.a as u32,
+            ];
+            server
+                .gl
+                .clear_buffer_u32_slice(glow::COLOR, index as u32, &values);
+        }
+    }
+}
+
+unsafe fn clear_depth_stencil_attachment(
+    server: &GlGraphicsServer,
+    depth_stencil: &Attachment,
+    depth: Option<f32>,
+    stencil: Option<i32>,
+) {
+    server.set_depth_write(true);
+    server.set_stencil_mask(0xFFFF_FFFF);
+
+    match depth_stencil.kind {
+        AttachmentKind::Color => unreachable!("depth cannot be color!"),
+        AttachmentKind::DepthStencil => match (depth, stencil) {
+            (Some(depth), Some(stencil)) => {
+                server
+                    .gl
+                    .clear_buffer_depth_stencil(glow::DEPTH_STENCIL, 0, depth, stencil);
+            }
+            (Some(depth), None) => {
+                let values = [depth];
+                server.gl.clear_buffer_f32_slice(glow::DEPTH, 0, &values);
+            }
+            (None, Some(stencil)) => {
+                let values = [stencil];
+                server.gl.clear_buffer_i32_slice(glow::STENCIL, 0, &values);
+            }
+            (None, None) => {
+                // Nothing to do
+            }
+        },
+        AttachmentKind::Depth => {
+            if let Some(depth) = depth {
+                let values = [depth];
+                server.gl.clear_buffer_f32_slice(glow::DEPTH, 0, &values);
+            }
+        }
+    }
+}
+
 impl GlFrameBuffer {
     pub fn new(
         server: &GlGraphicsServer,
         depth_attachment: Option<Attachment>,
         color_attachments: Vec<Attachment>,
     ) -> Result<Self, FrameworkError> {
+        validate_attachments(depth_attachment.as_ref(), &color_attachments)?;
+
         unsafe {
             let fbo = server.gl.create_framebuffer()?;
 
@@ -184,7 +264,49 @@ impl FrameBuffer for GlFrameBuffer {
         }
     }
 
-    fn blit_to(
+    fn set_layer(&mut self, attachment_index: usize, layer: usize) -> Result<(), FrameworkError> {
+        let attachment = self.color_attachments.get(attachment_index).ok_or(
+            FrameworkError::InvalidAttachmentIndex {
+                index: attachment_index,
+                count: self.color_attachments.len(),
+            },
+        )?;
+
+        let guard = attachment.texture.borrow();
+        let texture = guard.as_any().downcast_ref::<GlTexture>().unwrap();
+
+        let GpuTextureKind::Volume { depth, .. } = texture.kind() else {
+            return Err(FrameworkError::InvalidAttachment {
+                color_index: Some(attachment_index),
+                reason: "the texture is not layered".to_string(),
+            });
+        };
+
+        if layer >= depth {
+            return Err(FrameworkError::InvalidAttachment {
+                color_index: Some(attachment_index),
+                reason: format!("layer {layer} is out of bounds, the texture has {depth} layers"),
+            });
+        }
+
+        let server = self.state.upgrade().unwrap();
+
+        unsafe {
+            server.set_framebuffer(self.fbo);
+
+            server.gl.framebuffer_texture_layer(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0 + attachment_index as u32,
+                Some(texture.id()),
+                0,
+                layer as i32,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn blit_to_filtered(
         &self,
         dest: &dyn FrameBuffer,
         src_x0: i32,
@@ -198,12 +320,22 @@ impl FrameBuffer for GlFrameBuffer {
         copy_color: bool,
         copy_depth: bool,
         copy_stencil: bool,
-    ) {
+        src_attachment: usize,
+        filter: BlitFilter,
+    ) -> Result<(), FrameworkError> {
         let server = self.state.upgrade().unwrap();
 
         let source = self;
         let dest = dest.as_any().downcast_ref::<GlFrameBuffer>().unwrap();
 
+        // The back buffer has no attachments, its color buffer is always the source.
+        if copy_color && source.id().is_some() && src_attachment >= source.color_attachments.len() {
+            return Err(FrameworkError::InvalidAttachmentIndex {
+                index: src_attachment,
+                count: source.color_attachments.len(),
+            });
+        }
+
         let mut mask = 0;
         if copy_color {
             mask |= glow::COLOR_BUFFER_BIT;
@@ -215,6 +347,16 @@ impl FrameBuffer for GlFrameBuffer {
             mask |= glow::STENCIL_BUFFER_BIT;
         }
 
+        // Linear filtering is invalid for depth and stencil data.
+        let filter = if copy_depth || copy_stencil {
+            glow::NEAREST
+        } else {
+            match filter {
+                BlitFilter::Nearest => glow::NEAREST,
+                BlitFilter::Linear => glow::LINEAR,
+            }
+        };
+
         unsafe {
             server
                 .gl
@@ -222,31 +364,41 @@ impl FrameBuffer for GlFrameBuffer {
             server
                 .gl
                 .bind_framebuffer(glow::DRAW_FRAMEBUFFER, dest.id());
+            // Read buffer selection is a part of the frame buffer state, so it must be reset
+            // back to the first attachment to not affect other reads from the frame buffer.
+            let select_attachment = copy_color && src_attachment != 0 && source.id().is_some();
+            if select_attachment {
+                server
+                    .gl
+                    .read_buffer(glow::COLOR_ATTACHMENT0 + src_attachment as u32);
+            }
             server.gl.blit_framebuffer(
-                src_x0,
-                src_y0,
-                src_x1,
-                src_y1,
-                dst_x0,
-                dst_y0,
-                dst_x1,
-                dst_y1,
-                mask,
-                glow::NEAREST,
+                src_x0, src_y0, src_x1, src_y1, dst_x0, dst_y0, dst_x1, dst_y1, mask, filter,
             );
+            if select_attachment {
+                server.gl.read_buffer(glow::COLOR_ATTACHMENT0);
+            }
         }
+
+        Ok(())
     }
 
-    fn clear(
+    fn clear_scissored(
         &mut self,
         viewport: Rect<i32>,
+        scissor_box: Option<ScissorBox>,
         color: Option<Color>,
         depth: Option<f32>,
         stencil: Option<i32>,
     ) {
         let server = self.state.upgrade().unwrap();
 
-        server.set_scissor_test(false);
+        if let Some(scissor_box) = scissor_box.as_ref() {
+            server.set_scissor_test(true);
+            server.set_scissor_box(scissor_box);
+        } else {
+            server.set_scissor_test(false);
+        }
         server.set_viewport(viewport);
         server.set_framebuffer(self.id());
 
@@ -276,79 +428,192 @@ impl FrameBuffer for GlFrameBuffer {
 
             // Custom routes for specific frame buffer attachments.
             if let Some(depth_stencil) = self.depth_attachment.as_ref() {
-                server.set_depth_write(true);
-                server.set_stencil_mask(0xFFFF_FFFF);
+                clear_depth_stencil_attachment(&server, depth_stencil, depth, stencil);
+            }
+
+            if let Some(color) = color {
+                server.set_color_write(ColorMask::default());
+
+                for (i, attachment) in self.color_attachments.iter().enumerate() {
+                    clear_color_attachment(&server, i, attachment, color);
+                }
+            }
+        }
+
+        server.set_scissor_test(false);
+    }
+
+    fn clear_attachment(
+        &mut self,
+        index: usize,
+        viewport: Rect<i32>,
+        color: Color,
+    ) -> Result<(), FrameworkError> {
+        let attachment =
+            self.color_attachments
+                .get(index)
+                .ok_or(FrameworkError::InvalidAttachmentIndex {
+                    index,
+                    count: self.color_attachments.len(),
+                })?;
 
+        let server = self.state.upgrade().unwrap();
+
+        server.set_scissor_test(false);
+        server.set_viewport(viewport);
+        server.set_framebuffer(self.id());
+        server.set_color_write(ColorMask::default());
+
+        unsafe { clear_color_attachment(&server, index, attachment, color) }
+
+        Ok(())
+    }
+
+    fn clear_depth_stencil(
+        &mut self,
+        viewport: Rect<i32>,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    ) -> Result<(), FrameworkError> {
+        // Back buffer has its own depth/stencil buffer, that is cleared using the generic route.
+        if self.fbo.is_none() {
+            self.clear(viewport, None, depth, stencil);
+            return Ok(());
+        }
+
+        let depth_stencil = self.depth_attachment.as_ref().ok_or_else(|| {
+            FrameworkError::Custom("The frame buffer has no depth/stencil attachment!".to_string())
+        })?;
+
+        let server = self.state.upgrade().unwrap();
+
+        server.set_scissor_test(false);
+        server.set_viewport(viewport);
+        server.set_framebuffer(self.id());
+
+        unsafe { clear_depth_stencil_attachment(&server, depth_stencil, depth, stencil) }
+
+        Ok(())
+    }
+
+    fn read_pixels(
+        &self,
+        attachment_index: usize,
+        rect: Rect<i32>,
+        out: &mut Vec<u8>,
+    ) -> Result<PixelKind, FrameworkError> {
+        let attachment = self.color_attachments.get(attachment_index).ok_or(
+            FrameworkError::InvalidAttachmentIndex {
+                index: attachment_index,
+                count: self.color_attachments.len(),
+            },
+        )?;
+
+        if rect.size.x < 0 || rect.size.y < 0 {
+            return Err(FrameworkError::Custom(format!(
+                "Invalid read back rectangle size {}x{}!",
+                rect.size.x, rect.size.y
+            )));
+        }
+
+        let pixel_kind = attachment.texture.borrow().pixel_kind();
+        let pixel_descriptor = pixel_kind.pixel_descriptor();
+        // Some formats are stored in a packed form, read them as is to match the size of the
+        // pixel kind.
+        let data_type = match pixel_kind {
+            PixelKind::R16F => glow::HALF_FLOAT,
+            PixelKind::R11G11B10F => glow::UNSIGNED_INT_10F_11F_11F_REV,
+            _ => pixel_descriptor.data_type,
+        };
+
+        let start = out.len();
+        out.resize(
+            start + image_2d_size_bytes(pixel_kind, rect.size.x as usize, rect.size.y as usize),
+            0,
+        );
+
+        let server = self.state.upgrade().unwrap();
+
+        unsafe {
+            server
+                .gl
+                .bind_framebuffer(glow::READ_FRAMEBUFFER, self.id());
+            server
+                .gl
+                .read_buffer(glow::COLOR_ATTACHMENT0 + attachment_index as u32);
+            server.gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+            server.gl.read_pixels(
+                rect.position.x,
+                rect.position.y,
+                rect.size.x,
+                rect.size.y,
+                pixel_descriptor.format,
+                data_type,
+                PixelPackData::Slice(Some(&mut out[start..])),
+            );
+            // Restore the default values to not affect other reads.
+            server.gl.pixel_store_i32(glow::PACK_ALIGNMENT, 4);
+            server.gl.read_buffer(glow::COLOR_ATTACHMENT0);
+        }
+
+        Ok(pixel_kind)
+    }
+
+    fn invalidate(&mut self, attachments: &[usize], depth: bool, stencil: bool) {
+        let server = self.state.upgrade().unwrap();
+
+        if !server.supports_framebuffer_invalidation() {
+            return;
+        }
+
+        let mut gl_attachments = Vec::with_capacity(attachments.len() + 2);
+        if self.fbo.is_none() {
+            // Default frame buffer uses special names for its attachments.
+            if !attachments.is_empty() {
+                gl_attachments.push(glow::COLOR);
+            }
+            if depth {
+                gl_attachments.push(glow::DEPTH);
+            }
+            if stencil {
+                gl_attachments.push(glow::STENCIL);
+            }
+        } else {
+            for &index in attachments {
+                if index < self.color_attachments.len() {
+                    gl_attachments.push(glow::COLOR_ATTACHMENT0 + index as u32);
+                }
+            }
+            if let Some(depth_stencil) = self.depth_attachment.as_ref() {
                 match depth_stencil.kind {
                     AttachmentKind::Color => unreachable!("depth cannot be color!"),
                     AttachmentKind::DepthStencil => match (depth, stencil) {
-                        (Some(depth), Some(stencil)) => {
-                            server.gl.clear_buffer_depth_stencil(
-                                glow::DEPTH_STENCIL,
-                                0,
-                                depth,
-                                stencil,
-                            );
-                        }
-                        (Some(depth), None) => {
-                            let values = [depth];
-                            server.gl.clear_buffer_f32_slice(glow::DEPTH, 0, &values);
-                        }
-                        (None, Some(stencil)) => {
-                            let values = [stencil];
-                            server.gl.clear_buffer_i32_slice(glow::STENCIL, 0, &values);
-                        }
-                        (None, None) => {
+                        (true, true) => gl_attachments.push(glow::DEPTH_STENCIL_ATTACHMENT),
+                        (true, false) => gl_attachments.push(glow::DEPTH_ATTACHMENT),
+                        (false, true) => gl_attachments.push(glow::STENCIL_ATTACHMENT),
+                        (false, false) => {
                             // Nothing to do
                         }
                     },
                     AttachmentKind::Depth => {
-                        if let Some(depth) = depth {
-                            let values = [depth];
-                            server.gl.clear_buffer_f32_slice(glow::DEPTH, 0, &values);
+                        if depth {
+                            gl_attachments.push(glow::DEPTH_ATTACHMENT);
                         }
                     }
                 }
             }
+        }
 
-            if let Some(color) = color {
-                server.set_color_write(ColorMask::default());
+        if gl_attachments.is_empty() {
+            return;
+        }
 
-                for (i, attachment) in self.color_attachments.iter().enumerate() {
-                    match attachment.texture.borrow().pixel_kind().element_kind() {
-                        PixelElementKind::Float | PixelElementKind::NormalizedUnsignedInteger => {
-                            let fvalues = color.as_frgba();
-                            server.gl.clear_buffer_f32_slice(
-                                glow::COLOR,
-                                i as u32,
-                                &fvalues.data.0[0],
-                            )
-                        }
-                        PixelElementKind::Integer => {
-                            let values = [
-                                color.r as i32,
-                                color.g as i32,
-                                color.b as i32,
-                                color.a as i32,
-                            ];
-                            server
-                                .gl
-                                .clear_buffer_i32_slice(glow::COLOR, i as u32, &values);
-                        }
-                        PixelElementKind::UnsignedInteger => {
-                            let values = [
-                                color.r as u32,
-                                color.g as u32,
-                                color.b as u32,
-                                color.a as u32,
-                            ];
-                            server
-                                .gl
-                                .clear_buffer_u32_slice(glow::COLOR, i as u32, &values);
-                        }
-                    }
-                }
-            }
+        server.set_framebuffer(self.id());
+
+        unsafe {
+            server
+                .gl
+                .invalidate_framebuffer(glow::FRAMEBUFFER, &gl_attachments);
         }
     }
 
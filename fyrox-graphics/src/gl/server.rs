@@ -36,8 +36,8 @@ use crate::{
     server::{GraphicsServer, ServerCapabilities, SharedGraphicsServer},
     stats::PipelineStatistics,
     BlendEquation, BlendFactor, BlendFunc, BlendMode, ColorMask, CompareFunc, CullFace,
-    DrawParameters, PolygonFace, PolygonFillMode, ScissorBox, StencilAction, StencilFunc,
-    StencilOp,
+    DrawParameters, PolygonFace, PolygonFillMode, PolygonOffset, ScissorBox, StencilAction,
+    StencilFunc, StencilOp,
 };
 use glow::HasContext;
 #[cfg(not(target_arch = "wasm32"))]
@@ -161,6 +161,9 @@ impl ToGlConstant for CullFace {
     }
 }
 
+// From GL_NV_conservative_raster, glow does not have this constant.
+const CONSERVATIVE_RASTERIZATION_NV: u32 = 0x9346;
+
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum GlKind {
     OpenGL,
@@ -183,6 +186,9 @@ pub(crate) struct InnerState {
     clear_stencil: i32,
     clear_depth: f32,
     scissor_test: bool,
+    polygon_offset: Option<PolygonOffset>,
+    depth_clamp: bool,
+    conservative_rasterization: bool,
 
     polygon_face: PolygonFace,
     polygon_fill_mode: PolygonFillMode,
@@ -232,6 +238,9 @@ impl InnerState {
             clear_stencil: 0,
             clear_depth: 1.0,
             scissor_test: false,
+            polygon_offset: None,
+            depth_clamp: false,
+            conservative_rasterization: false,
             polygon_face: Default::default(),
             polygon_fill_mode: Default::default(),
             framebuffer: None,
@@ -583,6 +592,27 @@ impl GlGraphicsServer {
         self.state.borrow().gl_kind
     }
 
+    /// Checks whether the video driver supports `glInvalidateFramebuffer`. It is a part of the
+    /// core profile since OpenGL 4.3 and OpenGL ES 3.0.
+    pub fn supports_framebuffer_invalidation(&self) -> bool {
+        let version = self.gl.version();
+        if version.is_embedded {
+            version.major >= 3
+        } else {
+            (version.major, version.minor) >= (4, 3)
+                || self
+                    .gl
+                    .supported_extensions()
+                    .contains("GL_ARB_invalidate_subdata")
+        }
+    }
+
+    pub fn supports_conservative_rasterization(&self) -> bool {
+        self.gl
+            .supported_extensions()
+            .contains("GL_NV_conservative_raster")
+    }
+
     pub fn free_texture_unit(&self) -> Option<u32> {
         let state = self.state.borrow();
         for (index, unit) in state.texture_units_storage.units.iter().enumerate() {
@@ -921,6 +951,43 @@ impl GlGraphicsServer {
         }
     }
 
+    pub(crate) fn set_polygon_offset(&self, polygon_offset: Option<PolygonOffset>) {
+        let mut state = self.state.borrow_mut();
+        if state.polygon_offset != polygon_offset {
+            unsafe {
+                match polygon_offset {
+                    Some(offset) => {
+                        if state.polygon_offset.is_none() {
+                            self.gl.enable(glow::POLYGON_OFFSET_FILL);
+                        }
+                        self.gl.polygon_offset(offset.factor, offset.units);
+                    }
+                    None => {
+                        self.gl.disable(glow::POLYGON_OFFSET_FILL);
+                    }
+                }
+            }
+
+            state.polygon_offset = polygon_offset;
+        }
+    }
+
+    pub(crate) fn set_depth_clamp(&self, depth_clamp: bool) {
+        let mut state = self.state.borrow_mut();
+        // Depth clamping is not supported on OpenGL ES.
+        if state.depth_clamp != depth_clamp && state.gl_kind == GlKind::OpenGL {
+            state.depth_clamp = depth_clamp;
+
+            unsafe {
+                if depth_clamp {
+                    self.gl.enable(glow::DEPTH_CLAMP);
+                } else {
+                    self.gl.disable(glow::DEPTH_CLAMP);
+                }
+            }
+        }
+    }
+
     pub(crate) fn set_scissor_box(&self, scissor_box: &ScissorBox) {
         unsafe {
             self.gl.scissor(
@@ -942,6 +1009,8 @@ impl GlGraphicsServer {
             blend,
             stencil_op,
             scissor_box,
+            polygon_offset,
+            depth_clamp,
         } = draw_params;
 
         if let Some(ref blend_params) = blend {
@@ -984,6 +1053,9 @@ impl GlGraphicsServer {
         } else {
             self.set_scissor_test(false);
         }
+
+        self.set_polygon_offset(*polygon_offset);
+        self.set_depth_clamp(*depth_clamp);
     }
 }
 
@@ -1169,4 +1241,21 @@ impl GraphicsServer for GlGraphicsServer {
             }
         }
     }
+
+    fn set_conservative_rasterization(&self, enabled: bool) {
+        let mut state = self.state.borrow_mut();
+        if state.conservative_rasterization != enabled
+            && (!enabled || self.supports_conservative_rasterization())
+        {
+            state.conservative_rasterization = enabled;
+
+            unsafe {
+                if enabled {
+                    self.gl.enable(CONSERVATIVE_RASTERIZATION_NV);
+                } else {
+                    self.gl.disable(CONSERVATIVE_RASTERIZATION_NV);
+                }
+            }
+        }
+    }
 }
@@ -239,7 +239,7 @@ float S_SpotShadowFactor(
     float shadowBias,
     vec3 fragmentPosition,
     mat4 lightViewProjMatrix,
-    float shadowMapInvSize,
+    vec2 shadowMapInvSize,
 in sampler2D spotShadowTexture)
 {
     if (shadowsEnabled)
@@ -275,6 +275,20 @@ in sampler2D spotShadowTexture)
     }
 }
 
+// The same as above, but for square shadow maps.
+float S_SpotShadowFactor(
+    bool shadowsEnabled,
+    bool softShadows,
+    float shadowBias,
+    vec3 fragmentPosition,
+    mat4 lightViewProjMatrix,
+    float shadowMapInvSize,
+in sampler2D spotShadowTexture)
+{
+    return S_SpotShadowFactor(shadowsEnabled, softShadows, shadowBias, fragmentPosition,
+        lightViewProjMatrix, vec2(shadowMapInvSize), spotShadowTexture);
+}
+
 float Internal_FetchHeight(in sampler2D heightTexture, vec2 texCoords, float center) {
     return clamp(texture(heightTexture, texCoords).r - center, 0.0, 1.0);
 }
@@ -44,6 +44,18 @@ pub enum GpuTextureKind {
     },
 }
 
+impl GpuTextureKind {
+    /// Returns width and height of a single 2D slice of the texture.
+    pub fn surface_size(&self) -> (usize, usize) {
+        match *self {
+            Self::Line { length } => (length, 1),
+            Self::Rectangle { width, height }
+            | Self::Cube { width, height }
+            | Self::Volume { width, height, .. } => (width, height),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum PixelKind {
     R32F,
@@ -168,6 +180,19 @@ impl PixelKind {
         }
     }
 
+    pub fn is_depth(self) -> bool {
+        matches!(self, Self::D16 | Self::D32F | Self::D24S8)
+    }
+
+    pub fn has_stencil(self) -> bool {
+        matches!(self, Self::D24S8)
+    }
+
+    /// Returns `true` if the pixel kind could be used for color attachments of a frame buffer.
+    pub fn is_color_renderable(self) -> bool {
+        !self.is_compressed() && !self.is_depth()
+    }
+
     pub fn element_kind(self) -> PixelElementKind {
         match self {
             Self::R32F
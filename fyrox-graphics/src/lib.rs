@@ -486,6 +486,29 @@ pub struct ScissorBox {
     pub height: i32,
 }
 
+/// Depth offset that is applied to polygons, it is used to fight depth fighting (z-fighting) in
+/// shadow maps, decals, etc. The offset is calculated as `factor * DZ + units * r`, where `DZ` is
+/// the depth slope of a polygon and `r` is the smallest value that guaranteed to produce a resolvable
+/// offset for the depth buffer.
+#[derive(Serialize, Deserialize, Default, Visit, Debug, Clone, Copy, Reflect)]
+pub struct PolygonOffset {
+    /// Scale factor for the depth slope of a polygon.
+    pub factor: f32,
+    /// Constant offset in the units of the depth buffer.
+    pub units: f32,
+}
+
+// Offsets are compared bitwise, so the comparison is an equivalence relation (even for NaN) and
+// the offset can be a part of [`DrawParameters`], which must be [`Eq`].
+impl PartialEq for PolygonOffset {
+    fn eq(&self, other: &Self) -> bool {
+        self.factor.to_bits() == other.factor.to_bits()
+            && self.units.to_bits() == other.units.to_bits()
+    }
+}
+
+impl Eq for PolygonOffset {}
+
 /// A set of drawing parameters, that are used during draw call. It defines pretty much all pipeline
 /// settings all at once.
 #[derive(Serialize, Deserialize, Visit, Debug, PartialEq, Clone, Eq, Reflect)]
@@ -506,6 +529,16 @@ pub struct DrawParameters {
     pub stencil_op: StencilOp,
     /// Optional scissor box. If [`None`], then the scissor test is disabled.
     pub scissor_box: Option<ScissorBox>,
+    /// Optional polygon offset (depth bias). If [`None`], then the polygon offset is disabled.
+    #[serde(default)]
+    #[visit(optional)]
+    pub polygon_offset: Option<PolygonOffset>,
+    /// A flag, that defines whether the depth of the fragments should be clamped to the depth
+    /// range instead of clipping the geometry by the near and far planes. Not supported on
+    /// OpenGL ES and WebGL, where the flag is ignored.
+    #[serde(default)]
+    #[visit(optional)]
+    pub depth_clamp: bool,
 }
 
 impl Default for DrawParameters {
@@ -519,6 +552,8 @@ impl Default for DrawParameters {
             blend: None,
             stencil_op: Default::default(),
             scissor_box: None,
+            polygon_offset: None,
+            depth_clamp: false,
         }
     }
 }
@@ -166,6 +166,12 @@ pub trait GraphicsServer: Downcast {
     /// Sets current polygon fill mode. See [`PolygonFace`] and [`PolygonFillMode`] docs for more info.
     fn set_polygon_fill_mode(&self, polygon_face: PolygonFace, polygon_fill_mode: PolygonFillMode);
 
+    /// Enables or disables conservative rasterization. When enabled, every pixel that is touched
+    /// by a primitive is rasterized, even if the primitive does not cover the pixel's center.
+    /// This is a hardware-dependent feature, the call is ignored if the feature is not supported.
+    /// The default implementation does nothing.
+    fn set_conservative_rasterization(&self, _enabled: bool) {}
+
     /// A shortcut for [`Self::create_texture`], that creates a rectangular texture with the given
     /// size and pixel kind.
     fn create_2d_render_target(
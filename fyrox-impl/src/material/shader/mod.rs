@@ -136,7 +136,16 @@
 //!                     y: 20,
 //!                     width: 100,
 //!                     height: 30
-//!                 ))
+//!                 )),
+//!
+//!                 // Optional depth bias, could be omitted.
+//!                 polygon_offset: Some(PolygonOffset(
+//!                     factor: 1.0,
+//!                     units: 1.0
+//!                 )),
+//!
+//!                 // Clamp depth instead of clipping by near and far planes, could be omitted.
+//!                 depth_clamp: false
 //!             ),
 //!
 //!             // Vertex shader code.
@@ -138,6 +138,8 @@ impl GaussianBlur {
                 blend: None,
                 stencil_op: Default::default(),
                 scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
             },
             &[ResourceBindGroup {
                 bindings: &[
@@ -174,6 +176,8 @@ impl GaussianBlur {
                 blend: None,
                 stencil_op: Default::default(),
                 scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
             },
             &[ResourceBindGroup {
                 bindings: &[
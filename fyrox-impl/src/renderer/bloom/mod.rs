@@ -127,6 +127,8 @@ impl BloomRenderer {
                 blend: None,
                 stencil_op: Default::default(),
                 scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
             },
             &[ResourceBindGroup {
                 bindings: &[
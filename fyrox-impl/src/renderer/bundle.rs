@@ -54,7 +54,7 @@ use crate::{
             server::GraphicsServer,
             uniform::StaticUniformBuffer,
             uniform::{ByteStorage, UniformBuffer},
-            ElementRange,
+            DrawParameters, ElementRange, PolygonOffset,
         },
         FallbackResources, LightData, RenderPassStatistics,
     },
@@ -102,6 +102,43 @@ pub struct ObserverInfo {
     pub view_matrix: Matrix4<f32>,
     /// Projection matrix of the observer.
     pub projection_matrix: Matrix4<f32>,
+    /// Viewport (in pixels) of the observer. It is used to get the actual aspect ratio of the
+    /// rendering target, which could differ from the one that was used to build the projection
+    /// matrix.
+    pub viewport: Rect<i32>,
+}
+
+impl ObserverInfo {
+    /// Returns aspect ratio of the observer's viewport. Degenerate viewports (with zero height)
+    /// have the aspect ratio of 1.0.
+    pub fn aspect_ratio(&self) -> f32 {
+        if self.viewport.h() > 0 {
+            self.viewport.w() as f32 / self.viewport.h() as f32
+        } else {
+            1.0
+        }
+    }
+
+    /// Builds a frustum of the observer using its view and projection matrices. If the aspect
+    /// ratio of the viewport is different from the aspect ratio that was used to build the
+    /// projection matrix, then the horizontal extent of the frustum is widened to cover the entire
+    /// viewport. This prevents objects near the edges of the screen from being culled.
+    pub fn frustum(&self) -> Frustum {
+        let mut projection_matrix = self.projection_matrix;
+        let x_scale = projection_matrix[(0, 0)];
+        let y_scale = projection_matrix[(1, 1)];
+        if self.viewport.w() > 0 && self.viewport.h() > 0 && x_scale != 0.0 {
+            // Both perspective and orthographic projection matrices store the horizontal scale as
+            // the vertical scale divided by the aspect ratio.
+            let projection_aspect = y_scale / x_scale;
+            let viewport_aspect = self.aspect_ratio();
+            if viewport_aspect > projection_aspect {
+                projection_matrix[(0, 0)] = y_scale / viewport_aspect;
+            }
+        }
+        Frustum::from_view_projection_matrix(projection_matrix * self.view_matrix)
+            .unwrap_or_default()
+    }
 }
 
 /// Render context is used to collect render data from the scene nodes. It provides all required information about
@@ -157,6 +194,9 @@ pub struct BundleRenderContext<'a> {
     // renderer to have access to depth buffer that is available from G-Buffer.
     pub scene_depth: Option<&'a Rc<RefCell<dyn GpuTexture>>>,
     pub fallback_resources: &'a FallbackResources,
+    /// Optional polygon offset that overrides the one from the draw parameters of render passes.
+    /// It is used to apply depth bias in shadow passes.
+    pub polygon_offset: Option<PolygonOffset>,
 }
 
 /// A set of data of a surface for rendering.
@@ -625,11 +665,23 @@ impl RenderDataBundle {
                 };
             }
 
+            let overridden_draw_params;
+            let draw_params = match render_context.polygon_offset {
+                Some(polygon_offset) => {
+                    overridden_draw_params = DrawParameters {
+                        polygon_offset: Some(polygon_offset),
+                        ..render_pass.draw_params.clone()
+                    };
+                    &overridden_draw_params
+                }
+                None => &render_pass.draw_params,
+            };
+
             stats += render_context.frame_buffer.draw(
                 geometry,
                 render_context.viewport,
                 &*render_pass.program,
-                &render_pass.draw_params,
+                draw_params,
                 &[
                     ResourceBindGroup {
                         bindings: &material_bindings,
@@ -777,10 +829,7 @@ impl RenderDataBundleStorage {
             light_sources: Default::default(),
         };
 
-        let frustum = Frustum::from_view_projection_matrix(
-            observer_info.projection_matrix * observer_info.view_matrix,
-        )
-        .unwrap_or_default();
+        let frustum = observer_info.frustum();
 
         let mut lod_filter = vec![true; graph.capacity() as usize];
         for (node_handle, node) in graph.pair_iter() {
@@ -184,6 +184,8 @@ impl DebugRenderer {
                 blend: None,
                 stencil_op: Default::default(),
                 scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
             },
             &[ResourceBindGroup {
                 bindings: &[ResourceBinding::Buffer {
@@ -99,6 +99,7 @@ impl ForwardRenderer {
                 fallback_resources,
                 ambient_light,
                 scene_depth: Some(&scene_depth),
+                polygon_offset: None,
             },
         )?;
 
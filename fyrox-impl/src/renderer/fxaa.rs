@@ -102,6 +102,8 @@ impl FxaaRenderer {
                 blend: None,
                 stencil_op: Default::default(),
                 scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
             },
             &[ResourceBindGroup {
                 bindings: &[
@@ -109,10 +109,11 @@ impl GBuffer {
     ) -> Result<Self, FrameworkError> {
         let diffuse_texture = server.create_2d_render_target(PixelKind::RGBA8, width, height)?;
         let normal_texture = server.create_2d_render_target(PixelKind::RGBA8, width, height)?;
+        let depth_stencil = server.create_2d_render_target(PixelKind::D24S8, width, height)?;
         let framebuffer = server.create_frame_buffer(
             Some(Attachment {
                 kind: AttachmentKind::DepthStencil,
-                texture: server.create_2d_render_target(PixelKind::D24S8, width, height)?,
+                texture: depth_stencil.clone(),
             }),
             vec![
                 Attachment {
@@ -164,7 +165,9 @@ impl GBuffer {
             )?,
             decal_framebuffer,
             render_pass_name: ImmutableString::new("GBuffer"),
-            occlusion_tester: OcclusionTester::new(server, width, height, 16)?,
+            // Occlusion tests are done right after the G-Buffer is filled, so the tester can use
+            // its depth directly.
+            occlusion_tester: OcclusionTester::new(server, Some(depth_stencil), width, height, 16)?,
         })
     }
 
@@ -259,6 +262,7 @@ impl GBuffer {
                 fallback_resources,
                 ambient_light: Color::WHITE, // TODO
                 scene_depth: None,           // TODO. Add z-pre-pass.
+                polygon_offset: None,
             },
         )?;
 
@@ -275,7 +279,7 @@ impl GBuffer {
                 None,
                 unit_quad,
                 objects.iter(),
-                &*self.framebuffer,
+                None,
                 camera.global_position(),
                 view_projection,
                 uniform_buffer_cache,
@@ -325,6 +329,8 @@ impl GBuffer {
                     }),
                     stencil_op: Default::default(),
                     scissor_box: None,
+                    polygon_offset: None,
+                    depth_clamp: false,
                 },
                 &[ResourceBindGroup {
                     bindings: &[
@@ -172,6 +172,8 @@ impl HighDynamicRangeRenderer {
                 blend: None,
                 stencil_op: Default::default(),
                 scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
             },
             &[ResourceBindGroup {
                 bindings: &[
@@ -270,6 +272,8 @@ impl HighDynamicRangeRenderer {
                             blend: None,
                             stencil_op: Default::default(),
                             scissor_box: None,
+                            polygon_offset: None,
+                            depth_clamp: false,
                         },
                         &[ResourceBindGroup {
                             bindings: &[
@@ -321,6 +325,8 @@ impl HighDynamicRangeRenderer {
                 blend: None,
                 stencil_op: Default::default(),
                 scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
             },
             &[ResourceBindGroup {
                 bindings: &[
@@ -401,6 +407,8 @@ impl HighDynamicRangeRenderer {
                 blend: None,
                 stencil_op: Default::default(),
                 scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
             },
             &[ResourceBindGroup {
                 bindings: &[
@@ -23,7 +23,9 @@ use crate::{
     core::{
         algebra::{Matrix4, Point3, UnitQuaternion, Vector2, Vector3},
         color::Color,
-        math::{frustum::Frustum, Matrix4Ext, Rect, TriangleDefinition},
+        math::{
+            aabb::AxisAlignedBoundingBox, frustum::Frustum, Matrix4Ext, Rect, TriangleDefinition,
+        },
     },
     renderer::{
         bundle::{LightSourceKind, RenderDataBundleStorage},
@@ -54,7 +56,7 @@ use crate::{
         },
         skybox_shader::SkyboxShader,
         ssao::ScreenSpaceAmbientOcclusionRenderer,
-        visibility::ObserverVisibilityCache,
+        visibility::{ObserverVisibilityCache, OcclusionProxyRenderer},
         FallbackResources, GeometryCache, LightingStatistics, QualitySettings,
         RenderPassStatistics, TextureCache,
     },
@@ -68,6 +70,7 @@ use crate::{
         Scene,
     },
 };
+use fxhash::FxHashSet;
 use fyrox_graphics::framebuffer::BufferLocation;
 
 pub mod ambient;
@@ -91,6 +94,7 @@ pub struct DeferredLightRenderer {
     point_shadow_map_renderer: PointShadowMapRenderer,
     csm_renderer: CsmRenderer,
     light_volume: LightVolumeRenderer,
+    occlusion_proxy_renderer: OcclusionProxyRenderer,
 }
 
 pub(crate) struct DeferredRendererContext<'a> {
@@ -210,6 +214,8 @@ impl DeferredLightRenderer {
                 server,
                 settings.spot_shadow_map_size,
                 quality_defaults.spot_shadow_map_precision,
+                quality_defaults.spot_shadow_map_cascade_count,
+                quality_defaults.spot_shadow_map_atlas,
             )?,
             point_shadow_map_renderer: PointShadowMapRenderer::new(
                 server,
@@ -217,6 +223,7 @@ impl DeferredLightRenderer {
                 quality_defaults.point_shadow_map_precision,
             )?,
             light_volume: LightVolumeRenderer::new(server)?,
+            occlusion_proxy_renderer: OcclusionProxyRenderer::new(server)?,
             csm_renderer: CsmRenderer::new(
                 server,
                 quality_defaults.csm_settings.size,
@@ -231,13 +238,23 @@ impl DeferredLightRenderer {
         settings: &QualitySettings,
     ) -> Result<(), FrameworkError> {
         if settings.spot_shadow_map_size != self.spot_shadow_map_renderer.base_size()
-            || settings.spot_shadow_map_precision != self.spot_shadow_map_renderer.precision()
+            || settings.spot_shadow_map_cascade_count.max(1)
+                != self.spot_shadow_map_renderer.cascade_count()
+            || settings.spot_shadow_map_atlas != self.spot_shadow_map_renderer.is_atlas()
         {
+            let depth_bias = self.spot_shadow_map_renderer.depth_bias();
             self.spot_shadow_map_renderer = SpotShadowMapRenderer::new(
                 server,
                 settings.spot_shadow_map_size,
                 settings.spot_shadow_map_precision,
+                settings.spot_shadow_map_cascade_count,
+                settings.spot_shadow_map_atlas,
             )?;
+            self.spot_shadow_map_renderer
+                .set_depth_bias(depth_bias.units, depth_bias.factor);
+        } else if settings.spot_shadow_map_precision != self.spot_shadow_map_renderer.precision() {
+            self.spot_shadow_map_renderer
+                .set_precision(server, settings.spot_shadow_map_precision)?;
         }
         if settings.point_shadow_map_size != self.point_shadow_map_renderer.base_size()
             || settings.point_shadow_map_precision != self.point_shadow_map_renderer.precision()
@@ -274,6 +291,18 @@ impl DeferredLightRenderer {
         Ok(())
     }
 
+    pub fn spot_shadow_map_renderer(&self) -> &SpotShadowMapRenderer {
+        &self.spot_shadow_map_renderer
+    }
+
+    pub fn spot_shadow_map_renderer_mut(&mut self) -> &mut SpotShadowMapRenderer {
+        &mut self.spot_shadow_map_renderer
+    }
+
+    pub fn point_shadow_map_renderer(&self) -> &PointShadowMapRenderer {
+        &self.point_shadow_map_renderer
+    }
+
     pub(crate) fn render(
         &mut self,
         args: DeferredRendererContext,
@@ -346,6 +375,8 @@ impl DeferredLightRenderer {
                         blend: None,
                         stencil_op: Default::default(),
                         scissor_box: None,
+                        polygon_offset: None,
+                        depth_clamp: false,
                     },
                     &[ResourceBindGroup {
                         bindings: &[
@@ -394,6 +425,8 @@ impl DeferredLightRenderer {
                 }),
                 stencil_op: Default::default(),
                 scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
             },
             &[ResourceBindGroup {
                 bindings: &[
@@ -429,6 +462,45 @@ impl DeferredLightRenderer {
             ElementRange::Full,
         )?;
 
+        // Query the visibility of the light sources with no information about it in a single batch,
+        // using the bounding boxes of the light volumes as the proxies. The lights, for which the
+        // batch is ambiguous, will be queried separately below.
+        let mut batched_lights = FxHashSet::default();
+        if settings.use_light_occlusion_culling {
+            let mut proxies = Vec::new();
+            for light in render_data_bundle.light_sources.iter() {
+                let raw_radius = match light.kind {
+                    LightSourceKind::Spot { distance, .. } => distance,
+                    LightSourceKind::Point { radius, .. } => radius,
+                    LightSourceKind::Directional { .. } | LightSourceKind::Unknown => continue,
+                };
+                let scl = light.local_scale;
+                let light_radius = scl.x.max(scl.y).max(scl.z) * raw_radius;
+                let aabb = AxisAlignedBoundingBox::from_min_max(
+                    light.position - Vector3::repeat(light_radius),
+                    light.position + Vector3::repeat(light_radius),
+                );
+                // Back faces of the proxies are culled, so the camera must be outside of them.
+                if frustum.is_intersects_sphere(light.position, light_radius)
+                    && !aabb.is_contains_point(camera_global_position)
+                    && visibility_cache.needs_batch_query(camera_global_position, light.handle)
+                {
+                    proxies.push((light.handle, aabb));
+                    batched_lights.insert(light.handle);
+                }
+            }
+
+            pass_stats += visibility_cache.run_query_batch(
+                server,
+                &mut self.occlusion_proxy_renderer,
+                frame_buffer,
+                viewport,
+                camera_global_position,
+                view_projection,
+                &proxies,
+            )?;
+        }
+
         for light in render_data_bundle.light_sources.iter() {
             let distance_to_camera = (light.position - camera.global_position()).norm();
 
@@ -523,6 +595,9 @@ impl DeferredLightRenderer {
             } else {
                 2
             };
+            // Spot shadow maps could have less cascades than point ones.
+            let spot_cascade_index =
+                cascade_index.min(self.spot_shadow_map_renderer.cascade_count() - 1);
 
             let left_boundary = (shadows_distance - shadows_fade_out_range).max(0.0);
             let shadows_alpha = if distance_to_camera <= left_boundary {
@@ -538,6 +613,18 @@ impl DeferredLightRenderer {
                 StaticUniformBuffer::<256>::new().with(&(view_projection * bounding_shape_matrix)),
             )?;
 
+            // The stencil mask is also used as the proxy for the occlusion query below, so
+            // rasterize it conservatively if requested. This could only grow the lit area by
+            // a pixel at the edges.
+            let conservative_rasterization = visibility_cache.conservative_rasterization()
+                && settings.use_light_occlusion_culling
+                && !matches!(light.kind, LightSourceKind::Directional { .. })
+                && !batched_lights.contains(&light.handle)
+                && visibility_cache.needs_occlusion_query(camera_global_position, light.handle);
+            if conservative_rasterization {
+                server.set_conservative_rasterization(true);
+            }
+
             for (cull_face, stencil_action) in [
                 (CullFace::Front, StencilAction::Incr),
                 (CullFace::Back, StencilAction::Decr),
@@ -561,6 +648,8 @@ impl DeferredLightRenderer {
                         depth_test: Some(CompareFunc::Less),
                         blend: None,
                         scissor_box: None,
+                        polygon_offset: None,
+                        depth_clamp: false,
                     },
                     &[ResourceBindGroup {
                         bindings: &[ResourceBinding::Buffer {
@@ -575,6 +664,10 @@ impl DeferredLightRenderer {
                 )?;
             }
 
+            if conservative_rasterization {
+                server.set_conservative_rasterization(false);
+            }
+
             // Directional light sources cannot be optimized via occlusion culling, because they're
             // usually cover the entire screen anyway. TODO: This might still be optimizable, but
             // for now we'll skip it, since this optimization could be useful only for scenes with
@@ -583,14 +676,16 @@ impl DeferredLightRenderer {
             if !matches!(light.kind, LightSourceKind::Directional { .. })
                 && settings.use_light_occlusion_culling
             {
-                if visibility_cache.needs_occlusion_query(camera_global_position, light.handle) {
+                if !batched_lights.contains(&light.handle)
+                    && visibility_cache.needs_occlusion_query(camera_global_position, light.handle)
+                {
                     // Draw full screen quad, that will be used to count pixels that passed the stencil test
                     // on the stencil buffer's content generated by two previous drawing commands.
                     let uniform_buffer = uniform_buffer_cache
                         .write(StaticUniformBuffer::<256>::new().with(&frame_matrix))?;
 
                     visibility_cache.begin_query(server, camera_global_position, light.handle)?;
-                    frame_buffer.draw(
+                    pass_stats += frame_buffer.draw(
                         &*self.quad,
                         viewport,
                         &*self.flat_shader.program,
@@ -606,6 +701,8 @@ impl DeferredLightRenderer {
                             blend: None,
                             stencil_op: Default::default(),
                             scissor_box: None,
+                            polygon_offset: None,
+                            depth_clamp: false,
                         },
                         &[ResourceBindGroup {
                             bindings: &[ResourceBinding::Buffer {
@@ -649,9 +746,17 @@ impl DeferredLightRenderer {
                             &light_up_vec,
                         );
 
-                        light_view_projection = light_projection_matrix * light_view_matrix;
+                        // The matrix is used for sampling, so it must point to the area of the
+                        // cascade in the atlas (if any).
+                        light_view_projection = self
+                            .spot_shadow_map_renderer
+                            .cascade_atlas_matrix(spot_cascade_index)?
+                            * light_projection_matrix
+                            * light_view_matrix;
 
-                        pass_stats += self.spot_shadow_map_renderer.render(
+                        // Shadow bounds are not used yet, but could be used to skip shadow
+                        // casters outside of them on the next frame.
+                        let (spot_shadow_stats, _bounds) = self.spot_shadow_map_renderer.render(
                             server,
                             &scene.graph,
                             elapsed_time,
@@ -661,12 +766,14 @@ impl DeferredLightRenderer {
                             z_far,
                             light_projection_matrix,
                             geometry_cache,
-                            cascade_index,
+                            spot_cascade_index,
                             shader_cache,
                             textures,
                             fallback_resources,
                             uniform_memory_allocator,
+                            None,
                         )?;
+                        pass_stats += spot_shadow_stats;
 
                         light_stats.spot_shadow_maps_rendered += 1;
                     }
@@ -729,6 +836,8 @@ impl DeferredLightRenderer {
                         ..Default::default()
                     }),
                     scissor_box: None,
+                    polygon_offset: None,
+                    depth_clamp: false,
                 };
 
                 let quad = &self.quad;
@@ -756,8 +865,9 @@ impl DeferredLightRenderer {
 
                         light_stats.spot_lights_rendered += 1;
 
-                        let inv_size = 1.0
-                            / (self.spot_shadow_map_renderer.cascade_size(cascade_index) as f32);
+                        let inv_size = self
+                            .spot_shadow_map_renderer
+                            .cascade_texel_size(spot_cascade_index)?;
                         let uniform_buffer = uniform_buffer_cache.write(
                             StaticUniformBuffer::<1024>::new()
                                 .with(&frame_matrix)
@@ -805,7 +915,7 @@ impl DeferredLightRenderer {
                                     ResourceBinding::texture(
                                         &self
                                             .spot_shadow_map_renderer
-                                            .cascade_texture(cascade_index),
+                                            .cascade_texture(spot_cascade_index)?,
                                         &shader.spot_shadow_texture,
                                     ),
                                     ResourceBinding::texture(
@@ -934,6 +1044,8 @@ impl DeferredLightRenderer {
                                 }),
                                 stencil_op: Default::default(),
                                 scissor_box: None,
+                                polygon_offset: None,
+                                depth_clamp: false,
                             },
                             &[ResourceBindGroup {
                                 bindings: &[
@@ -193,6 +193,8 @@ impl LightVolumeRenderer {
                             write_mask: 0xFFFF_FFFF,
                         },
                         scissor_box: None,
+                        polygon_offset: None,
+                        depth_clamp: false,
                     },
                     &[ResourceBindGroup {
                         bindings: &[ResourceBinding::Buffer {
@@ -235,6 +237,8 @@ impl LightVolumeRenderer {
                             ..Default::default()
                         },
                         scissor_box: None,
+                        polygon_offset: None,
+                        depth_clamp: false,
                     },
                     &[ResourceBindGroup {
                         bindings: &[
@@ -297,6 +301,8 @@ impl LightVolumeRenderer {
                             write_mask: 0xFFFF_FFFF,
                         },
                         scissor_box: None,
+                        polygon_offset: None,
+                        depth_clamp: false,
                     },
                     &[ResourceBindGroup {
                         bindings: &[ResourceBinding::Buffer {
@@ -338,6 +344,8 @@ impl LightVolumeRenderer {
                             ..Default::default()
                         },
                         scissor_box: None,
+                        polygon_offset: None,
+                        depth_clamp: false,
                     },
                     &[ResourceBindGroup {
                         bindings: &[
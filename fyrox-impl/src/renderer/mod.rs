@@ -92,6 +92,7 @@ use crate::{
             server::{GraphicsServer, SharedGraphicsServer},
             uniform::StaticUniformBuffer,
             DrawParameters, ElementRange, GeometryBufferExt, PolygonFace, PolygonFillMode,
+            PolygonOffset,
         },
         fxaa::FxaaRenderer,
         gbuffer::{GBuffer, GBufferRenderContext},
@@ -106,6 +107,7 @@ use crate::{
 use fxhash::FxHashMap;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+pub use shadow::spot::SpotShadowBounds;
 pub use stats::*;
 use std::{any::TypeId, cell::RefCell, collections::hash_map::Entry, rc::Rc, sync::mpsc::Receiver};
 use strum_macros::{AsRefStr, EnumString, VariantNames};
@@ -218,6 +220,16 @@ pub struct QualitySettings {
     /// Specifies the distance from the camera at which spot shadows start to fade out.
     /// Shadows beyond this distance will gradually become less visible.
     pub spot_shadows_fade_out_range: f32,
+    /// Amount of spot shadow map cascades. Each next cascade is two times smaller than the
+    /// previous one and is used for lights that are farther from the camera. Use smaller values
+    /// to save video memory. Values less than one are treated as one.
+    #[serde(default = "default_spot_shadow_map_cascade_count")]
+    pub spot_shadow_map_cascade_count: usize,
+    /// Whether to pack all spot shadow map cascades into a single texture (atlas) or not. The
+    /// atlas requires less frame buffer switches, but the soft shadows may slightly bleed on the
+    /// edges of the cascades.
+    #[serde(default)]
+    pub spot_shadow_map_atlas: bool,
 
     /// Cascaded-shadow maps settings.
     pub csm_settings: CsmSettings,
@@ -252,6 +264,10 @@ pub struct QualitySettings {
     pub use_light_occlusion_culling: bool,
 }
 
+fn default_spot_shadow_map_cascade_count() -> usize {
+    3
+}
+
 impl Default for QualitySettings {
     fn default() -> Self {
         Self::high()
@@ -273,6 +289,8 @@ impl QualitySettings {
             spot_shadows_enabled: true,
             spot_soft_shadows: true,
             spot_shadows_fade_out_range: 1.0,
+            spot_shadow_map_cascade_count: 3,
+            spot_shadow_map_atlas: false,
 
             use_ssao: true,
             ssao_radius: 0.5,
@@ -309,6 +327,8 @@ impl QualitySettings {
             spot_shadows_enabled: true,
             spot_soft_shadows: true,
             spot_shadows_fade_out_range: 1.0,
+            spot_shadow_map_cascade_count: 3,
+            spot_shadow_map_atlas: false,
 
             use_ssao: true,
             ssao_radius: 0.5,
@@ -350,6 +370,8 @@ impl QualitySettings {
             spot_shadows_enabled: true,
             spot_soft_shadows: false,
             spot_shadows_fade_out_range: 1.0,
+            spot_shadow_map_cascade_count: 3,
+            spot_shadow_map_atlas: false,
 
             use_ssao: true,
             ssao_radius: 0.5,
@@ -391,6 +413,8 @@ impl QualitySettings {
             spot_shadows_enabled: false,
             spot_soft_shadows: false,
             spot_shadows_fade_out_range: 1.0,
+            spot_shadow_map_cascade_count: 1,
+            spot_shadow_map_atlas: false,
 
             use_ssao: false,
             ssao_radius: 0.5,
@@ -571,7 +595,7 @@ impl AssociatedSceneData {
         })
     }
 
-    fn copy_depth_stencil_to_scene_framebuffer(&mut self) {
+    fn copy_depth_stencil_to_scene_framebuffer(&mut self) -> Result<(), FrameworkError> {
         self.gbuffer.framebuffer().blit_to(
             &*self.hdr_scene_framebuffer,
             0,
@@ -585,7 +609,8 @@ impl AssociatedSceneData {
             false,
             true,
             true,
-        );
+            0,
+        )
     }
 
     /// Returns high-dynamic range frame buffer texture.
@@ -872,6 +897,8 @@ fn blit_pixels(
             blend: None,
             stencil_op: Default::default(),
             scissor_box: None,
+            polygon_offset: None,
+            depth_clamp: false,
         },
         &[ResourceBindGroup {
             bindings: &[
@@ -1147,6 +1174,65 @@ impl Renderer {
         self.quality_settings
     }
 
+    /// Returns the light view-projection (world-to-shadow) matrix of the given spot shadow map
+    /// cascade. The shadow map is shared across spot lights, so the matrix belongs to the spot
+    /// light that was rendered into the cascade last. Use it together with
+    /// [`Self::spot_shadow_cascade_texture`] to sample the shadow map in a custom pass.
+    pub fn spot_shadow_cascade_view_projection(
+        &self,
+        cascade: usize,
+    ) -> Result<Matrix4<f32>, FrameworkError> {
+        self.deferred_light_renderer
+            .spot_shadow_map_renderer()
+            .cascade_view_projection(cascade)
+    }
+
+    /// Returns the area of the given spot shadow map cascade in its depth texture. It covers the
+    /// entire texture, unless [`QualitySettings::spot_shadow_map_atlas`] is enabled.
+    pub fn spot_shadow_cascade_viewport(
+        &self,
+        cascade: usize,
+    ) -> Result<Rect<i32>, FrameworkError> {
+        self.deferred_light_renderer
+            .spot_shadow_map_renderer()
+            .cascade_viewport(cascade)
+    }
+
+    /// Returns the depth texture of the given spot shadow map cascade.
+    pub fn spot_shadow_cascade_texture(
+        &self,
+        cascade: usize,
+    ) -> Result<Rc<RefCell<dyn GpuTexture>>, FrameworkError> {
+        self.deferred_light_renderer
+            .spot_shadow_map_renderer()
+            .cascade_texture(cascade)
+    }
+
+    /// Sets the depth bias that is applied to shadow casters of spot lights. `constant` is an offset
+    /// in the units of the depth buffer, and `slope` is a scale factor for the depth slope of a
+    /// polygon. Increase the values to get rid of shadow acne, decrease them if shadows are
+    /// detached from their casters. Zero bias (default) disables the bias.
+    pub fn set_spot_shadow_depth_bias(&mut self, constant: f32, slope: f32) {
+        self.deferred_light_renderer
+            .spot_shadow_map_renderer_mut()
+            .set_depth_bias(constant, slope)
+    }
+
+    /// Returns the depth bias that is applied to shadow casters of spot lights.
+    pub fn spot_shadow_depth_bias(&self) -> PolygonOffset {
+        self.deferred_light_renderer
+            .spot_shadow_map_renderer()
+            .depth_bias()
+    }
+
+    /// Returns the cube map of the largest point shadow map cascade. The shadow map is shared
+    /// across point lights, so it contains the shadows of the point light that was rendered last.
+    pub fn point_shadow_cube_texture(&self) -> Rc<RefCell<dyn GpuTexture>> {
+        self.deferred_light_renderer
+            .point_shadow_map_renderer()
+            .cube_texture()
+    }
+
     /// Removes all cached GPU data, forces renderer to re-upload data to GPU.
     /// Do not call this method until you absolutely need! It may cause **significant**
     /// performance lag!
@@ -1383,6 +1469,7 @@ impl Renderer {
                     z_far: camera.projection().z_far(),
                     view_matrix: camera.view_matrix(),
                     projection_matrix: camera.projection_matrix(),
+                    viewport,
                 },
                 GBUFFER_PASS_NAME.clone(),
                 RenderDataBundleStorageOptions {
@@ -1414,7 +1501,7 @@ impl Renderer {
 
             server.set_polygon_fill_mode(PolygonFace::FrontAndBack, PolygonFillMode::Fill);
 
-            scene_associated_data.copy_depth_stencil_to_scene_framebuffer();
+            scene_associated_data.copy_depth_stencil_to_scene_framebuffer()?;
 
             scene_associated_data.hdr_scene_framebuffer.clear(
                 viewport,
@@ -1578,6 +1665,13 @@ impl Renderer {
                             uniform_memory_allocator: &mut self.uniform_memory_allocator,
                         })?;
             }
+
+            // Depth and stencil of the scene frame buffers are not needed anymore, they will be
+            // overwritten by the G-Buffer content on the next frame. This allows tiled GPUs to skip
+            // storing them back to memory.
+            scene_associated_data
+                .ldr_scene_framebuffer
+                .invalidate(&[], true, true);
         }
 
         self.visibility_cache.update(graph);
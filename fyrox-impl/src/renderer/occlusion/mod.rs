@@ -59,6 +59,7 @@ use crate::{
     scene::{graph::Graph, mesh::surface::SurfaceData, node::Node},
 };
 use bytemuck::{Pod, Zeroable};
+use fxhash::FxHashMap;
 use fyrox_graphics::framebuffer::BufferLocation;
 use std::{cell::RefCell, rc::Rc};
 
@@ -96,11 +97,14 @@ pub struct OcclusionTester {
     cube: Box<dyn GeometryBuffer>,
     visibility_buffer_optimizer: VisibilityBufferOptimizer,
     matrix_storage: MatrixStorage,
-    objects_to_test: Vec<Handle<Node>>,
+    /// World-space proxy boxes that are tested in the current visibility test, a node could have
+    /// multiple boxes.
+    objects_to_test: Vec<(Handle<Node>, AxisAlignedBoundingBox)>,
     view_projection: Matrix4<f32>,
     observer_position: Vector3<f32>,
     pub grid_cache: GridCache,
     tiles: TileBuffer,
+    shared_depth: bool,
 }
 
 const MAX_BITS: usize = u32::BITS as usize;
@@ -141,22 +145,35 @@ impl TileBuffer {
     }
 }
 
+fn inflated_aabb(mut aabb: AxisAlignedBoundingBox) -> AxisAlignedBoundingBox {
+    aabb.inflate(Vector3::repeat(0.01));
+    aabb
+}
+
 fn inflated_world_aabb(graph: &Graph, object: Handle<Node>) -> Option<AxisAlignedBoundingBox> {
-    let mut aabb = graph
+    graph
         .try_get(object)
-        .map(|node_ref| node_ref.world_bounding_box())?;
-    aabb.inflate(Vector3::repeat(0.01));
-    Some(aabb)
+        .map(|node_ref| inflated_aabb(node_ref.world_bounding_box()))
 }
 
 impl OcclusionTester {
+    /// Creates a new occlusion tester. If `shared_depth` is set, the tester tests against the given
+    /// depth buffer directly instead of keeping its own copy of the scene depth. The depth buffer
+    /// must be filled with the scene depth before [`Self::try_run_visibility_test`] is called,
+    /// the tester never writes to it. This allows to render scene depth once and share it with
+    /// the occlusion queries, which removes a full-screen depth blit per visibility test.
     pub fn new(
         server: &dyn GraphicsServer,
+        shared_depth: Option<Rc<RefCell<dyn GpuTexture>>>,
         width: usize,
         height: usize,
         tile_size: usize,
     ) -> Result<Self, FrameworkError> {
-        let depth_stencil = server.create_2d_render_target(PixelKind::D24S8, width, height)?;
+        let is_depth_shared = shared_depth.is_some();
+        let depth_stencil = match shared_depth {
+            Some(depth_stencil) => depth_stencil,
+            None => server.create_2d_render_target(PixelKind::D24S8, width, height)?,
+        };
         let visibility_mask = server.create_2d_render_target(PixelKind::RGBA8, width, height)?;
         let w_tiles = width / tile_size + 1;
         let h_tiles = height / tile_size + 1;
@@ -193,6 +210,7 @@ impl OcclusionTester {
             observer_position: Default::default(),
             grid_cache: GridCache::new(Vector3::repeat(1)),
             tiles: TileBuffer::new(w_tiles, h_tiles),
+            shared_depth: is_depth_shared,
         })
     }
 
@@ -202,7 +220,11 @@ impl OcclusionTester {
             return;
         };
 
-        let mut objects_visibility = vec![false; self.objects_to_test.len()];
+        let mut objects_visibility = self
+            .objects_to_test
+            .iter()
+            .map(|(_, aabb)| inflated_aabb(*aabb).is_contains_point(self.observer_position))
+            .collect::<Vec<_>>();
         for y in 0..self.h_tiles {
             let img_y = self.h_tiles.saturating_sub(1) - y;
             let tile_offset = y * self.w_tiles;
@@ -223,9 +245,15 @@ impl OcclusionTester {
             }
         }
 
+        // A node is visible if any of its proxy boxes is visible.
+        let mut nodes_visibility = FxHashMap::<Handle<Node>, bool>::default();
+        for ((node, _), vis) in self.objects_to_test.iter().zip(objects_visibility.iter()) {
+            *nodes_visibility.entry(*node).or_default() |= *vis;
+        }
+
         let cell = self.grid_cache.get_or_insert_cell(self.observer_position);
-        for (obj, vis) in self.objects_to_test.iter().zip(objects_visibility.iter()) {
-            cell.mark(*obj, (*vis).into());
+        for (node, vis) in nodes_visibility {
+            cell.mark(node, vis.into());
         }
 
         for (object, visibility) in cell.iter_mut() {
@@ -246,20 +274,14 @@ impl OcclusionTester {
 
     fn prepare_tiles(
         &mut self,
-        graph: &Graph,
         viewport: &Rect<i32>,
         debug_renderer: Option<&mut DebugRenderer>,
     ) -> Result<(), FrameworkError> {
         self.tiles.clear();
 
         let mut lines = Vec::new();
-        for (object_index, object) in self.objects_to_test.iter().enumerate() {
+        for (object_index, (_, aabb)) in self.objects_to_test.iter().enumerate() {
             let object_index = object_index as u32;
-            let Some(node_ref) = graph.try_get(*object) else {
-                continue;
-            };
-
-            let aabb = node_ref.world_bounding_box();
             let rect = aabb.project(&self.view_projection, viewport);
 
             if debug_renderer.is_some() {
@@ -310,54 +332,102 @@ impl OcclusionTester {
         Ok(())
     }
 
-    fn upload_data<'a>(
+    fn upload_data(
         &mut self,
-        graph: &Graph,
-        objects_to_test: impl Iterator<Item = &'a Handle<Node>>,
-        prev_framebuffer: &dyn FrameBuffer,
+        objects_to_test: impl Iterator<Item = (Handle<Node>, AxisAlignedBoundingBox)>,
+        prev_framebuffer: Option<&dyn FrameBuffer>,
         observer_position: Vector3<f32>,
         view_projection: Matrix4<f32>,
-    ) {
+    ) -> Result<(), FrameworkError> {
         self.view_projection = view_projection;
         self.observer_position = observer_position;
-        let w = self.frame_size.x as i32;
-        let h = self.frame_size.y as i32;
-        prev_framebuffer.blit_to(
-            &*self.framebuffer,
-            0,
-            0,
-            w,
-            h,
-            0,
-            0,
-            w,
-            h,
-            false,
-            true,
-            false,
-        );
+        // Shared depth buffer already contains the scene depth, there's nothing to copy.
+        if let Some(prev_framebuffer) = prev_framebuffer.filter(|_| !self.shared_depth) {
+            let w = self.frame_size.x as i32;
+            let h = self.frame_size.y as i32;
+            prev_framebuffer.blit_to(
+                &*self.framebuffer,
+                0,
+                0,
+                w,
+                h,
+                0,
+                0,
+                w,
+                h,
+                false,
+                true,
+                false,
+                0,
+            )?;
+        }
 
         self.objects_to_test.clear();
         if let Some(cell) = self.grid_cache.cell(self.observer_position) {
-            for object in objects_to_test {
-                if cell.needs_occlusion_query(*object) {
-                    self.objects_to_test.push(*object);
+            for (object, aabb) in objects_to_test {
+                if cell.needs_occlusion_query(object) {
+                    self.objects_to_test.push((object, aabb));
                 }
             }
         }
 
-        self.objects_to_test.sort_unstable_by_key(|a| {
-            (graph[*a].global_position().sqr_distance(&observer_position) * 1000.0) as u64
+        self.objects_to_test.sort_unstable_by_key(|(_, aabb)| {
+            (aabb.center().sqr_distance(&observer_position) * 1000.0) as u64
         });
+
+        Ok(())
     }
 
+    /// Tests visibility of the given objects against scene depth. `prev_framebuffer` is the source
+    /// of scene depth, it is ignored if the tester uses a shared depth buffer and may be `None`
+    /// in this case. World-space bounding boxes of the objects are used as occluder proxies, use
+    /// [`Self::try_run_visibility_test_with_bounds`] to supply tighter bounds.
     pub fn try_run_visibility_test<'a>(
         &mut self,
         graph: &Graph,
         debug_renderer: Option<&mut DebugRenderer>,
         unit_quad: &dyn GeometryBuffer,
         objects_to_test: impl Iterator<Item = &'a Handle<Node>>,
-        prev_framebuffer: &dyn FrameBuffer,
+        prev_framebuffer: Option<&dyn FrameBuffer>,
+        observer_position: Vector3<f32>,
+        view_projection: Matrix4<f32>,
+        uniform_buffer_cache: &mut UniformBufferCache,
+    ) -> Result<(), FrameworkError> {
+        self.try_run_visibility_test_with_bounds(
+            debug_renderer,
+            unit_quad,
+            objects_to_test.filter_map(|object| {
+                graph
+                    .try_get(*object)
+                    .map(|node_ref| (*object, node_ref.world_bounding_box()))
+            }),
+            prev_framebuffer,
+            observer_position,
+            view_projection,
+            uniform_buffer_cache,
+        )
+    }
+
+    /// Does the same as [`Self::try_run_visibility_test`], but tests explicit world-space proxy
+    /// boxes instead of the bounding boxes of the nodes. The same node could be listed multiple
+    /// times with different sub-boxes (for example, one box per cluster of a large mesh), the node
+    /// is considered visible if any of its boxes is visible. Visibility info is still cached per
+    /// node.
+    ///
+    /// # Performance
+    ///
+    /// Tighter boxes reduce false "visible" results for large or animated meshes, but every box
+    /// is a separate query that takes a slot in every screen tile it covers, and there are only
+    /// 32 slots per tile. Splitting objects in too many boxes increases the cost of the test and
+    /// may cause the most distant objects to be skipped in crowded tiles. A few boxes per object
+    /// is usually enough.
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_run_visibility_test_with_bounds(
+        &mut self,
+        debug_renderer: Option<&mut DebugRenderer>,
+        unit_quad: &dyn GeometryBuffer,
+        objects_to_test: impl Iterator<Item = (Handle<Node>, AxisAlignedBoundingBox)>,
+        prev_framebuffer: Option<&dyn FrameBuffer>,
         observer_position: Vector3<f32>,
         view_projection: Matrix4<f32>,
         uniform_buffer_cache: &mut UniformBufferCache,
@@ -367,12 +437,11 @@ impl OcclusionTester {
         }
 
         self.upload_data(
-            graph,
             objects_to_test,
             prev_framebuffer,
             observer_position,
             view_projection,
-        );
+        )?;
 
         let w = self.frame_size.x as i32;
         let h = self.frame_size.y as i32;
@@ -381,13 +450,13 @@ impl OcclusionTester {
         self.framebuffer
             .clear(viewport, Some(Color::TRANSPARENT), None, None);
 
-        self.prepare_tiles(graph, &viewport, debug_renderer)?;
+        self.prepare_tiles(&viewport, debug_renderer)?;
 
         self.matrix_storage
-            .upload(self.objects_to_test.iter().filter_map(|h| {
-                let aabb = inflated_world_aabb(graph, *h)?;
+            .upload(self.objects_to_test.iter().map(|(_, aabb)| {
+                let aabb = inflated_aabb(*aabb);
                 let s = aabb.max - aabb.min;
-                Some(Matrix4::new_translation(&aabb.center()) * Matrix4::new_nonuniform_scaling(&s))
+                Matrix4::new_translation(&aabb.center()) * Matrix4::new_nonuniform_scaling(&s)
             }))?;
 
         let shader = &self.shader;
@@ -411,6 +480,8 @@ impl OcclusionTester {
                 }),
                 stencil_op: Default::default(),
                 scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
             },
             &[ResourceBindGroup {
                 bindings: &[
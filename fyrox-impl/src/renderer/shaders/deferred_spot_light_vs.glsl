@@ -12,7 +12,7 @@ layout (std140) uniform Uniforms {
     float lightRadius;
     float halfHotspotConeAngleCos;
     float halfConeAngleCos;
-    float shadowMapInvSize;
+    vec2 shadowMapInvSize;
     float shadowBias;
     float lightIntensity;
     float shadowAlpha;
@@ -0,0 +1,12 @@
+layout (location = 0) in vec3 vertexPosition;
+layout (location = 1) in vec2 vertexTexCoord;
+
+uniform sampler2D matrices;
+
+out vec2 texCoord;
+
+void main()
+{
+    texCoord = vertexTexCoord;
+    gl_Position = S_FetchMatrix(matrices, gl_InstanceID) * vec4(vertexPosition, 1.0);
+}
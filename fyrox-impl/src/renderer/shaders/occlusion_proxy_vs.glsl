@@ -0,0 +1,8 @@
+layout (location = 0) in vec3 vertexPosition;
+
+uniform sampler2D matrices;
+
+void main()
+{
+    gl_Position = S_FetchMatrix(matrices, gl_InstanceID) * vec4(vertexPosition, 1.0);
+}
@@ -259,6 +259,7 @@ impl CsmRenderer {
                     z_far,
                     view_matrix: light_view_matrix,
                     projection_matrix: cascade_projection_matrix,
+                    viewport,
                 },
                 DIRECTIONAL_SHADOW_PASS_NAME.clone(),
                 RenderDataBundleStorageOptions {
@@ -283,6 +284,7 @@ impl CsmRenderer {
                     fallback_resources,
                     ambient_light: Color::WHITE, // TODO
                     scene_depth: None,
+                    polygon_offset: None,
                 },
             )?;
         }
@@ -0,0 +1,176 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Instanced rendering of shadow casters. Bundles that use the standard shader and consist of
+//! plain (not skinned and without blend shapes) instances are drawn with a single draw call per
+//! bundle. Since every bundle shares the same geometry and material, this effectively groups
+//! shadow casters by geometry.
+
+use crate::{
+    core::{math::Rect, ImmutableString},
+    material::{
+        shader::{ShaderResource, ShaderResourceExtension},
+        MaterialResourceBinding,
+    },
+    renderer::{
+        bundle::{RenderDataBundle, RenderDataBundleStorage},
+        cache::texture::TextureCache,
+        framework::{
+            error::FrameworkError,
+            framebuffer::{FrameBuffer, ResourceBindGroup, ResourceBinding},
+            gpu_program::{GpuProgram, UniformLocation},
+            server::GraphicsServer,
+            ColorMask, CompareFunc, CullFace, DrawParameters, ElementRange, PolygonOffset,
+        },
+        storage::MatrixStorage,
+        FallbackResources, GeometryCache, RenderPassStatistics,
+    },
+};
+
+struct Shader {
+    program: Box<dyn GpuProgram>,
+    matrices: UniformLocation,
+    diffuse_texture: UniformLocation,
+}
+
+impl Shader {
+    fn new(server: &dyn GraphicsServer) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("../shaders/instanced_shadow_fs.glsl");
+        let vertex_source = include_str!("../shaders/instanced_shadow_vs.glsl");
+        let program =
+            server.create_program("InstancedShadowShader", vertex_source, fragment_source)?;
+        Ok(Self {
+            matrices: program.uniform_location(&ImmutableString::new("matrices"))?,
+            diffuse_texture: program.uniform_location(&ImmutableString::new("diffuseTexture"))?,
+            program,
+        })
+    }
+}
+
+pub struct InstancedShadowRenderer {
+    shader: Shader,
+    matrix_storage: MatrixStorage,
+    standard_shader: ShaderResource,
+}
+
+impl InstancedShadowRenderer {
+    pub fn new(server: &dyn GraphicsServer) -> Result<Self, FrameworkError> {
+        Ok(Self {
+            shader: Shader::new(server)?,
+            matrix_storage: MatrixStorage::new(server)?,
+            standard_shader: ShaderResource::standard(),
+        })
+    }
+
+    /// Returns `true` if the bundle can be drawn by [`Self::render`]. Only bundles with at least
+    /// two instances are worth instancing, single instances are left for the regular path.
+    pub fn is_instancing_candidate(&self, bundle: &RenderDataBundle) -> bool {
+        bundle.instances.len() > 1
+            && bundle.material.is_ok()
+            && bundle.material.data_ref().shader() == &self.standard_shader
+            && bundle.instances.iter().all(|instance| {
+                instance.bone_matrices.is_empty()
+                    && instance.blend_shapes_weights.is_empty()
+                    && instance.element_range == ElementRange::Full
+            })
+    }
+
+    /// Draws every instancing candidate of the given bundle storage, one draw call per bundle.
+    /// Per-instance world-view-projection matrices are uploaded to a matrix storage texture.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        server: &dyn GraphicsServer,
+        bundle_storage: &RenderDataBundleStorage,
+        geometry_cache: &mut GeometryCache,
+        texture_cache: &mut TextureCache,
+        fallback_resources: &FallbackResources,
+        frame_buffer: &mut dyn FrameBuffer,
+        viewport: Rect<i32>,
+        polygon_offset: Option<PolygonOffset>,
+    ) -> Result<RenderPassStatistics, FrameworkError> {
+        let mut stats = RenderPassStatistics::default();
+
+        let observer_info = &bundle_storage.observer_info;
+        let view_projection = observer_info.projection_matrix * observer_info.view_matrix;
+
+        for bundle in bundle_storage.bundles.iter() {
+            if !self.is_instancing_candidate(bundle) {
+                continue;
+            }
+
+            let diffuse_texture = match bundle
+                .material
+                .data_ref()
+                .binding_ref(ImmutableString::new("diffuseTexture"))
+            {
+                Some(MaterialResourceBinding::Texture(binding)) => binding
+                    .value
+                    .as_ref()
+                    .and_then(|texture| texture_cache.get(server, texture))
+                    .cloned(),
+                _ => None,
+            }
+            .unwrap_or_else(|| fallback_resources.white_dummy.clone());
+
+            let Some(geometry) = geometry_cache.get(server, &bundle.data, bundle.time_to_live)
+            else {
+                continue;
+            };
+
+            self.matrix_storage.upload(
+                bundle
+                    .instances
+                    .iter()
+                    .map(|instance| view_projection * instance.world_transform),
+            )?;
+
+            stats += frame_buffer.draw_instances(
+                bundle.instances.len(),
+                geometry,
+                viewport,
+                &*self.shader.program,
+                &DrawParameters {
+                    cull_face: Some(CullFace::Back),
+                    color_write: ColorMask::all(false),
+                    depth_write: true,
+                    stencil_test: None,
+                    depth_test: Some(CompareFunc::Less),
+                    blend: None,
+                    stencil_op: Default::default(),
+                    scissor_box: None,
+                    polygon_offset,
+                    depth_clamp: false,
+                },
+                &[ResourceBindGroup {
+                    bindings: &[
+                        ResourceBinding::texture(
+                            self.matrix_storage.texture(),
+                            &self.shader.matrices,
+                        ),
+                        ResourceBinding::texture(&diffuse_texture, &self.shader.diffuse_texture),
+                    ],
+                }],
+            );
+        }
+
+        Ok(stats)
+    }
+}
@@ -21,14 +21,14 @@
 #![warn(clippy::too_many_arguments)]
 
 pub mod csm;
+pub mod instanced;
 pub mod point;
 pub mod spot;
 
+/// Each next cascade is two times smaller than the previous one.
 pub fn cascade_size(base_size: usize, cascade: usize) -> usize {
-    match cascade {
-        0 => base_size,
-        1 => (base_size / 2).max(1),
-        2 => (base_size / 4).max(1),
-        _ => unreachable!(),
-    }
+    base_size
+        .checked_shr(cascade as u32)
+        .unwrap_or_default()
+        .max(1)
 }
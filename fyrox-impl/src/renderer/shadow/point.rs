@@ -171,6 +171,14 @@ impl PointShadowMapRenderer {
         self.precision
     }
 
+    /// Returns the cube map of the largest (first) cascade. Unlike the spot shadow maps, the cube
+    /// map is not a depth texture, it stores the distance from the light to the closest shadow
+    /// caster in the [`PixelKind::R16F`] format. Use [`Self::cascade_texture`] to get the cube
+    /// maps of the other cascades.
+    pub fn cube_texture(&self) -> Rc<RefCell<dyn GpuTexture>> {
+        self.cascade_texture(0)
+    }
+
     pub fn cascade_texture(&self, cascade: usize) -> Rc<RefCell<dyn GpuTexture>> {
         self.cascades[cascade].color_attachments()[0]
             .texture
@@ -227,6 +235,7 @@ impl PointShadowMapRenderer {
                     z_far,
                     view_matrix: light_view_matrix,
                     projection_matrix: light_projection_matrix,
+                    viewport,
                 },
                 POINT_SHADOW_PASS_NAME.clone(),
                 RenderDataBundleStorageOptions {
@@ -251,6 +260,7 @@ impl PointShadowMapRenderer {
                     fallback_resources,
                     ambient_light: Color::WHITE, // TODO
                     scene_depth: None,
+                    polygon_offset: None,
                 },
             )?;
         }
@@ -20,9 +20,9 @@
 
 use crate::{
     core::{
-        algebra::{Matrix4, Vector3},
+        algebra::{Matrix4, Vector2, Vector3},
         color::Color,
-        math::Rect,
+        math::{aabb::AxisAlignedBoundingBox, frustum::Frustum, Rect},
     },
     renderer::{
         bundle::{
@@ -35,8 +35,9 @@ use crate::{
             framebuffer::{Attachment, AttachmentKind, FrameBuffer},
             gpu_texture::{GpuTexture, PixelKind},
             server::GraphicsServer,
+            PolygonOffset, ScissorBox,
         },
-        shadow::cascade_size,
+        shadow::{cascade_size, instanced::InstancedShadowRenderer},
         FallbackResources, GeometryCache, RenderPassStatistics, ShadowMapPrecision,
         SPOT_SHADOW_PASS_NAME,
     },
@@ -44,53 +45,155 @@ use crate::{
 };
 use std::{cell::RefCell, rc::Rc};
 
+/// World-space volume that was used to collect shadow casters for a spot light shadow map. Casters
+/// that are entirely outside of it do not affect the shadow map, so they can be skipped the next
+/// time the shadow map is rendered.
+#[derive(Clone, Debug, Default)]
+pub struct SpotShadowBounds {
+    /// Frustum of the light, built from its view and projection matrices.
+    pub frustum: Frustum,
+    /// Axis-aligned bounding box that encloses the frustum.
+    pub aabb: AxisAlignedBoundingBox,
+}
+
+impl SpotShadowBounds {
+    /// Creates the bounds from the view and projection matrices of a light.
+    pub fn from_light_matrices(
+        light_view_matrix: Matrix4<f32>,
+        light_projection_matrix: Matrix4<f32>,
+    ) -> Self {
+        let frustum =
+            Frustum::from_view_projection_matrix(light_projection_matrix * light_view_matrix)
+                .unwrap_or_default();
+        Self {
+            aabb: AxisAlignedBoundingBox::from_points(&frustum.corners()),
+            frustum,
+        }
+    }
+}
+
 pub struct SpotShadowMapRenderer {
     precision: ShadowMapPrecision,
-    // Three "cascades" for various use cases:
+    // A set of "cascades" for various use cases, each next cascade is two times smaller than the
+    // previous one:
     //  0 - largest, for lights close to camera.
     //  1 - medium, for lights with medium distance to camera.
-    //  2 - small, for farthest lights.
-    cascades: [Box<dyn FrameBuffer>; 3],
+    //  2.. - small, for farthest lights.
+    // In atlas mode there's only one frame buffer, that contains all the cascades.
+    cascades: Vec<Box<dyn FrameBuffer>>,
+    // Area of the frame buffer of each cascade.
+    cascade_viewports: Vec<Rect<i32>>,
+    atlas: bool,
+    size: usize,
+    instanced_renderer: InstancedShadowRenderer,
+    // Light view-projection matrix that was used to render each cascade last time.
+    cascade_view_projections: Vec<Matrix4<f32>>,
+    // Depth bias that is applied to shadow casters, zero means no bias.
+    depth_bias: PolygonOffset,
+}
+
+fn make_shadow_map(
+    server: &dyn GraphicsServer,
+    width: usize,
+    height: usize,
+    precision: ShadowMapPrecision,
+) -> Result<Box<dyn FrameBuffer>, FrameworkError> {
+    let depth = server.create_2d_render_target(
+        match precision {
+            ShadowMapPrecision::Full => PixelKind::D32F,
+            ShadowMapPrecision::Half => PixelKind::D16,
+        },
+        width,
+        height,
+    )?;
+
+    server.create_frame_buffer(
+        Some(Attachment {
+            kind: AttachmentKind::Depth,
+            texture: depth,
+        }),
+        vec![],
+    )
+}
+
+// Atlas layout: the first (largest) cascade is placed at the left side of the atlas, the rest
+// of the cascades are stacked in a column to the right of it.
+fn atlas_viewports(size: usize, cascade_count: usize) -> Vec<Rect<i32>> {
+    let mut y = 0;
+    (0..cascade_count)
+        .map(|cascade| {
+            let cascade_size = cascade_size(size, cascade) as i32;
+            if cascade == 0 {
+                Rect::new(0, 0, cascade_size, cascade_size)
+            } else {
+                let viewport = Rect::new(size as i32, y, cascade_size, cascade_size);
+                y += cascade_size;
+                viewport
+            }
+        })
+        .collect()
+}
+
+// Frame buffers of cascades and their viewports.
+type Cascades = (Vec<Box<dyn FrameBuffer>>, Vec<Rect<i32>>);
+
+fn make_cascades(
+    server: &dyn GraphicsServer,
     size: usize,
+    precision: ShadowMapPrecision,
+    cascade_count: usize,
+    atlas: bool,
+) -> Result<Cascades, FrameworkError> {
+    if atlas {
+        let viewports = atlas_viewports(size, cascade_count);
+        let (width, height) = viewports.iter().fold((0, 0), |(width, height), viewport| {
+            (
+                width.max(viewport.x() + viewport.w()),
+                height.max(viewport.y() + viewport.h()),
+            )
+        });
+        let atlas = make_shadow_map(server, width as usize, height as usize, precision)?;
+        Ok((vec![atlas], viewports))
+    } else {
+        (0..cascade_count)
+            .map(|cascade| {
+                let cascade_size = cascade_size(size, cascade);
+                Ok((
+                    make_shadow_map(server, cascade_size, cascade_size, precision)?,
+                    Rect::new(0, 0, cascade_size as i32, cascade_size as i32),
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|cascades| cascades.into_iter().unzip())
+    }
 }
 
 impl SpotShadowMapRenderer {
+    /// Creates a new spot shadow map renderer. If `atlas` is `true`, then all the cascades will be
+    /// packed into a single texture, otherwise each cascade will have its own texture. Use
+    /// [`Self::cascade_viewport`] to find the area of a cascade in the atlas.
     pub fn new(
         server: &dyn GraphicsServer,
         size: usize,
         precision: ShadowMapPrecision,
+        cascade_count: usize,
+        atlas: bool,
     ) -> Result<Self, FrameworkError> {
-        fn make_cascade(
-            server: &dyn GraphicsServer,
-            size: usize,
-            precision: ShadowMapPrecision,
-        ) -> Result<Box<dyn FrameBuffer>, FrameworkError> {
-            let depth = server.create_2d_render_target(
-                match precision {
-                    ShadowMapPrecision::Full => PixelKind::D32F,
-                    ShadowMapPrecision::Half => PixelKind::D16,
-                },
-                size,
-                size,
-            )?;
+        // There must be at least one cascade to render anything.
+        let cascade_count = cascade_count.max(1);
 
-            server.create_frame_buffer(
-                Some(Attachment {
-                    kind: AttachmentKind::Depth,
-                    texture: depth,
-                }),
-                vec![],
-            )
-        }
+        let (cascades, cascade_viewports) =
+            make_cascades(server, size, precision, cascade_count, atlas)?;
 
         Ok(Self {
             precision,
             size,
-            cascades: [
-                make_cascade(server, cascade_size(size, 0), precision)?,
-                make_cascade(server, cascade_size(size, 1), precision)?,
-                make_cascade(server, cascade_size(size, 2), precision)?,
-            ],
+            cascades,
+            cascade_viewports,
+            atlas,
+            instanced_renderer: InstancedShadowRenderer::new(server)?,
+            cascade_view_projections: vec![Matrix4::identity(); cascade_count],
+            depth_bias: Default::default(),
         })
     }
 
@@ -102,16 +205,139 @@ impl SpotShadowMapRenderer {
         self.precision
     }
 
-    pub fn cascade_texture(&self, cascade: usize) -> Rc<RefCell<dyn GpuTexture>> {
-        self.cascades[cascade]
+    /// Changes the precision of the shadow maps. It re-creates the cascades with the same size,
+    /// the old textures are freed, so previously returned cascade textures are no longer used by
+    /// the renderer. The content of the new cascades is undefined until the next render.
+    pub fn set_precision(
+        &mut self,
+        server: &dyn GraphicsServer,
+        precision: ShadowMapPrecision,
+    ) -> Result<(), FrameworkError> {
+        if self.precision != precision {
+            (self.cascades, self.cascade_viewports) = make_cascades(
+                server,
+                self.size,
+                precision,
+                self.cascade_viewports.len(),
+                self.atlas,
+            )?;
+            self.cascade_view_projections.fill(Matrix4::identity());
+            self.precision = precision;
+        }
+        Ok(())
+    }
+
+    /// Sets the depth bias that is applied to shadow casters during the shadow pass. `constant` is
+    /// an offset in the units of the depth buffer, and `slope` is a scale factor for the depth
+    /// slope of a polygon. Increase the values to get rid of shadow acne, decrease them if shadows
+    /// are detached from their casters (peter-panning). Zero bias (default) means that the bias
+    /// from the material render passes is used.
+    pub fn set_depth_bias(&mut self, constant: f32, slope: f32) {
+        self.depth_bias = PolygonOffset {
+            factor: slope,
+            units: constant,
+        };
+    }
+
+    /// Returns current depth bias, see [`Self::set_depth_bias`] for more info.
+    pub fn depth_bias(&self) -> PolygonOffset {
+        self.depth_bias
+    }
+
+    pub fn cascade_count(&self) -> usize {
+        self.cascade_viewports.len()
+    }
+
+    /// Returns `true` if all the cascades are packed into a single texture.
+    pub fn is_atlas(&self) -> bool {
+        self.atlas
+    }
+
+    fn check_cascade(&self, cascade: usize) -> Result<(), FrameworkError> {
+        if cascade < self.cascade_viewports.len() {
+            Ok(())
+        } else {
+            Err(FrameworkError::Custom(format!(
+                "Spot shadow map cascade {cascade} is out of bounds, there are only {} cascades!",
+                self.cascade_viewports.len()
+            )))
+        }
+    }
+
+    fn framebuffer_index(&self, cascade: usize) -> usize {
+        if self.atlas {
+            0
+        } else {
+            cascade
+        }
+    }
+
+    /// Returns the area of the cascade texture (see [`Self::cascade_texture`]) that is occupied by
+    /// the given cascade. Without atlas, the area covers the entire texture.
+    pub fn cascade_viewport(&self, cascade: usize) -> Result<Rect<i32>, FrameworkError> {
+        self.check_cascade(cascade)?;
+        Ok(self.cascade_viewports[cascade])
+    }
+
+    /// Returns a matrix that maps the clip space of the light to the area of the given cascade in
+    /// its texture. Multiply the light view-projection matrix by it to sample the cascade from
+    /// the atlas without any additional UV remapping in a shader. It is identity without atlas.
+    pub fn cascade_atlas_matrix(&self, cascade: usize) -> Result<Matrix4<f32>, FrameworkError> {
+        let viewport = self.cascade_viewport(cascade)?;
+        let (width, height) = self
+            .cascade_texture(cascade)?
+            .borrow()
+            .kind()
+            .surface_size();
+
+        let scale_x = viewport.w() as f32 / width as f32;
+        let scale_y = viewport.h() as f32 / height as f32;
+        let offset_x = viewport.x() as f32 / width as f32;
+        let offset_y = viewport.y() as f32 / height as f32;
+
+        // uv' = offset + uv * scale, where uv = ndc * 0.5 + 0.5. The offset is multiplied by w to
+        // survive the perspective division.
+        #[rustfmt::skip]
+        let matrix = Matrix4::new(
+            scale_x, 0.0, 0.0, 2.0 * offset_x + scale_x - 1.0,
+            0.0, scale_y, 0.0, 2.0 * offset_y + scale_y - 1.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        );
+
+        Ok(matrix)
+    }
+
+    /// Returns the size of a single texel of the texture of the given cascade in texture
+    /// coordinates, for each axis. The axes differ in atlas mode, because the atlas is not square.
+    pub fn cascade_texel_size(&self, cascade: usize) -> Result<Vector2<f32>, FrameworkError> {
+        let (width, height) = self
+            .cascade_texture(cascade)?
+            .borrow()
+            .kind()
+            .surface_size();
+        Ok(Vector2::new(1.0 / width as f32, 1.0 / height as f32))
+    }
+
+    pub fn cascade_texture(
+        &self,
+        cascade: usize,
+    ) -> Result<Rc<RefCell<dyn GpuTexture>>, FrameworkError> {
+        self.check_cascade(cascade)?;
+        Ok(self.cascades[self.framebuffer_index(cascade)]
             .depth_attachment()
             .unwrap()
             .texture
-            .clone()
+            .clone())
     }
 
-    pub fn cascade_size(&self, cascade: usize) -> usize {
-        cascade_size(self.size, cascade)
+    /// Returns the light view-projection matrix (world-to-shadow) that was used to render the
+    /// given cascade the last time. Together with [`Self::cascade_texture`] it allows sampling the
+    /// shadow map in a custom pass. The matrix is identity if the cascade was never rendered. In
+    /// atlas mode, the matrix should be combined with [`Self::cascade_atlas_matrix`] for sampling.
+    pub fn cascade_view_projection(&self, cascade: usize) -> Result<Matrix4<f32>, FrameworkError> {
+        self.check_cascade(cascade)?;
+        Ok(self.cascade_view_projections[cascade])
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -131,37 +357,59 @@ impl SpotShadowMapRenderer {
         texture_cache: &mut TextureCache,
         fallback_resources: &FallbackResources,
         uniform_memory_allocator: &mut UniformMemoryAllocator,
-    ) -> Result<RenderPassStatistics, FrameworkError> {
+        depth_bias: Option<PolygonOffset>,
+    ) -> Result<(RenderPassStatistics, SpotShadowBounds), FrameworkError> {
+        self.check_cascade(cascade)?;
+
+        // Per-call bias has priority over the bias of the renderer.
+        let depth_bias = depth_bias.unwrap_or(self.depth_bias);
+        let polygon_offset = (depth_bias != PolygonOffset::default()).then_some(depth_bias);
+
         let mut statistics = RenderPassStatistics::default();
 
-        let framebuffer = &mut *self.cascades[cascade];
-        let cascade_size = cascade_size(self.size, cascade);
+        let viewport = self.cascade_viewports[cascade];
+        let framebuffer_index = self.framebuffer_index(cascade);
+        let framebuffer = &mut *self.cascades[framebuffer_index];
 
-        let viewport = Rect::new(0, 0, cascade_size as i32, cascade_size as i32);
+        // Other cascades of the atlas must be left intact.
+        let scissor_box = self.atlas.then_some(ScissorBox {
+            x: viewport.x(),
+            y: viewport.y(),
+            width: viewport.w(),
+            height: viewport.h(),
+        });
+        framebuffer.clear_scissored(viewport, scissor_box, None, Some(1.0), None);
 
-        framebuffer.clear(viewport, None, Some(1.0), None);
+        self.cascade_view_projections[cascade] = light_projection_matrix * light_view_matrix;
+        let bounds =
+            SpotShadowBounds::from_light_matrices(light_view_matrix, light_projection_matrix);
 
+        let observer_info = ObserverInfo {
+            observer_position: light_position,
+            z_near,
+            z_far,
+            view_matrix: light_view_matrix,
+            projection_matrix: light_projection_matrix,
+            viewport,
+        };
         let bundle_storage = RenderDataBundleStorage::from_graph(
             graph,
             elapsed_time,
-            ObserverInfo {
-                observer_position: light_position,
-                z_near,
-                z_far,
-                view_matrix: light_view_matrix,
-                projection_matrix: light_projection_matrix,
-            },
+            observer_info,
             SPOT_SHADOW_PASS_NAME.clone(),
             RenderDataBundleStorageOptions {
                 collect_lights: false,
             },
         );
 
+        // Repeated plain meshes are drawn with instancing, everything else goes through the
+        // regular per-instance path.
+        let instanced_renderer = &mut self.instanced_renderer;
         statistics += bundle_storage.render_to_frame_buffer(
             server,
             geom_cache,
             shader_cache,
-            |_| true,
+            |bundle| !instanced_renderer.is_instancing_candidate(bundle),
             |_| true,
             BundleRenderContext {
                 texture_cache,
@@ -174,9 +422,62 @@ impl SpotShadowMapRenderer {
                 fallback_resources,
                 ambient_light: Color::WHITE, // TODO
                 scene_depth: None,
+                polygon_offset,
             },
         )?;
 
-        Ok(statistics)
+        statistics += instanced_renderer.render(
+            server,
+            &bundle_storage,
+            geom_cache,
+            texture_cache,
+            fallback_resources,
+            framebuffer,
+            viewport,
+            polygon_offset,
+        )?;
+
+        Ok((statistics, bounds))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SpotShadowBounds;
+    use crate::core::algebra::{Matrix4, Point3, Vector3};
+
+    #[test]
+    fn test_spot_shadow_bounds_contain_cone() {
+        let position = Vector3::new(1.0, 2.0, 3.0);
+        let direction = Vector3::new(1.0, -1.0, 0.5).normalize();
+        let full_cone_angle = 1.2f32;
+        let radius = 10.0;
+
+        let view = Matrix4::look_at_rh(
+            &Point3::from(position),
+            &Point3::from(position + direction),
+            &Vector3::y(),
+        );
+        let projection = Matrix4::new_perspective(1.0, full_cone_angle, 0.01, radius);
+        let bounds = SpotShadowBounds::from_light_matrices(view, projection);
+
+        let side = direction.cross(&Vector3::y()).normalize();
+        let up = side.cross(&direction);
+        let cone_tan = (full_cone_angle * 0.5).tan();
+        for distance in [0.1, 1.0, 5.0, radius * 0.95] {
+            for i in 0..16 {
+                let angle = i as f32 * std::f32::consts::TAU / 16.0;
+                let rim = (side * angle.cos() + up * angle.sin()) * cone_tan * distance * 0.95;
+                let point = position + direction * distance + rim;
+                assert!(bounds.frustum.is_contains_point(point), "{point:?}");
+                assert!(bounds.aabb.is_contains_point(point), "{point:?}");
+            }
+        }
+
+        // Points behind the light and past its radius are outside.
+        assert!(!bounds.frustum.is_contains_point(position - direction));
+        assert!(!bounds
+            .frustum
+            .is_contains_point(position + direction * radius * 1.1));
     }
 }
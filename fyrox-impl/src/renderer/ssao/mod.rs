@@ -232,6 +232,8 @@ impl ScreenSpaceAmbientOcclusionRenderer {
                 blend: None,
                 stencil_op: Default::default(),
                 scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
             },
             &[ResourceBindGroup {
                 bindings: &[
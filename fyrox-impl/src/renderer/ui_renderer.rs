@@ -245,6 +245,8 @@ impl UiRenderer {
                             ..Default::default()
                         },
                         scissor_box,
+                        polygon_offset: None,
+                        depth_clamp: false,
                     },
                     &[ResourceBindGroup {
                         bindings: &[ResourceBinding::Buffer {
@@ -340,6 +342,8 @@ impl UiRenderer {
                 }),
                 stencil_op: Default::default(),
                 scissor_box,
+                polygon_offset: None,
+                depth_clamp: false,
             };
 
             let solid_color = match cmd.brush {
@@ -21,32 +21,122 @@
 //! Volumetric visibility cache based on occlusion query.
 
 use crate::{
-    core::{algebra::Vector3, pool::Handle},
+    core::{
+        algebra::{Matrix4, Vector3},
+        color::Color,
+        math::{aabb::AxisAlignedBoundingBox, Rect},
+        pool::Handle,
+        visitor::prelude::*,
+        ImmutableString,
+    },
     graph::BaseSceneGraph,
-    renderer::framework::{
-        error::FrameworkError,
-        query::{Query, QueryKind, QueryResult},
-        server::GraphicsServer,
+    renderer::{
+        framework::{
+            buffer::BufferUsage,
+            error::FrameworkError,
+            framebuffer::{FrameBuffer, ResourceBindGroup, ResourceBinding},
+            geometry_buffer::{DrawCallStatistics, GeometryBuffer},
+            gpu_program::{GpuProgram, UniformLocation},
+            query::{Query, QueryKind, QueryResult},
+            server::GraphicsServer,
+            ColorMask, CompareFunc, CullFace, DrawParameters, GeometryBufferExt,
+        },
+        storage::MatrixStorage,
     },
-    scene::{graph::Graph, node::Node},
+    scene::{debug::SceneDrawingContext, graph::Graph, mesh::surface::SurfaceData, node::Node},
 };
 use fxhash::FxHashMap;
+use std::fmt::{Debug, Formatter};
 
 #[derive(Debug)]
 struct PendingQuery {
     query: Box<dyn Query>,
     observer_position: Vector3<f32>,
-    node: Handle<Node>,
+    nodes: Vec<Handle<Node>>,
 }
 
-#[derive(Debug)]
-enum Visibility {
+/// Visibility of an object from a cell of the visibility cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Visit)]
+pub enum Visibility {
+    /// An occlusion query was issued, but its result is not known yet.
+    #[default]
     Undefined,
+    /// The object was occluded.
     Invisible,
+    /// At least one fragment of the object was visible.
     Visible,
 }
 
-type NodeVisibilityMap = FxHashMap<Handle<Node>, Visibility>;
+#[derive(Debug, Default, Clone, Copy, Visit)]
+struct VisibilityEntry {
+    visibility: Visibility,
+    /// Amount of updates since the last query that confirmed the visibility.
+    #[visit(skip)]
+    age: u32,
+    /// `true` if there's an occlusion query for the entry on GPU.
+    #[visit(skip)]
+    query_pending: bool,
+    /// `true` if the last batch query of the entry has found some visible fragments, so the entry
+    /// needs a separate query to find out its visibility.
+    #[visit(skip)]
+    batch_inconclusive: bool,
+}
+
+impl VisibilityEntry {
+    fn merge(self, other: VisibilityEntry) -> VisibilityEntry {
+        VisibilityEntry {
+            visibility: merge_visibility(self.visibility, other.visibility),
+            age: self.age.min(other.age),
+            query_pending: self.query_pending || other.query_pending,
+            batch_inconclusive: self.batch_inconclusive || other.batch_inconclusive,
+        }
+    }
+}
+
+type NodeVisibilityMap = FxHashMap<Handle<Node>, VisibilityEntry>;
+
+/// Describes a change of visibility of an object, see
+/// [`ObserverVisibilityCache::set_diagnostics_callback`].
+#[derive(Debug, Clone)]
+pub struct VisibilityTransition {
+    /// Handle of the object.
+    pub node: Handle<Node>,
+    /// Visibility of the object before the change.
+    pub old: Visibility,
+    /// Visibility of the object after the change.
+    pub new: Visibility,
+    /// Grid-space position of the cell in which the visibility has changed.
+    pub cell: Vector3<i32>,
+}
+
+/// A callback that is called on every visibility change, see
+/// [`ObserverVisibilityCache::set_diagnostics_callback`].
+pub type VisibilityDiagnosticsCallback = Box<dyn FnMut(&VisibilityTransition)>;
+
+struct DiagnosticsCallback(VisibilityDiagnosticsCallback);
+
+impl Debug for DiagnosticsCallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DiagnosticsCallback")
+    }
+}
+
+/// Statistics of a visibility cache of an observer, see [`ObserverVisibilityCache::stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VisibilityStats {
+    /// Amount of grid cells in the cache.
+    pub cells: usize,
+    /// Total amount of visibility records (pairs of a cell and an object) in the cache.
+    pub entries: usize,
+    /// Amount of occlusion queries, that are waiting for the results from GPU.
+    pub pending_queries: usize,
+    /// Amount of records with [`Visibility::Visible`] visibility.
+    pub visible: usize,
+    /// Amount of records with [`Visibility::Invisible`] visibility.
+    pub invisible: usize,
+    /// Amount of records with [`Visibility::Undefined`] visibility.
+    pub undefined: usize,
+}
 
 /// Volumetric visibility cache based on occlusion query.
 #[derive(Debug)]
@@ -55,6 +145,10 @@ pub struct ObserverVisibilityCache {
     pending_queries: Vec<PendingQuery>,
     granularity: Vector3<u32>,
     distance_discard_threshold: f32,
+    observer_position: Vector3<f32>,
+    diagnostics_callback: Option<DiagnosticsCallback>,
+    conservative_rasterization: bool,
+    visibility_decay: Option<u32>,
 }
 
 fn world_to_grid(world_position: Vector3<f32>, granularity: Vector3<u32>) -> Vector3<i32> {
@@ -73,6 +167,16 @@ fn grid_to_world(grid_position: Vector3<i32>, granularity: Vector3<u32>) -> Vect
     )
 }
 
+// Visible objects stay visible (the same as for query results), pending queries are preferred
+// over the invisibility, so the objects will be re-tested.
+fn merge_visibility(a: Visibility, b: Visibility) -> Visibility {
+    match (a, b) {
+        (Visibility::Visible, _) | (_, Visibility::Visible) => Visibility::Visible,
+        (Visibility::Undefined, _) | (_, Visibility::Undefined) => Visibility::Undefined,
+        (Visibility::Invisible, Visibility::Invisible) => Visibility::Invisible,
+    }
+}
+
 impl ObserverVisibilityCache {
     /// Creates new visibility cache with the given granularity and distance discard threshold.
     /// Granularity in means how much the cache should subdivide the world. For example 2 means that
@@ -84,9 +188,132 @@ impl ObserverVisibilityCache {
             pending_queries: Default::default(),
             granularity,
             distance_discard_threshold,
+            observer_position: Default::default(),
+            diagnostics_callback: None,
+            conservative_rasterization: false,
+            visibility_decay: None,
         }
     }
 
+    /// Sets the amount of [`Self::update`] calls after which a visible object turns into
+    /// [`Visibility::Undefined`] state, unless a new occlusion query confirms its visibility. This
+    /// forces a fresh occlusion query for the object, which allows moving objects (or objects
+    /// behind moving occluders) to become invisible again. Undefined objects are still treated as
+    /// visible until the query finishes, so lower values cost more queries, but react faster to
+    /// the changes. `None` (default) means that visible objects stay visible until their cell is
+    /// discarded, which is the cheapest option and works fine for static scenes.
+    pub fn set_visibility_decay(&mut self, updates: Option<u32>) {
+        self.visibility_decay = updates;
+    }
+
+    /// Returns the visibility decay threshold, see [`Self::set_visibility_decay`] for more info.
+    pub fn visibility_decay(&self) -> Option<u32> {
+        self.visibility_decay
+    }
+
+    /// Enables or disables conservative rasterization of the proxy geometry of the occlusion
+    /// queries. With conservative rasterization, a proxy that barely touches a visible pixel
+    /// is counted as visible, which makes the queries more accurate for small and thin objects.
+    /// This is a hardware-dependent feature (it requires `GL_NV_conservative_raster`), standard
+    /// rasterization is used when it is not supported. Disabled by default.
+    pub fn set_conservative_rasterization(&mut self, enabled: bool) {
+        self.conservative_rasterization = enabled;
+    }
+
+    /// Returns `true` if conservative rasterization of the occlusion query proxies is enabled,
+    /// see [`Self::set_conservative_rasterization`] for more info.
+    pub fn conservative_rasterization(&self) -> bool {
+        self.conservative_rasterization
+    }
+
+    /// Sets a callback that will be called from [`Self::update`] every time when visibility of an
+    /// object changes (for example, when an object turns from [`Visibility::Undefined`] into
+    /// [`Visibility::Visible`]). Could be used to log or to visualize the results of occlusion
+    /// queries when occlusion culling misbehaves. There's no overhead when the callback is not set.
+    pub fn set_diagnostics_callback(&mut self, callback: Option<VisibilityDiagnosticsCallback>) {
+        self.diagnostics_callback = callback.map(DiagnosticsCallback);
+    }
+
+    /// Sets new granularity of the cache (see [`Self::new`] for more info). Existing cells are
+    /// re-quantized into the new grid, so the visibility info is preserved and the cells are
+    /// still discarded when they're too far from the observer. When multiple cells end up in the
+    /// same new cell, their visibility info is merged and visible objects stay visible.
+    pub fn set_granularity(&mut self, granularity: Vector3<u32>) {
+        if self.granularity == granularity {
+            return;
+        }
+
+        let old_granularity = self.granularity;
+        self.granularity = granularity;
+
+        let cells = std::mem::take(&mut self.cells);
+        for (grid_position, cell) in cells {
+            let world_position = grid_to_world(grid_position, old_granularity);
+            let new_cell = self
+                .cells
+                .entry(world_to_grid(world_position, granularity))
+                .or_default();
+            for (node, entry) in cell {
+                new_cell
+                    .entry(node)
+                    .and_modify(|existing| *existing = existing.merge(entry))
+                    .or_insert(entry);
+            }
+        }
+    }
+
+    /// Returns current granularity of the cache.
+    pub fn granularity(&self) -> Vector3<u32> {
+        self.granularity
+    }
+
+    /// Sets new distance discard threshold, see [`Self::new`] for more info. Distant cells are
+    /// discarded on the next [`Self::update`] call.
+    pub fn set_distance_discard_threshold(&mut self, threshold: f32) {
+        self.distance_discard_threshold = threshold;
+    }
+
+    /// Returns current distance discard threshold.
+    pub fn distance_discard_threshold(&self) -> f32 {
+        self.distance_discard_threshold
+    }
+
+    /// Removes all the visibility info from the cache. Results of in-flight queries will be
+    /// discarded.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    /// Returns total amount of visibility records (pairs of a cell and an object) in the cache.
+    pub fn len(&self) -> usize {
+        self.cells.values().map(|cell| cell.len()).sum()
+    }
+
+    /// Returns `true` if there's no visibility info in the cache.
+    pub fn is_empty(&self) -> bool {
+        self.cells.values().all(|cell| cell.is_empty())
+    }
+
+    /// Collects statistics of the cache. A large amount of cells usually means that the distance
+    /// discard threshold is too large for the granularity of the cache, and a small share of
+    /// invisible records means that occlusion culling is not effective for the scene.
+    pub fn stats(&self) -> VisibilityStats {
+        let mut stats = VisibilityStats {
+            cells: self.cells.len(),
+            pending_queries: self.pending_queries.len(),
+            ..Default::default()
+        };
+        for entry in self.cells.values().flat_map(|cell| cell.values()) {
+            stats.entries += 1;
+            match entry.visibility {
+                Visibility::Undefined => stats.undefined += 1,
+                Visibility::Invisible => stats.invisible += 1,
+                Visibility::Visible => stats.visible += 1,
+            }
+        }
+        stats
+    }
+
     /// Transforms the given world-space position into internal grid-space position.
     pub fn world_to_grid(&self, world_position: Vector3<f32>) -> Vector3<i32> {
         world_to_grid(world_position, self.granularity)
@@ -101,7 +328,7 @@ impl ObserverVisibilityCache {
         &self,
         observer_position: Vector3<f32>,
         node: Handle<Node>,
-    ) -> Option<&Visibility> {
+    ) -> Option<&VisibilityEntry> {
         let grid_position = self.world_to_grid(observer_position);
 
         self.cells
@@ -109,21 +336,40 @@ impl ObserverVisibilityCache {
             .and_then(|cell| cell.get(&node))
     }
 
+    /// Iterates over the objects recorded in the grid cell of the given observer position, along
+    /// with their visibility from the cell. Returns `None` if there's no such cell in the cache.
+    pub fn cell_entries(
+        &self,
+        observer_position: Vector3<f32>,
+    ) -> Option<impl Iterator<Item = (Handle<Node>, Visibility)> + '_> {
+        let grid_position = self.world_to_grid(observer_position);
+        self.cells
+            .get(&grid_position)
+            .map(|cell| cell.iter().map(|(node, entry)| (*node, entry.visibility)))
+    }
+
+    /// Checks whether the object at the given handle is recorded in the grid cell of the given
+    /// observer position, no matter what its visibility is.
+    pub fn contains(&self, observer_position: Vector3<f32>, node: Handle<Node>) -> bool {
+        self.visibility_info(observer_position, node).is_some()
+    }
+
     /// Checks whether the given object needs an occlusion query for the given observer position.
     pub fn needs_occlusion_query(
         &self,
         observer_position: Vector3<f32>,
         node: Handle<Node>,
     ) -> bool {
-        let Some(visibility) = self.visibility_info(observer_position, node) else {
+        let Some(entry) = self.visibility_info(observer_position, node) else {
             // There's no data about the visibility, so the occlusion query is needed.
             return true;
         };
 
-        match visibility {
+        match entry.visibility {
             Visibility::Undefined => {
-                // There's already an occlusion query on GPU.
-                false
+                // There's either an occlusion query on GPU already, or the visibility has decayed
+                // and must be queried again.
+                !entry.query_pending
             }
             Visibility::Invisible => {
                 // The object could be invisible from one angle at the observer position, but visible
@@ -139,6 +385,17 @@ impl ObserverVisibilityCache {
         }
     }
 
+    /// Checks whether the given object needs an occlusion query for the given observer position,
+    /// and the query could be a part of a batch (see [`Self::run_query_batch`]). It is `false` for
+    /// the objects of a batch that has found some visible fragments, until a separate query (see
+    /// [`Self::begin_query`]) resolves their visibility.
+    pub fn needs_batch_query(&self, observer_position: Vector3<f32>, node: Handle<Node>) -> bool {
+        self.needs_occlusion_query(observer_position, node)
+            && self
+                .visibility_info(observer_position, node)
+                .map_or(true, |entry| !entry.batch_inconclusive)
+    }
+
     /// Checks whether the object at the given handle is visible from the given observer position.
     /// This method returns `true` for non-completed occlusion queries, because occlusion query is
     /// async operation.
@@ -147,7 +404,7 @@ impl ObserverVisibilityCache {
             return false;
         };
 
-        match *visibility_info {
+        match visibility_info.visibility {
             Visibility::Visible
             // Undefined visibility is treated like the object is visible, this is needed because
             // GPU queries are async, and we must still render the object to prevent popping light.
@@ -163,23 +420,117 @@ impl ObserverVisibilityCache {
         server: &dyn GraphicsServer,
         observer_position: Vector3<f32>,
         node: Handle<Node>,
+    ) -> Result<(), FrameworkError> {
+        self.begin_batch_query(server, observer_position, &[node])
+    }
+
+    /// Begins a single visibility query (using occlusion query) for a group of objects from the
+    /// given observer position. Proxies of all the objects should be drawn before [`Self::end_query`]
+    /// call, ideally in a single instanced draw call (see [`Self::run_query_batch`], which does
+    /// everything at once). Since there's only one query, its result is conclusive only when no
+    /// fragment has passed - in this case every object of the group is marked invisible. Otherwise,
+    /// it is unknown which objects are visible, so the objects are treated as visible and will need
+    /// separate queries (see [`Self::needs_batch_query`]), unless there's only one object in the
+    /// group. Batches are the most efficient when the objects are likely to be occluded together,
+    /// for example, when they're located in the same room.
+    pub fn begin_batch_query(
+        &mut self,
+        server: &dyn GraphicsServer,
+        observer_position: Vector3<f32>,
+        nodes: &[Handle<Node>],
     ) -> Result<(), FrameworkError> {
         let query = server.create_query()?;
         query.begin(QueryKind::AnySamplesPassed);
+        self.push_query(query, observer_position, nodes);
+        Ok(())
+    }
+
+    /// Runs a single visibility query for a group of objects from the given observer position,
+    /// drawing the given world-space proxy boxes of all the objects with one instanced draw call.
+    /// See [`Self::begin_batch_query`] for the meaning of the result. The frame buffer must contain
+    /// the scene depth, the boxes are tested against it without writing anything. The observer
+    /// must be outside of every box, because back faces of the boxes are culled. The same node
+    /// could be listed multiple times with different boxes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn run_query_batch(
+        &mut self,
+        server: &dyn GraphicsServer,
+        proxy_renderer: &mut OcclusionProxyRenderer,
+        frame_buffer: &mut dyn FrameBuffer,
+        viewport: Rect<i32>,
+        observer_position: Vector3<f32>,
+        view_projection: Matrix4<f32>,
+        objects: &[(Handle<Node>, AxisAlignedBoundingBox)],
+    ) -> Result<DrawCallStatistics, FrameworkError> {
+        if objects.is_empty() {
+            return Ok(Default::default());
+        }
+
+        let mut nodes = objects.iter().map(|(node, _)| *node).collect::<Vec<_>>();
+        nodes.dedup();
+
+        proxy_renderer
+            .matrix_storage
+            .upload(objects.iter().map(|(_, aabb)| {
+                view_projection
+                    * Matrix4::new_translation(&aabb.center())
+                    * Matrix4::new_nonuniform_scaling(&(aabb.max - aabb.min))
+            }))?;
+
+        self.begin_batch_query(server, observer_position, &nodes)?;
+        let stats = frame_buffer.draw_instances(
+            objects.len(),
+            &*proxy_renderer.cube,
+            viewport,
+            &*proxy_renderer.program,
+            &DrawParameters {
+                cull_face: Some(CullFace::Back),
+                color_write: ColorMask::all(false),
+                depth_write: false,
+                stencil_test: None,
+                depth_test: Some(CompareFunc::LessOrEqual),
+                blend: None,
+                stencil_op: Default::default(),
+                scissor_box: None,
+                polygon_offset: None,
+                depth_clamp: false,
+            },
+            &[ResourceBindGroup {
+                bindings: &[ResourceBinding::texture(
+                    proxy_renderer.matrix_storage.texture(),
+                    &proxy_renderer.matrices,
+                )],
+            }],
+        );
+        self.end_query();
+
+        Ok(stats)
+    }
+
+    fn push_query(
+        &mut self,
+        query: Box<dyn Query>,
+        observer_position: Vector3<f32>,
+        nodes: &[Handle<Node>],
+    ) {
         self.pending_queries.push(PendingQuery {
             query,
             observer_position,
-            node,
+            nodes: nodes.to_vec(),
         });
 
         let grid_position = self.world_to_grid(observer_position);
-        self.cells
-            .entry(grid_position)
-            .or_default()
-            .entry(node)
-            .or_insert(Visibility::Undefined);
-
-        Ok(())
+        let cell = self.cells.entry(grid_position).or_default();
+        for node in nodes {
+            cell.entry(*node)
+                .or_insert(VisibilityEntry {
+                    visibility: Visibility::Undefined,
+                    age: 0,
+                    query_pending: false,
+                    batch_inconclusive: false,
+                })
+                .query_pending = true;
+        }
     }
 
     /// Ends the last visibility query.
@@ -193,6 +544,8 @@ impl ObserverVisibilityCache {
 
     /// This method removes info about too distant objects and processes the pending visibility queries.
     pub fn update(&mut self, observer_position: Vector3<f32>) {
+        self.observer_position = observer_position;
+
         self.pending_queries.retain_mut(|pending_query| {
             if let Some(QueryResult::AnySamplesPassed(query_result)) =
                 pending_query.query.try_get_result()
@@ -204,28 +557,61 @@ impl ObserverVisibilityCache {
                     return false;
                 };
 
-                let Some(visibility) = cell.get_mut(&pending_query.node) else {
-                    return false;
-                };
-
-                match visibility {
-                    Visibility::Undefined => match query_result {
-                        true => {
-                            *visibility = Visibility::Visible;
-                        }
-                        false => {
-                            *visibility = Visibility::Invisible;
+                let is_batch = pending_query.nodes.len() > 1;
+
+                for node in pending_query.nodes.iter() {
+                    let Some(entry) = cell.get_mut(node) else {
+                        continue;
+                    };
+
+                    let old = entry.visibility;
+                    entry.query_pending = false;
+                    entry.batch_inconclusive = is_batch && query_result;
+                    if entry.batch_inconclusive {
+                        // Inconclusive result - at least one object of the batch is visible, but
+                        // it is unknown which one. The objects are treated as visible until they're
+                        // queried separately.
+                        if entry.visibility == Visibility::Invisible {
+                            entry.visibility = Visibility::Undefined;
                         }
-                    },
-                    Visibility::Invisible => {
+                    } else {
                         if query_result {
-                            // Override "invisibility" - if any fragment of an object is visible, then
-                            // it will remain visible forever. This is ok for non-moving objects only.
-                            *visibility = Visibility::Visible;
+                            entry.age = 0;
+                        }
+
+                        match entry.visibility {
+                            Visibility::Undefined => match query_result {
+                                true => {
+                                    entry.visibility = Visibility::Visible;
+                                }
+                                false => {
+                                    entry.visibility = Visibility::Invisible;
+                                }
+                            },
+                            Visibility::Invisible => {
+                                if query_result {
+                                    // Override "invisibility" - if any fragment of an object is
+                                    // visible, then it will remain visible until the visibility
+                                    // decays (see `set_visibility_decay`).
+                                    entry.visibility = Visibility::Visible;
+                                }
+                            }
+                            Visibility::Visible => {
+                                // Ignore the query result and keep the visibility.
+                            }
                         }
                     }
-                    Visibility::Visible => {
-                        // Ignore the query result and keep the visibility.
+
+                    if let Some(DiagnosticsCallback(callback)) = self.diagnostics_callback.as_mut()
+                    {
+                        if old != entry.visibility {
+                            callback(&VisibilityTransition {
+                                node: *node,
+                                old,
+                                new: entry.visibility,
+                                cell: grid_position,
+                            });
+                        }
                     }
                 }
 
@@ -235,6 +621,35 @@ impl ObserverVisibilityCache {
             }
         });
 
+        // Age out visible objects, so they will be queried again.
+        if let Some(visibility_decay) = self.visibility_decay {
+            for (grid_position, cell) in self.cells.iter_mut() {
+                for (node, entry) in cell.iter_mut() {
+                    if entry.visibility != Visibility::Visible {
+                        continue;
+                    }
+
+                    entry.age += 1;
+                    if entry.age < visibility_decay {
+                        continue;
+                    }
+
+                    entry.visibility = Visibility::Undefined;
+                    entry.age = 0;
+
+                    if let Some(DiagnosticsCallback(callback)) = self.diagnostics_callback.as_mut()
+                    {
+                        callback(&VisibilityTransition {
+                            node: *node,
+                            old: Visibility::Visible,
+                            new: Visibility::Undefined,
+                            cell: *grid_position,
+                        });
+                    }
+                }
+            }
+        }
+
         // Remove visibility info from the cache for distant cells.
         self.cells.retain(|grid_position, _| {
             let world_position = grid_to_world(*grid_position, self.granularity);
@@ -242,6 +657,85 @@ impl ObserverVisibilityCache {
             world_position.metric_distance(&observer_position) < self.distance_discard_threshold
         });
     }
+
+    /// Draws every occupied cell of the cache as a wireframe box. Cells are colored from green to
+    /// red depending on how many nodes they track relative to the fullest cell, the cell of the
+    /// observer (as of the last [`Self::update`] call) is drawn white. Useful to pick a proper
+    /// granularity for a scene.
+    pub fn debug_draw(&self, ctx: &mut SceneDrawingContext) {
+        let max_count = self
+            .cells
+            .values()
+            .map(|cell| cell.len())
+            .max()
+            .unwrap_or(0);
+        let observer_cell = self.world_to_grid(self.observer_position);
+        let half_size = self.granularity.map(|g| 0.5 / g as f32);
+
+        for (grid_position, cell) in self.cells.iter() {
+            let color = if *grid_position == observer_cell {
+                Color::WHITE
+            } else {
+                Color::GREEN.lerp(Color::RED, cell.len() as f32 / max_count.max(1) as f32)
+            };
+            let center = self.grid_to_world(*grid_position);
+            ctx.draw_aabb(
+                &AxisAlignedBoundingBox::from_min_max(center - half_size, center + half_size),
+                color,
+            );
+        }
+    }
+}
+
+/// Draws world-space boxes as the proxies of occlusion queries, see
+/// [`ObserverVisibilityCache::run_query_batch`].
+pub struct OcclusionProxyRenderer {
+    program: Box<dyn GpuProgram>,
+    matrices: UniformLocation,
+    cube: Box<dyn GeometryBuffer>,
+    matrix_storage: MatrixStorage,
+}
+
+impl OcclusionProxyRenderer {
+    /// Creates a new proxy renderer.
+    pub fn new(server: &dyn GraphicsServer) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("shaders/occlusion_proxy_fs.glsl");
+        let vertex_source = include_str!("shaders/occlusion_proxy_vs.glsl");
+        let program =
+            server.create_program("OcclusionProxyShader", vertex_source, fragment_source)?;
+        Ok(Self {
+            matrices: program.uniform_location(&ImmutableString::new("matrices"))?,
+            program,
+            cube: <dyn GeometryBuffer>::from_surface_data(
+                &SurfaceData::make_cube(Matrix4::identity()),
+                BufferUsage::StaticDraw,
+                server,
+            )?,
+            matrix_storage: MatrixStorage::new(server)?,
+        })
+    }
+}
+
+/// Only the grid and the resolved visibility info is saved, in-flight queries and the objects
+/// with [`Visibility::Undefined`] visibility are discarded on load. This allows to save the
+/// cache of a large static world and reuse it on the next run without re-running the queries.
+impl Visit for ObserverVisibilityCache {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        let mut region = visitor.enter_region(name)?;
+
+        self.granularity.visit("Granularity", &mut region)?;
+        self.cells.visit("Cells", &mut region)?;
+
+        if region.is_reading() {
+            self.pending_queries.clear();
+            self.cells.retain(|_, cell| {
+                cell.retain(|_, entry| entry.visibility != Visibility::Undefined);
+                !cell.is_empty()
+            });
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -250,13 +744,57 @@ struct ObserverData {
     visibility_cache: ObserverVisibilityCache,
 }
 
+/// Parameters of per-observer visibility caches, see [`ObserverVisibilityCache::new`] for more
+/// info.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VisibilityCacheParams {
+    /// Granularity of the cache grid per axis.
+    pub granularity: Vector3<u32>,
+    /// Maximum distance from an observer, at which visibility info is kept.
+    pub distance_discard_threshold: f32,
+}
+
+impl Default for VisibilityCacheParams {
+    fn default() -> Self {
+        Self {
+            granularity: Vector3::repeat(2),
+            distance_discard_threshold: 100.0,
+        }
+    }
+}
+
 /// Visibility cache that caches visibility info for multiple cameras.
 #[derive(Default, Debug)]
 pub struct VisibilityCache {
     observers: FxHashMap<Handle<Node>, ObserverData>,
+    params: VisibilityCacheParams,
 }
 
 impl VisibilityCache {
+    /// Creates new visibility cache, that will use the given parameters for new observers.
+    pub fn with_params(params: VisibilityCacheParams) -> Self {
+        Self {
+            observers: Default::default(),
+            params,
+        }
+    }
+
+    /// Returns current parameters of the cache.
+    pub fn params(&self) -> VisibilityCacheParams {
+        self.params
+    }
+
+    /// Sets new parameters of the cache and applies them to every registered observer, see
+    /// [`ObserverVisibilityCache::set_granularity`] for more info.
+    pub fn set_params(&mut self, params: VisibilityCacheParams) {
+        self.params = params;
+        for data in self.observers.values_mut() {
+            data.visibility_cache.set_granularity(params.granularity);
+            data.visibility_cache
+                .set_distance_discard_threshold(params.distance_discard_threshold);
+        }
+    }
+
     /// Gets or adds new storage for the given observer.
     pub fn get_or_register(
         &mut self,
@@ -268,11 +806,22 @@ impl VisibilityCache {
             .entry(observer)
             .or_insert_with(|| ObserverData {
                 position: graph[observer].global_position(),
-                visibility_cache: ObserverVisibilityCache::new(Vector3::repeat(2), 100.0),
+                visibility_cache: ObserverVisibilityCache::new(
+                    self.params.granularity,
+                    self.params.distance_discard_threshold,
+                ),
             })
             .visibility_cache
     }
 
+    /// Collects statistics of the caches of every registered observer.
+    pub fn stats(&self) -> FxHashMap<Handle<Node>, VisibilityStats> {
+        self.observers
+            .iter()
+            .map(|(observer, data)| (*observer, data.visibility_cache.stats()))
+            .collect()
+    }
+
     /// Updates the cache by removing unused data.
     pub fn update(&mut self, graph: &Graph) {
         self.observers.retain(|observer, data| {
@@ -288,3 +837,70 @@ impl VisibilityCache {
         });
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{algebra::Vector3, pool::Handle},
+        renderer::{
+            framework::query::{Query, QueryKind, QueryResult},
+            visibility::ObserverVisibilityCache,
+        },
+    };
+
+    #[derive(Debug)]
+    struct FinishedQuery(bool);
+
+    impl Query for FinishedQuery {
+        fn begin(&self, _kind: QueryKind) {}
+
+        fn end(&self) {}
+
+        fn try_get_result(&self) -> Option<QueryResult> {
+            Some(QueryResult::AnySamplesPassed(self.0))
+        }
+    }
+
+    #[test]
+    fn test_batch_query_bookkeeping() {
+        let observer = Vector3::new(0.5, 0.5, 0.5);
+        let mut cache = ObserverVisibilityCache::new(Vector3::repeat(1), 100.0);
+
+        // No fragment of the batch has passed - every object is invisible.
+        let a = Handle::new(1, 1);
+        let b = Handle::new(2, 1);
+        cache.push_query(Box::new(FinishedQuery(false)), observer, &[a, b]);
+        assert!(!cache.needs_occlusion_query(observer, a));
+        cache.update(observer);
+        for node in [a, b] {
+            assert!(!cache.is_visible(observer, node));
+            assert!(cache.needs_batch_query(observer, node));
+        }
+
+        // Some fragments of the batch have passed - it is unknown which object is visible, so both
+        // are treated as visible and need separate queries.
+        let c = Handle::new(3, 1);
+        let d = Handle::new(4, 1);
+        cache.push_query(Box::new(FinishedQuery(true)), observer, &[c, d]);
+        cache.update(observer);
+        for node in [c, d] {
+            assert!(cache.is_visible(observer, node));
+            assert!(cache.needs_occlusion_query(observer, node));
+            assert!(!cache.needs_batch_query(observer, node));
+        }
+
+        // An invisible batch becomes ambiguous as well.
+        cache.push_query(Box::new(FinishedQuery(true)), observer, &[a, b]);
+        cache.update(observer);
+        assert!(cache.is_visible(observer, a));
+        assert!(!cache.needs_batch_query(observer, a));
+
+        // A separate query resolves the visibility.
+        cache.push_query(Box::new(FinishedQuery(false)), observer, &[c]);
+        cache.update(observer);
+        assert!(!cache.is_visible(observer, c));
+        assert!(cache.needs_batch_query(observer, c));
+        assert!(cache.is_visible(observer, d));
+        assert!(!cache.needs_batch_query(observer, d));
+    }
+}
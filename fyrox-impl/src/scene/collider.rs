@@ -26,7 +26,7 @@ use crate::{
     core::{
         algebra::Vector3,
         log::Log,
-        math::aabb::AxisAlignedBoundingBox,
+        math::{aabb::AxisAlignedBoundingBox, Matrix4Ext},
         num_traits::{NumCast, One, ToPrimitive, Zero},
         pool::Handle,
         reflect::prelude::*,
@@ -221,6 +221,16 @@ pub struct ConvexPolyhedronShape {
     pub geometry_source: GeometrySource,
 }
 
+/// Convex hull of an explicit set of points, given in the local coordinates of the collider.
+/// The points do not have to be convex or to be in any order, the hull is computed when the native
+/// shape is created. If the hull cannot be computed (for example, if there are less than three
+/// points), then the collider is invalid and it does not participate in the simulation.
+#[derive(Default, Clone, Debug, Visit, Reflect, PartialEq)]
+pub struct ConvexHullShape {
+    /// Points of the hull.
+    pub points: Vec<Vector3<f32>>,
+}
+
 /// A set of bits used for pairwise collision filtering.
 #[derive(Clone, Copy, Default, PartialEq, Debug, Reflect, Eq)]
 pub struct BitMask(pub u32);
@@ -344,6 +354,37 @@ impl InteractionGroups {
             filter,
         }
     }
+
+    /// Creates new interaction group that is not a member of any group and does not interact
+    /// with any group. Use [`Self::with_membership_layer`] and [`Self::with_filter_layer`] to add groups.
+    pub fn none() -> Self {
+        Self::new(BitMask(0), BitMask(0))
+    }
+
+    /// Creates new interaction group that is a member of every group and interacts with every group.
+    pub fn all() -> Self {
+        Self::new(BitMask(u32::MAX), BitMask(u32::MAX))
+    }
+
+    /// Adds the group with the given index (`0..32`) to the groups memberships. Indices outside
+    /// of the range are ignored.
+    pub fn with_membership_layer(mut self, layer: u32) -> Self {
+        self.memberships.0 |= 1u32.checked_shl(layer).unwrap_or(0);
+        self
+    }
+
+    /// Adds the group with the given index (`0..32`) to the groups filter. Indices outside
+    /// of the range are ignored.
+    pub fn with_filter_layer(mut self, layer: u32) -> Self {
+        self.filter.0 |= 1u32.checked_shl(layer).unwrap_or(0);
+        self
+    }
+
+    /// Checks whether interactions are allowed between these groups and the other groups,
+    /// see [`InteractionGroups`] docs for the exact condition.
+    pub fn test(&self, other: &InteractionGroups) -> bool {
+        (self.memberships & other.filter).0 != 0 && (other.memberships & self.filter).0 != 0
+    }
 }
 
 impl Default for InteractionGroups {
@@ -388,6 +429,36 @@ bitflags::bitflags! {
     }
 }
 
+bitflags::bitflags! {
+    #[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+    /// Flags that define a set of collider properties, that were changed and must be synced with
+    /// the physics engine. See [`Collider::has_change`] and [`Collider::acknowledge_changes`].
+    pub struct ColliderChanges: u32 {
+        /// Shape of the collider or its global scale.
+        const SHAPE = 1 << 0;
+        /// Friction coefficient of the collider.
+        const FRICTION = 1 << 1;
+        /// Density of the collider.
+        const DENSITY = 1 << 2;
+        /// Restitution coefficient of the collider.
+        const RESTITUTION = 1 << 3;
+        /// Sensor flag of the collider.
+        const IS_SENSOR = 1 << 4;
+        /// Collision groups of the collider.
+        const COLLISION_GROUPS = 1 << 5;
+        /// Solver groups of the collider.
+        const SOLVER_GROUPS = 1 << 6;
+        /// Friction combine rule of the collider.
+        const FRICTION_COMBINE_RULE = 1 << 7;
+        /// Restitution combine rule of the collider.
+        const RESTITUTION_COMBINE_RULE = 1 << 8;
+        /// Explicit mass of the collider.
+        const MASS = 1 << 9;
+        /// Explicit center of mass of the collider.
+        const CENTER_OF_MASS = 1 << 10;
+    }
+}
+
 /// The status of the time-of-impact computation algorithm.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum TOIStatus {
@@ -436,7 +507,11 @@ impl From<rapier2d::parry::query::ShapeCastStatus> for TOIStatus {
 }
 
 /// Possible collider shapes.
-#[derive(Clone, Debug, PartialEq, Visit, Reflect, AsRefStr, EnumString, VariantNames)]
+///
+/// The shape is serialized with an explicit version and a stable id of each variant (see
+/// [`ColliderShape::id`]), so new variants can be added in any place of the enum without breaking
+/// previously saved scenes.
+#[derive(Clone, Debug, PartialEq, Reflect, AsRefStr, EnumString, VariantNames)]
 pub enum ColliderShape {
     /// See [`BallShape`] docs.
     Ball(BallShape),
@@ -458,6 +533,8 @@ pub enum ColliderShape {
     Heightfield(HeightfieldShape),
     /// See [`ConvexPolyhedronShape`] docs.
     Polyhedron(ConvexPolyhedronShape),
+    /// See [`ConvexHullShape`] docs.
+    ConvexHull(ConvexHullShape),
 }
 
 uuid_provider!(ColliderShape = "2e627337-71ea-4b33-a5f1-be697f705a86");
@@ -468,6 +545,94 @@ impl Default for ColliderShape {
     }
 }
 
+impl ColliderShape {
+    /// Current version of the serialized representation of the shape. Version 0 is the one
+    /// produced by the derived implementation of [`Visit`], which used the index of a variant
+    /// as its id. It has the same layout as version 1, except the version field itself.
+    const VERSION: u8 = 1;
+
+    /// Returns stable id of the variant that is used for serialization. Ids of existing variants
+    /// must never change and ids of removed variants must never be reused, new variants must get
+    /// new ids.
+    fn id(&self) -> u32 {
+        match self {
+            Self::Ball(_) => 0,
+            Self::Cylinder(_) => 1,
+            Self::Cone(_) => 2,
+            Self::Cuboid(_) => 3,
+            Self::Capsule(_) => 4,
+            Self::Segment(_) => 5,
+            Self::Triangle(_) => 6,
+            Self::Trimesh(_) => 7,
+            Self::Heightfield(_) => 8,
+            Self::Polyhedron(_) => 9,
+            Self::ConvexHull(_) => 10,
+        }
+    }
+
+    fn from_id(id: u32) -> Option<Self> {
+        Some(match id {
+            0 => Self::Ball(Default::default()),
+            1 => Self::Cylinder(Default::default()),
+            2 => Self::Cone(Default::default()),
+            3 => Self::Cuboid(Default::default()),
+            4 => Self::Capsule(Default::default()),
+            5 => Self::Segment(Default::default()),
+            6 => Self::Triangle(Default::default()),
+            7 => Self::Trimesh(Default::default()),
+            8 => Self::Heightfield(Default::default()),
+            9 => Self::Polyhedron(Default::default()),
+            10 => Self::ConvexHull(Default::default()),
+            _ => return None,
+        })
+    }
+}
+
+impl Visit for ColliderShape {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        let mut region = visitor.enter_region(name)?;
+
+        let mut version = if region.is_reading() {
+            0
+        } else {
+            Self::VERSION
+        };
+        let _ = version.visit("Version", &mut region);
+        if version > Self::VERSION {
+            return Err(VisitError::User(format!(
+                "Unsupported collider shape version {version}, the most recent supported \
+                 version is {}. The data was probably saved by a newer version of the engine.",
+                Self::VERSION
+            )));
+        }
+
+        let mut id = self.id();
+        id.visit("Id", &mut region)?;
+        if region.is_reading() {
+            *self = Self::from_id(id).ok_or_else(|| {
+                VisitError::User(format!(
+                    "Unknown collider shape id {id}. The data was probably saved by a newer \
+                     version of the engine."
+                ))
+            })?;
+        }
+
+        match self {
+            Self::Ball(shape) => shape.visit("0", &mut region),
+            Self::Cylinder(shape) => shape.visit("0", &mut region),
+            Self::Cone(shape) => shape.visit("0", &mut region),
+            Self::Cuboid(shape) => shape.visit("0", &mut region),
+            Self::Capsule(shape) => shape.visit("0", &mut region),
+            Self::Segment(shape) => shape.visit("0", &mut region),
+            Self::Triangle(shape) => shape.visit("0", &mut region),
+            Self::Trimesh(shape) => shape.visit("0", &mut region),
+            Self::Heightfield(shape) => shape.visit("0", &mut region),
+            Self::Polyhedron(shape) => shape.visit("0", &mut region),
+            Self::ConvexHull(shape) => shape.visit("0", &mut region),
+        }
+    }
+}
+
 impl ColliderShape {
     /// Initializes a ball shape defined by its radius.
     pub fn ball(radius: f32) -> Self {
@@ -544,6 +709,123 @@ impl ColliderShape {
     pub fn heightfield(geometry_source: GeometrySource) -> Self {
         Self::Heightfield(HeightfieldShape { geometry_source })
     }
+
+    /// Initializes a convex hull shape defined by a set of points, see [`ConvexHullShape`] docs.
+    pub fn convex_hull(points: Vec<Vector3<f32>>) -> Self {
+        Self::ConvexHull(ConvexHullShape { points })
+    }
+
+    /// Returns a copy of the shape with the given scale baked in. Points of capsules, segments,
+    /// triangles and convex hulls are scaled per axis, as well as half extents of cuboids and
+    /// half heights of cylinders and cones. Shapes that cannot represent non-uniform scale are
+    /// approximated using the maximum scale among the involved axes: radius of balls and
+    /// capsules use the maximum of all three axes, radius of cylinders and cones use the maximum
+    /// of X and Z axes. Shapes that are built from geometry sources are returned unchanged,
+    /// because the scale of their source nodes is baked in when the native shape is rebuilt.
+    pub fn scaled(&self, scale: Vector3<f32>) -> ColliderShape {
+        let scale = scale.abs();
+        let max_scale = scale.max();
+        let radial_scale = scale.x.max(scale.z);
+        match self {
+            Self::Ball(ball) => Self::ball(ball.radius * max_scale),
+            Self::Cylinder(cylinder) => Self::cylinder(
+                cylinder.half_height * scale.y,
+                cylinder.radius * radial_scale,
+            ),
+            Self::Cone(cone) => Self::cone(cone.half_height * scale.y, cone.radius * radial_scale),
+            Self::Cuboid(cuboid) => Self::Cuboid(CuboidShape {
+                half_extents: cuboid.half_extents.component_mul(&scale),
+            }),
+            Self::Capsule(capsule) => Self::capsule(
+                capsule.begin.component_mul(&scale),
+                capsule.end.component_mul(&scale),
+                capsule.radius * max_scale,
+            ),
+            Self::Segment(segment) => Self::segment(
+                segment.begin.component_mul(&scale),
+                segment.end.component_mul(&scale),
+            ),
+            Self::Triangle(triangle) => Self::triangle(
+                triangle.a.component_mul(&scale),
+                triangle.b.component_mul(&scale),
+                triangle.c.component_mul(&scale),
+            ),
+            Self::ConvexHull(hull) => Self::convex_hull(
+                hull.points
+                    .iter()
+                    .map(|p| p.component_mul(&scale))
+                    .collect(),
+            ),
+            Self::Trimesh(_) | Self::Heightfield(_) | Self::Polyhedron(_) => self.clone(),
+        }
+    }
+
+    /// Computes an axis-aligned bounding box of the shape without creating a native shape.
+    /// Primitive shapes and convex hulls are bounded analytically in the local coordinates of
+    /// the collider. Shapes that are built from geometry sources (triangle meshes, height fields
+    /// and convex polyhedra) are bounded by the world-space bounding boxes of their source nodes,
+    /// because the native shapes bake the transforms of those nodes in.
+    ///
+    /// Returns `None` if a geometry source does not exist in the graph or the shape has no
+    /// geometry at all (for example, a convex hull without points).
+    pub fn compute_aabb(&self, graph: &Graph) -> Option<AxisAlignedBoundingBox> {
+        let source_aabb = |source: &GeometrySource| {
+            graph.try_get(source.0).map(|node| {
+                node.local_bounding_box()
+                    .transform(&node.global_transform())
+            })
+        };
+
+        match self {
+            Self::Ball(ball) => Some(AxisAlignedBoundingBox::from_radius(ball.radius)),
+            Self::Cylinder(CylinderShape {
+                half_height,
+                radius,
+            })
+            | Self::Cone(ConeShape {
+                half_height,
+                radius,
+            }) => Some(AxisAlignedBoundingBox::from_min_max(
+                Vector3::new(-radius, -half_height, -radius),
+                Vector3::new(*radius, *half_height, *radius),
+            )),
+            Self::Cuboid(cuboid) => Some(AxisAlignedBoundingBox::from_min_max(
+                -cuboid.half_extents,
+                cuboid.half_extents,
+            )),
+            Self::Capsule(capsule) => {
+                let mut aabb = AxisAlignedBoundingBox::from_points(&[capsule.begin, capsule.end]);
+                aabb.inflate(Vector3::repeat(capsule.radius * 2.0));
+                Some(aabb)
+            }
+            Self::Segment(segment) => Some(AxisAlignedBoundingBox::from_points(&[
+                segment.begin,
+                segment.end,
+            ])),
+            Self::Triangle(triangle) => Some(AxisAlignedBoundingBox::from_points(&[
+                triangle.a, triangle.b, triangle.c,
+            ])),
+            Self::Trimesh(trimesh) => {
+                let mut sources = trimesh.sources.iter();
+                let mut aabb = source_aabb(sources.next()?)?;
+                for source in sources {
+                    aabb.add_box(source_aabb(source)?);
+                }
+                Some(aabb)
+            }
+            Self::Heightfield(HeightfieldShape { geometry_source })
+            | Self::Polyhedron(ConvexPolyhedronShape { geometry_source }) => {
+                source_aabb(geometry_source)
+            }
+            Self::ConvexHull(hull) => {
+                if hull.points.is_empty() {
+                    None
+                } else {
+                    Some(AxisAlignedBoundingBox::from_points(&hull.points))
+                }
+            }
+        }
+    }
 }
 
 /// Collider is a geometric entity that can be attached to a rigid body to allow participate it
@@ -561,6 +843,14 @@ pub struct Collider {
     #[reflect(setter = "set_density")]
     pub(crate) density: InheritableVariable<Option<f32>>,
 
+    #[visit(optional)] // Backward compatibility
+    #[reflect(setter = "set_mass")]
+    pub(crate) mass: InheritableVariable<Option<f32>>,
+
+    #[visit(optional)] // Backward compatibility
+    #[reflect(setter = "set_center_of_mass")]
+    pub(crate) center_of_mass: InheritableVariable<Option<Vector3<f32>>>,
+
     #[reflect(min_value = 0.0, step = 0.05, setter = "set_restitution")]
     pub(crate) restitution: InheritableVariable<f32>,
 
@@ -582,6 +872,16 @@ pub struct Collider {
     #[visit(skip)]
     #[reflect(hidden)]
     pub(crate) native: Cell<ColliderHandle>,
+
+    /// Global scale of the collider that was baked in the current native shape.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub(crate) native_scale: Cell<Vector3<f32>>,
+
+    /// Forces the native shape to be rebuilt, see [`Collider::invalidate_native_shape`].
+    #[visit(skip)]
+    #[reflect(hidden)]
+    shape_invalidated: Cell<bool>,
 }
 
 impl Default for Collider {
@@ -591,6 +891,8 @@ impl Default for Collider {
             shape: Default::default(),
             friction: InheritableVariable::new_modified(0.0),
             density: InheritableVariable::new_modified(None),
+            mass: InheritableVariable::new_modified(None),
+            center_of_mass: InheritableVariable::new_modified(None),
             restitution: InheritableVariable::new_modified(0.0),
             is_sensor: InheritableVariable::new_modified(false),
             collision_groups: Default::default(),
@@ -598,6 +900,8 @@ impl Default for Collider {
             friction_combine_rule: Default::default(),
             restitution_combine_rule: Default::default(),
             native: Cell::new(ColliderHandle::invalid()),
+            native_scale: Cell::new(Vector3::repeat(1.0)),
+            shape_invalidated: Cell::new(false),
         }
     }
 }
@@ -623,6 +927,8 @@ impl Clone for Collider {
             shape: self.shape.clone(),
             friction: self.friction.clone(),
             density: self.density.clone(),
+            mass: self.mass.clone(),
+            center_of_mass: self.center_of_mass.clone(),
             restitution: self.restitution.clone(),
             is_sensor: self.is_sensor.clone(),
             collision_groups: self.collision_groups.clone(),
@@ -631,6 +937,8 @@ impl Clone for Collider {
             restitution_combine_rule: self.restitution_combine_rule.clone(),
             // Do not copy. The copy will have its own native representation (for example - Rapier's collider)
             native: Cell::new(ColliderHandle::invalid()),
+            native_scale: Cell::new(Vector3::repeat(1.0)),
+            shape_invalidated: Cell::new(false),
         }
     }
 }
@@ -641,6 +949,14 @@ impl TypeUuidProvider for Collider {
     }
 }
 
+// Norms of the basis vectors of a rotated transform differ from the actual scale by a few ULPs,
+// so the scale is compared with a relative tolerance to not rebuild the native shape every frame.
+fn is_scale_changed(old: Vector3<f32>, new: Vector3<f32>) -> bool {
+    old.iter()
+        .zip(new.iter())
+        .any(|(a, b)| (a - b).abs() > 1.0e-5 * a.abs().max(1.0))
+}
+
 impl Collider {
     /// Sets the new shape to the collider.
     ///
@@ -653,6 +969,14 @@ impl Collider {
         self.shape.set_value_and_mark_modified(shape)
     }
 
+    /// Forces the native shape to be rebuilt on the next physics sync, without changing the shape.
+    /// This is useful for [`ColliderShape::Trimesh`], [`ColliderShape::Heightfield`], and
+    /// [`ColliderShape::Polyhedron`] shapes, which are built from the geometry of other nodes,
+    /// when that geometry is modified.
+    pub fn invalidate_native_shape(&mut self) {
+        self.shape_invalidated.set(true);
+    }
+
     /// Returns shared reference to the collider shape.
     pub fn shape(&self) -> &ColliderShape {
         &self.shape
@@ -663,6 +987,22 @@ impl Collider {
         (*self.shape).clone()
     }
 
+    /// Returns a copy of the collider shape with the global scale of the collider baked in. This
+    /// is the shape that is used to create the native shape, see [`ColliderShape::scaled`] for
+    /// more info.
+    pub fn scaled_shape(&self) -> ColliderShape {
+        self.shape.scaled(self.global_scale())
+    }
+
+    pub(crate) fn global_scale(&self) -> Vector3<f32> {
+        let basis = self.global_transform().basis();
+        Vector3::new(
+            basis.column(0).norm(),
+            basis.column(1).norm(),
+            basis.column(2).norm(),
+        )
+    }
+
     /// Returns mutable reference to the current collider shape.
     ///
     /// # Performance
@@ -715,6 +1055,47 @@ impl Collider {
         *self.density
     }
 
+    /// Sets an explicit mass of the collider. Unlike [`Self::set_density`], the mass does not
+    /// depend on the volume of the collider shape, the angular inertia is still computed from
+    /// the shape. When both density and explicit mass are set, the explicit mass wins and the
+    /// density is ignored. `None` means that the mass is defined by the density.
+    ///
+    /// # Performance
+    ///
+    /// This is relatively expensive operation - it forces the physics engine to recalculate mass
+    /// properties of the parent rigid body. Try avoid calling this method each frame for better
+    /// performance.
+    pub fn set_mass(&mut self, mass: Option<f32>) -> Option<f32> {
+        self.mass.set_value_and_mark_modified(mass)
+    }
+
+    /// Returns explicit mass of the collider (if any).
+    pub fn mass(&self) -> Option<f32> {
+        *self.mass
+    }
+
+    /// Sets an explicit center of mass of the collider, in local coordinates of the collider.
+    /// `None` means that the center of mass is computed from the collider shape. The override is
+    /// applied regardless of whether the mass is defined by the density or set explicitly.
+    ///
+    /// # Performance
+    ///
+    /// This is relatively expensive operation - it forces the physics engine to recalculate mass
+    /// properties of the parent rigid body. Try avoid calling this method each frame for better
+    /// performance.
+    pub fn set_center_of_mass(
+        &mut self,
+        center_of_mass: Option<Vector3<f32>>,
+    ) -> Option<Vector3<f32>> {
+        self.center_of_mass
+            .set_value_and_mark_modified(center_of_mass)
+    }
+
+    /// Returns explicit center of mass of the collider (if any).
+    pub fn center_of_mass(&self) -> Option<Vector3<f32>> {
+        *self.center_of_mass
+    }
+
     /// Sets friction coefficient for the collider. The greater value is the more kinematic energy
     /// will be converted to heat (in other words - lost), the parent rigid body will slowdown much
     /// faster and so on.
@@ -830,6 +1211,16 @@ impl Collider {
         physics.contacts_with(self.native.get())
     }
 
+    /// Returns an iterator that yields contact information for the collider together with the
+    /// magnitude of the largest normal impulse applied by any contact point of the pair during the
+    /// last physics step. Pairs that involve a sensor collider are never reported.
+    pub fn contact_forces<'a>(
+        &self,
+        physics: &'a PhysicsWorld,
+    ) -> impl Iterator<Item = (ContactPair, f32)> + 'a {
+        physics.contact_forces_with(self.native.get())
+    }
+
     /// Returns an iterator that yields intersection information for the collider.
     /// Intersections checks between regular colliders and sensor colliders
     pub fn intersects<'a>(
@@ -839,16 +1230,98 @@ impl Collider {
         physics.intersections_with(self.native.get())
     }
 
+    /// Returns `true` if any of the properties defined by the given flags was changed and is not
+    /// yet synced with the physics engine. See [`ColliderChanges`] docs for more info.
+    pub fn has_change(&self, flags: ColliderChanges) -> bool {
+        self.changes().intersects(flags)
+    }
+
+    /// Returns a set of properties that were changed and are not yet synced with the physics engine.
+    pub fn changes(&self) -> ColliderChanges {
+        let mut changes = ColliderChanges::empty();
+        changes.set(
+            ColliderChanges::SHAPE,
+            self.shape.need_sync()
+                || self.shape_invalidated.get()
+                || is_scale_changed(self.native_scale.get(), self.global_scale()),
+        );
+        changes.set(ColliderChanges::FRICTION, self.friction.need_sync());
+        changes.set(ColliderChanges::DENSITY, self.density.need_sync());
+        changes.set(ColliderChanges::MASS, self.mass.need_sync());
+        changes.set(
+            ColliderChanges::CENTER_OF_MASS,
+            self.center_of_mass.need_sync(),
+        );
+        changes.set(ColliderChanges::RESTITUTION, self.restitution.need_sync());
+        changes.set(ColliderChanges::IS_SENSOR, self.is_sensor.need_sync());
+        changes.set(
+            ColliderChanges::COLLISION_GROUPS,
+            self.collision_groups.need_sync(),
+        );
+        changes.set(
+            ColliderChanges::SOLVER_GROUPS,
+            self.solver_groups.need_sync(),
+        );
+        changes.set(
+            ColliderChanges::FRICTION_COMBINE_RULE,
+            self.friction_combine_rule.need_sync(),
+        );
+        changes.set(
+            ColliderChanges::RESTITUTION_COMBINE_RULE,
+            self.restitution_combine_rule.need_sync(),
+        );
+        changes
+    }
+
+    /// Marks the properties defined by the given flags as synced with the physics engine, so the
+    /// engine will not sync them on its own. This is useful for custom physics integrations that
+    /// apply the changes on their own (for example, to update only friction without rebuilding
+    /// the shape).
+    ///
+    /// # Important notes
+    ///
+    /// Acknowledging changes that were not actually applied to the physics engine will desync the
+    /// collider node and its native representation.
+    pub fn acknowledge_changes(&self, flags: ColliderChanges) {
+        if flags.contains(ColliderChanges::SHAPE) {
+            self.shape.mark_synced();
+            self.shape_invalidated.set(false);
+            self.native_scale.set(self.global_scale());
+        }
+        if flags.contains(ColliderChanges::FRICTION) {
+            self.friction.mark_synced();
+        }
+        if flags.contains(ColliderChanges::DENSITY) {
+            self.density.mark_synced();
+        }
+        if flags.contains(ColliderChanges::MASS) {
+            self.mass.mark_synced();
+        }
+        if flags.contains(ColliderChanges::CENTER_OF_MASS) {
+            self.center_of_mass.mark_synced();
+        }
+        if flags.contains(ColliderChanges::RESTITUTION) {
+            self.restitution.mark_synced();
+        }
+        if flags.contains(ColliderChanges::IS_SENSOR) {
+            self.is_sensor.mark_synced();
+        }
+        if flags.contains(ColliderChanges::COLLISION_GROUPS) {
+            self.collision_groups.mark_synced();
+        }
+        if flags.contains(ColliderChanges::SOLVER_GROUPS) {
+            self.solver_groups.mark_synced();
+        }
+        if flags.contains(ColliderChanges::FRICTION_COMBINE_RULE) {
+            self.friction_combine_rule.mark_synced();
+        }
+        if flags.contains(ColliderChanges::RESTITUTION_COMBINE_RULE) {
+            self.restitution_combine_rule.mark_synced();
+        }
+    }
+
     pub(crate) fn needs_sync_model(&self) -> bool {
-        self.shape.need_sync()
-            || self.friction.need_sync()
-            || self.density.need_sync()
-            || self.restitution.need_sync()
-            || self.is_sensor.need_sync()
-            || self.collision_groups.need_sync()
-            || self.solver_groups.need_sync()
-            || self.friction_combine_rule.need_sync()
-            || self.restitution_combine_rule.need_sync()
+        !self.changes().is_empty()
     }
 }
 
@@ -968,6 +1441,8 @@ pub struct ColliderBuilder {
     shape: ColliderShape,
     friction: f32,
     density: Option<f32>,
+    mass: Option<f32>,
+    center_of_mass: Option<Vector3<f32>>,
     restitution: f32,
     is_sensor: bool,
     collision_groups: InteractionGroups,
@@ -984,6 +1459,8 @@ impl ColliderBuilder {
             shape: Default::default(),
             friction: 0.0,
             density: None,
+            mass: None,
+            center_of_mass: None,
             restitution: 0.0,
             is_sensor: false,
             collision_groups: Default::default(),
@@ -1005,6 +1482,18 @@ impl ColliderBuilder {
         self
     }
 
+    /// Sets desired explicit mass. See [`Collider::set_mass`] for more info.
+    pub fn with_mass(mut self, mass: Option<f32>) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    /// Sets desired explicit center of mass. See [`Collider::set_center_of_mass`] for more info.
+    pub fn with_center_of_mass(mut self, center_of_mass: Option<Vector3<f32>>) -> Self {
+        self.center_of_mass = center_of_mass;
+        self
+    }
+
     /// Sets desired restitution value.
     pub fn with_restitution(mut self, restitution: f32) -> Self {
         self.restitution = restitution;
@@ -1054,6 +1543,8 @@ impl ColliderBuilder {
             shape: self.shape.into(),
             friction: self.friction.into(),
             density: self.density.into(),
+            mass: self.mass.into(),
+            center_of_mass: self.center_of_mass.into(),
             restitution: self.restitution.into(),
             is_sensor: self.is_sensor.into(),
             collision_groups: self.collision_groups.into(),
@@ -1061,6 +1552,8 @@ impl ColliderBuilder {
             friction_combine_rule: self.friction_combine_rule.into(),
             restitution_combine_rule: self.restitution_combine_rule.into(),
             native: Cell::new(ColliderHandle::invalid()),
+            native_scale: Cell::new(Vector3::repeat(1.0)),
+            shape_invalidated: Cell::new(false),
         }
     }
 
@@ -1077,14 +1570,558 @@ impl ColliderBuilder {
 
 #[cfg(test)]
 mod test {
-    use crate::core::algebra::Vector2;
+    use crate::asset::untyped::ResourceKind;
+    use crate::core::{
+        algebra::{Isometry3, Matrix4, Point3, Unit, UnitQuaternion, Vector2, Vector3},
+        pool::Handle,
+        visitor::prelude::*,
+    };
     use crate::scene::{
         base::BaseBuilder,
-        collider::{ColliderBuilder, ColliderShape},
-        graph::Graph,
+        collider::ColliderHandle,
+        collider::{
+            BitMask, Collider, ColliderBuilder, ColliderChanges, ColliderShape, CuboidShape,
+            GeometrySource, InteractionGroups,
+        },
+        graph::{
+            physics::{CoefficientCombineRule, QueryFilter, RayCastOptions, ShapeCastError},
+            Graph,
+        },
+        mesh::{
+            surface::{SurfaceBuilder, SurfaceData, SurfaceResource},
+            MeshBuilder,
+        },
+        node::Node,
         rigidbody::{RigidBodyBuilder, RigidBodyType},
+        transform::TransformBuilder,
     };
 
+    #[test]
+    fn test_interaction_groups_layers() {
+        let player = InteractionGroups::none()
+            .with_membership_layer(0)
+            .with_filter_layer(1);
+        let wall = InteractionGroups::none()
+            .with_membership_layer(1)
+            .with_filter_layer(0)
+            .with_filter_layer(2);
+        let pickup = InteractionGroups::none().with_membership_layer(2);
+        assert_eq!(player, InteractionGroups::new(BitMask(0b1), BitMask(0b10)));
+        assert_eq!(wall, InteractionGroups::new(BitMask(0b10), BitMask(0b101)));
+        assert!(player.test(&wall));
+        assert!(wall.test(&player));
+        // The pickup has no filter, so it interacts with nothing.
+        assert!(!wall.test(&pickup));
+        assert!(!pickup.test(&pickup));
+        assert!(InteractionGroups::all().test(&InteractionGroups::all()));
+        assert_eq!(InteractionGroups::all(), InteractionGroups::default());
+        assert_eq!(
+            InteractionGroups::none().with_membership_layer(32),
+            InteractionGroups::none()
+        );
+        // Matches the semantics of Rapier.
+        let to_rapier = |g: InteractionGroups| {
+            rapier3d::geometry::InteractionGroups::new(
+                rapier3d::geometry::Group::from_bits_truncate(g.memberships.0),
+                rapier3d::geometry::Group::from_bits_truncate(g.filter.0),
+            )
+        };
+        for (a, b) in [(player, wall), (wall, pickup), (pickup, player)] {
+            assert_eq!(a.test(&b), to_rapier(a).test(to_rapier(b)));
+        }
+    }
+
+    #[test]
+    fn test_collider_changes() {
+        let mut collider = ColliderBuilder::new(BaseBuilder::new()).build_collider();
+        assert!(!collider.has_change(ColliderChanges::all()));
+
+        collider.set_friction(0.5);
+        collider.set_shape(ColliderShape::ball(1.0));
+        assert!(collider.has_change(ColliderChanges::FRICTION));
+        assert!(collider.has_change(ColliderChanges::SHAPE));
+        assert!(!collider.has_change(ColliderChanges::RESTITUTION));
+
+        collider.acknowledge_changes(ColliderChanges::FRICTION);
+        assert!(!collider.has_change(ColliderChanges::FRICTION));
+        assert_eq!(collider.changes(), ColliderChanges::SHAPE);
+        assert!(collider.needs_sync_model());
+
+        collider.acknowledge_changes(ColliderChanges::all());
+        assert!(!collider.needs_sync_model());
+
+        collider.invalidate_native_shape();
+        assert_eq!(collider.changes(), ColliderChanges::SHAPE);
+        collider.acknowledge_changes(ColliderChanges::SHAPE);
+        assert!(!collider.needs_sync_model());
+    }
+
+    #[test]
+    fn test_collider_combine_rules() {
+        let mut collider = ColliderBuilder::new(BaseBuilder::new()).build_collider();
+        // Must match Rapier's defaults.
+        let rapier_default =
+            CoefficientCombineRule::from(rapier3d::dynamics::CoefficientCombineRule::default());
+        assert_eq!(collider.friction_combine_rule(), rapier_default);
+        assert_eq!(collider.restitution_combine_rule(), rapier_default);
+        assert_eq!(rapier_default, CoefficientCombineRule::Average);
+
+        collider.set_friction_combine_rule(CoefficientCombineRule::Max);
+        assert_eq!(collider.changes(), ColliderChanges::FRICTION_COMBINE_RULE);
+        collider.set_restitution_combine_rule(CoefficientCombineRule::Multiply);
+        assert!(collider.has_change(ColliderChanges::RESTITUTION_COMBINE_RULE));
+        assert_eq!(
+            collider.friction_combine_rule(),
+            CoefficientCombineRule::Max
+        );
+        assert_eq!(
+            collider.restitution_combine_rule(),
+            CoefficientCombineRule::Multiply
+        );
+    }
+
+    #[test]
+    fn test_collider_builder_parity() {
+        let collision_groups = InteractionGroups::new(BitMask(0b0101), BitMask(0b0011));
+        let solver_groups = InteractionGroups::new(BitMask(0b1000), BitMask(0b0110));
+        let collider = ColliderBuilder::new(BaseBuilder::new())
+            .with_shape(ColliderShape::ball(2.0))
+            .with_friction(0.4)
+            .with_density(Some(3.0))
+            .with_mass(Some(4.0))
+            .with_center_of_mass(Some(Vector3::new(0.0, 1.0, 0.0)))
+            .with_restitution(0.7)
+            .with_sensor(true)
+            .with_collision_groups(collision_groups)
+            .with_solver_groups(solver_groups)
+            .with_friction_combine_rule(CoefficientCombineRule::Min)
+            .with_restitution_combine_rule(CoefficientCombineRule::Max)
+            .build_collider();
+
+        assert!(matches!(collider.shape(), ColliderShape::Ball(ball) if ball.radius == 2.0));
+        assert_eq!(collider.friction(), 0.4);
+        assert_eq!(collider.density(), Some(3.0));
+        assert_eq!(collider.mass(), Some(4.0));
+        assert_eq!(collider.center_of_mass(), Some(Vector3::new(0.0, 1.0, 0.0)));
+        assert_eq!(collider.restitution(), 0.7);
+        assert!(collider.is_sensor());
+        assert_eq!(collider.collision_groups(), collision_groups);
+        assert_eq!(collider.solver_groups(), solver_groups);
+        assert_eq!(
+            collider.friction_combine_rule(),
+            CoefficientCombineRule::Min
+        );
+        assert_eq!(
+            collider.restitution_combine_rule(),
+            CoefficientCombineRule::Max
+        );
+        assert_eq!(collider.native.get(), ColliderHandle::invalid());
+    }
+
+    fn load_shape(visitor: Visitor) -> VisitResult {
+        let data = visitor.save_binary_to_vec().unwrap();
+        let mut visitor = Visitor::load_from_memory(&data).unwrap();
+        let mut shape = ColliderShape::default();
+        shape.visit("Shape", &mut visitor).map(|_| ())
+    }
+
+    #[test]
+    fn test_collider_shape_visit() {
+        let mut shape = ColliderShape::cuboid(1.0, 2.0, 3.0);
+        let mut visitor = Visitor::new();
+        shape.visit("Shape", &mut visitor).unwrap();
+        let data = visitor.save_binary_to_vec().unwrap();
+        let mut visitor = Visitor::load_from_memory(&data).unwrap();
+        let mut loaded = ColliderShape::default();
+        loaded.visit("Shape", &mut visitor).unwrap();
+        assert_eq!(loaded, shape);
+
+        // Data saved before the shape had a version must be loaded as well.
+        let mut visitor = Visitor::new();
+        {
+            let mut region = visitor.enter_region("Shape").unwrap();
+            3u32.visit("Id", &mut region).unwrap();
+            CuboidShape::default().visit("0", &mut region).unwrap();
+        }
+        assert!(load_shape(visitor).is_ok());
+
+        // Shapes unknown to this version must produce an error.
+        let mut visitor = Visitor::new();
+        {
+            let mut region = visitor.enter_region("Shape").unwrap();
+            let mut version = ColliderShape::VERSION;
+            version.visit("Version", &mut region).unwrap();
+            1000u32.visit("Id", &mut region).unwrap();
+        }
+        assert!(load_shape(visitor).is_err());
+
+        let mut visitor = Visitor::new();
+        {
+            let mut region = visitor.enter_region("Shape").unwrap();
+            let mut version = ColliderShape::VERSION + 1;
+            version.visit("Version", &mut region).unwrap();
+            0u32.visit("Id", &mut region).unwrap();
+        }
+        assert!(load_shape(visitor).is_err());
+    }
+
+    #[test]
+    fn test_convex_hull_shape() {
+        let points = vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.1, 0.1, 0.1),
+        ];
+        let mut shape = ColliderShape::convex_hull(points.clone());
+        let mut visitor = Visitor::new();
+        shape.visit("Shape", &mut visitor).unwrap();
+        let data = visitor.save_binary_to_vec().unwrap();
+        let mut visitor = Visitor::load_from_memory(&data).unwrap();
+        let mut loaded = ColliderShape::default();
+        loaded.visit("Shape", &mut visitor).unwrap();
+        assert_eq!(loaded, shape);
+
+        let mut graph = Graph::new();
+        let mut create_collider = |shape| {
+            let collider = ColliderBuilder::new(BaseBuilder::new())
+                .with_shape(shape)
+                .build(&mut graph);
+            RigidBodyBuilder::new(BaseBuilder::new().with_children(&[collider])).build(&mut graph);
+            collider
+        };
+        let valid = create_collider(shape);
+        let degenerate = create_collider(ColliderShape::convex_hull(points[..2].to_vec()));
+        graph.update(Vector2::new(800.0, 600.0), 1.0, Default::default());
+        graph.update(Vector2::new(800.0, 600.0), 1.0, Default::default());
+        let native = graph[valid].as_collider().native.get();
+        assert_eq!(graph.physics.collider_node(native), valid);
+        let native = graph[degenerate].as_collider().native.get();
+        assert!(graph.physics.collider_node(native).is_none());
+    }
+
+    #[test]
+    fn test_collider_mass_override() {
+        let mut graph = Graph::new();
+        let collider = ColliderBuilder::new(BaseBuilder::new())
+            .with_shape(ColliderShape::cuboid(0.5, 0.5, 0.5))
+            .with_density(Some(2.0))
+            .with_mass(Some(5.0))
+            .with_center_of_mass(Some(Vector3::new(0.0, 0.25, 0.0)))
+            .build(&mut graph);
+        RigidBodyBuilder::new(BaseBuilder::new().with_children(&[collider])).build(&mut graph);
+        graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+        graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+
+        let native = graph[collider].as_collider().native.get();
+        let mass_properties = graph
+            .physics
+            .colliders
+            .get(native)
+            .unwrap()
+            .mass_properties();
+        assert!((mass_properties.mass() - 5.0).abs() < 1.0e-5);
+        assert_eq!(
+            mass_properties.local_com.coords,
+            Vector3::new(0.0, 0.25, 0.0)
+        );
+
+        // Without explicit mass the density defines the mass again: 1x1x1 cube, density 2.
+        let collider_ref = graph[collider].as_collider_mut();
+        collider_ref.set_mass(None);
+        collider_ref.set_center_of_mass(None);
+        assert!(collider_ref.has_change(ColliderChanges::MASS | ColliderChanges::CENTER_OF_MASS));
+        graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+
+        let collider_ref = graph[collider].as_collider();
+        assert!(!collider_ref.needs_sync_model());
+        let mass_properties = graph
+            .physics
+            .colliders
+            .get(native)
+            .unwrap()
+            .mass_properties();
+        assert!((mass_properties.mass() - 2.0).abs() < 1.0e-5);
+        assert_eq!(mass_properties.local_com.coords, Vector3::default());
+
+        let copy = collider_ref.clone();
+        assert_eq!(copy.mass(), None);
+        assert_eq!(copy.center_of_mass(), None);
+        assert_eq!(copy.native.get(), ColliderHandle::invalid());
+
+        // Explicit values must survive cloning and copying to another graph.
+        let center_of_mass = Some(Vector3::new(1.0, 2.0, 3.0));
+        let collider_ref = graph[collider].as_collider_mut();
+        collider_ref.set_mass(Some(7.0));
+        collider_ref.set_center_of_mass(center_of_mass);
+        let copy = collider_ref.clone();
+        assert_eq!(copy.mass(), Some(7.0));
+        assert_eq!(copy.center_of_mass(), center_of_mass);
+
+        let mut other_graph = Graph::new();
+        let (copy, _) = graph.copy_node(
+            collider,
+            &mut other_graph,
+            &mut |_, _| true,
+            &mut |_, _| {},
+            &mut |_, _, _| {},
+        );
+        let copy = other_graph[copy].as_collider();
+        assert_eq!(copy.mass(), Some(7.0));
+        assert_eq!(copy.center_of_mass(), center_of_mass);
+    }
+
+    #[test]
+    fn test_collider_shape_compute_aabb() {
+        let mut graph = Graph::new();
+
+        let aabb = |shape: ColliderShape, graph: &Graph| {
+            shape.compute_aabb(graph).map(|aabb| (aabb.min, aabb.max))
+        };
+
+        assert_eq!(
+            aabb(ColliderShape::ball(2.0), &graph),
+            Some((Vector3::repeat(-2.0), Vector3::repeat(2.0)))
+        );
+        assert_eq!(
+            aabb(ColliderShape::cylinder(1.0, 0.5), &graph),
+            Some((Vector3::new(-0.5, -1.0, -0.5), Vector3::new(0.5, 1.0, 0.5)))
+        );
+        assert_eq!(
+            aabb(ColliderShape::cuboid(1.0, 2.0, 3.0), &graph),
+            Some((Vector3::new(-1.0, -2.0, -3.0), Vector3::new(1.0, 2.0, 3.0)))
+        );
+        assert_eq!(
+            aabb(ColliderShape::capsule_y(1.0, 0.5), &graph),
+            Some((Vector3::new(-0.5, -1.5, -0.5), Vector3::new(0.5, 1.5, 0.5)))
+        );
+        assert_eq!(
+            aabb(
+                ColliderShape::triangle(
+                    Vector3::new(0.0, 0.0, 0.0),
+                    Vector3::new(1.0, 2.0, 0.0),
+                    Vector3::new(-1.0, 0.0, 3.0)
+                ),
+                &graph
+            ),
+            Some((Vector3::new(-1.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 3.0)))
+        );
+        assert_eq!(aabb(ColliderShape::convex_hull(vec![]), &graph), None);
+
+        let mesh = MeshBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(10.0, 0.0, 0.0))
+                    .build(),
+            ),
+        )
+        .with_surfaces(vec![SurfaceBuilder::new(SurfaceResource::new_ok(
+            ResourceKind::Embedded,
+            SurfaceData::make_cube(Matrix4::identity()),
+        ))
+        .build()])
+        .build(&mut graph);
+        graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+
+        assert_eq!(
+            aabb(ColliderShape::trimesh(vec![GeometrySource(mesh)]), &graph),
+            Some((Vector3::new(9.5, -0.5, -0.5), Vector3::new(10.5, 0.5, 0.5)))
+        );
+        assert_eq!(
+            aabb(
+                ColliderShape::trimesh(vec![GeometrySource(mesh), GeometrySource::default()]),
+                &graph
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_collider_shape_scaled() {
+        let scale = Vector3::new(2.0, -3.0, 4.0);
+        assert_eq!(
+            ColliderShape::ball(1.0).scaled(scale),
+            ColliderShape::ball(4.0)
+        );
+        assert_eq!(
+            ColliderShape::cylinder(1.0, 1.0).scaled(scale),
+            ColliderShape::cylinder(3.0, 4.0)
+        );
+        assert_eq!(
+            ColliderShape::cuboid(1.0, 1.0, 1.0).scaled(scale),
+            ColliderShape::cuboid(2.0, 3.0, 4.0)
+        );
+        assert_eq!(
+            ColliderShape::capsule_y(1.0, 0.5).scaled(scale),
+            ColliderShape::capsule(
+                Vector3::new(0.0, -3.0, 0.0),
+                Vector3::new(0.0, 3.0, 0.0),
+                2.0
+            )
+        );
+        let trimesh = ColliderShape::trimesh(vec![GeometrySource::default()]);
+        assert_eq!(trimesh.scaled(scale), trimesh);
+    }
+
+    #[test]
+    fn test_collider_follows_scale() {
+        let mut graph = Graph::new();
+        let collider = ColliderBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_scale(Vector3::new(2.0, 1.0, 3.0))
+                    .build(),
+            ),
+        )
+        .with_shape(ColliderShape::cuboid(0.5, 0.5, 0.5))
+        .build(&mut graph);
+        RigidBodyBuilder::new(BaseBuilder::new().with_children(&[collider]))
+            .with_body_type(RigidBodyType::Static)
+            .build(&mut graph);
+        // The native collider is created on the second update.
+        for _ in 0..2 {
+            graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+        }
+
+        let half_extents = |graph: &Graph| {
+            let native = graph[collider].as_collider().native.get();
+            graph
+                .physics
+                .colliders
+                .get(native)
+                .unwrap()
+                .shape()
+                .as_cuboid()
+                .unwrap()
+                .half_extents
+        };
+        assert_eq!(half_extents(&graph), Vector3::new(1.0, 0.5, 1.5));
+        assert!(!graph[collider].as_collider().needs_sync_model());
+
+        graph[collider]
+            .local_transform_mut()
+            .set_scale(Vector3::new(1.0, 4.0, 1.0));
+        graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+        assert_eq!(half_extents(&graph), Vector3::new(0.5, 2.0, 0.5));
+        assert!(!graph[collider].as_collider().needs_sync_model());
+    }
+
+    #[test]
+    fn test_collider_rotation_does_not_rebuild_shape() {
+        let mut graph = Graph::new();
+        let collider = ColliderBuilder::new(BaseBuilder::new())
+            .with_shape(ColliderShape::cuboid(0.5, 0.5, 0.5))
+            .build(&mut graph);
+        let axis = Unit::new_normalize(Vector3::new(1.0, 2.0, 3.0));
+        // The norms of the basis vectors of these rotations are off by a few ULPs.
+        let body = RigidBodyBuilder::new(
+            BaseBuilder::new()
+                .with_local_transform(
+                    TransformBuilder::new()
+                        .with_local_rotation(UnitQuaternion::from_axis_angle(&axis, 0.7))
+                        .build(),
+                )
+                .with_children(&[collider]),
+        )
+        .with_body_type(RigidBodyType::Static)
+        .build(&mut graph);
+        for _ in 0..2 {
+            graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+        }
+        let collider_ref = graph[collider].as_collider();
+        assert_ne!(collider_ref.native.get(), ColliderHandle::invalid());
+        assert!(!collider_ref.has_change(ColliderChanges::SHAPE));
+
+        graph[body]
+            .local_transform_mut()
+            .set_rotation(UnitQuaternion::from_axis_angle(&axis, 0.8));
+        graph.update_hierarchical_data();
+        assert!(!graph[collider]
+            .as_collider()
+            .has_change(ColliderChanges::SHAPE));
+    }
+
+    #[test]
+    fn test_collider_node_lookup() {
+        let mut graph = Graph::new();
+        let collider = ColliderBuilder::new(BaseBuilder::new()).build(&mut graph);
+        RigidBodyBuilder::new(BaseBuilder::new().with_children(&[collider])).build(&mut graph);
+        // Need to call two times for the native collider to be created.
+        graph.update(Vector2::new(800.0, 600.0), 1.0, Default::default());
+        graph.update(Vector2::new(800.0, 600.0), 1.0, Default::default());
+
+        let native = graph[collider].as_collider().native.get();
+        assert_eq!(graph.physics.collider_node(native), collider);
+        assert!(graph
+            .physics
+            .collider_node(ColliderHandle::invalid())
+            .is_none());
+    }
+
+    #[test]
+    fn test_collider_contact_forces() {
+        let mut graph = Graph::new();
+
+        let ground = ColliderBuilder::new(BaseBuilder::new())
+            .with_shape(ColliderShape::cuboid(5.0, 0.5, 5.0))
+            .build(&mut graph);
+        RigidBodyBuilder::new(BaseBuilder::new().with_children(&[ground]))
+            .with_body_type(RigidBodyType::Static)
+            .build(&mut graph);
+
+        let sensor = ColliderBuilder::new(BaseBuilder::new())
+            .with_shape(ColliderShape::cuboid(5.0, 0.5, 5.0))
+            .with_sensor(true)
+            .build(&mut graph);
+        RigidBodyBuilder::new(BaseBuilder::new().with_children(&[sensor]))
+            .with_body_type(RigidBodyType::Static)
+            .build(&mut graph);
+
+        let cube = ColliderBuilder::new(BaseBuilder::new())
+            .with_shape(ColliderShape::cuboid(0.5, 0.5, 0.5))
+            .build(&mut graph);
+        RigidBodyBuilder::new(
+            BaseBuilder::new()
+                .with_local_transform(
+                    TransformBuilder::new()
+                        .with_local_position(Vector3::new(0.0, 0.99, 0.0))
+                        .build(),
+                )
+                .with_children(&[cube]),
+        )
+        .with_body_type(RigidBodyType::Dynamic)
+        .build(&mut graph);
+
+        for _ in 0..10 {
+            graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+        }
+
+        let forces = graph[cube]
+            .as_collider()
+            .contact_forces(&graph.physics)
+            .collect::<Vec<_>>();
+        assert_eq!(forces.len(), 1);
+        let (pair, max_impulse) = &forces[0];
+        assert!(pair.collider1 == ground || pair.collider2 == ground);
+        assert!(*max_impulse > 0.0);
+
+        let pairs = graph.physics.all_contact_pairs().collect::<Vec<_>>();
+        assert_eq!(pairs.len(), 1);
+        assert!(pairs[0] == (ground, cube) || pairs[0] == (cube, ground));
+        let pairs = graph.physics.all_intersection_pairs().collect::<Vec<_>>();
+        assert_eq!(pairs.len(), 1);
+        assert!(pairs[0] == (sensor, cube) || pairs[0] == (cube, sensor));
+
+        assert_eq!(
+            graph[sensor]
+                .as_collider()
+                .contact_forces(&graph.physics)
+                .count(),
+            0
+        );
+    }
+
     #[test]
     fn test_collider_intersect() {
         let mut graph = Graph::new();
@@ -1138,6 +2175,9 @@ mod test {
                 .intersects(&graph.physics)
                 .count()
         );
+        // Both bodies are static, so there are no active contacts or intersections.
+        assert_eq!(graph.physics.all_contact_pairs().count(), 0);
+        assert_eq!(graph.physics.all_intersection_pairs().count(), 0);
         assert_eq!(
             1,
             graph[collider_non_sensor]
@@ -1146,4 +2186,115 @@ mod test {
                 .count()
         );
     }
+
+    /// Builds two static unit cubes at x = 0 and x = 5 and returns their bodies and colliders.
+    fn two_cubes(graph: &mut Graph) -> [(Handle<Node>, Handle<Node>); 2] {
+        [0.0, 5.0].map(|x| {
+            let collider = ColliderBuilder::new(BaseBuilder::new())
+                .with_shape(ColliderShape::cuboid(0.5, 0.5, 0.5))
+                .build(graph);
+            let body = RigidBodyBuilder::new(
+                BaseBuilder::new()
+                    .with_local_transform(
+                        TransformBuilder::new()
+                            .with_local_position(Vector3::new(x, 0.0, 0.0))
+                            .build(),
+                    )
+                    .with_children(&[collider]),
+            )
+            .with_body_type(RigidBodyType::Static)
+            .build(graph);
+            (body, collider)
+        })
+    }
+
+    #[test]
+    fn test_cast_ray_filtered() {
+        let mut graph = Graph::new();
+        let [(_, first), (second_body, second)] = two_cubes(&mut graph);
+        graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+        graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+
+        let cast = |filter: QueryFilter| {
+            let mut hits = Vec::new();
+            graph.physics.cast_ray_filtered(
+                &graph,
+                RayCastOptions {
+                    ray_origin: Point3::new(-10.0, 0.0, 0.0),
+                    ray_direction: Vector3::x(),
+                    max_len: 100.0,
+                    groups: Default::default(),
+                    sort_results: true,
+                },
+                filter,
+                &mut hits,
+            );
+            hits.into_iter().map(|hit| hit.collider).collect::<Vec<_>>()
+        };
+
+        assert_eq!(cast(QueryFilter::default()), vec![first, second]);
+        assert_eq!(
+            cast(QueryFilter {
+                exclude_collider: Some(first),
+                ..Default::default()
+            }),
+            vec![second]
+        );
+        assert_eq!(
+            cast(QueryFilter {
+                exclude_rigid_body: Some(second_body),
+                ..Default::default()
+            }),
+            vec![first]
+        );
+        let predicate = |handle: Handle<Node>, _: &Collider| handle != first;
+        assert_eq!(
+            cast(QueryFilter {
+                predicate: Some(&predicate),
+                ..Default::default()
+            }),
+            vec![second]
+        );
+    }
+
+    #[test]
+    fn test_cast_collider_shape() {
+        let mut graph = Graph::new();
+        let [(_, first), _] = two_cubes(&mut graph);
+        graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+        graph.update(Vector2::new(800.0, 600.0), 1.0 / 60.0, Default::default());
+
+        let position = Isometry3::translation(-10.0, 0.0, 0.0);
+        let cast = |shape: &ColliderShape, velocity: Vector3<f32>| {
+            graph.physics.cast_collider_shape(
+                &graph,
+                shape,
+                &position,
+                &velocity,
+                100.0,
+                Default::default(),
+            )
+        };
+
+        let (hit, toi) = cast(&ColliderShape::ball(0.5), Vector3::x())
+            .unwrap()
+            .unwrap();
+        assert_eq!(hit, first);
+        assert!((toi.toi - 9.0).abs() < 1.0e-3);
+        assert!(cast(&ColliderShape::ball(0.5), Vector3::y())
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            cast(&ColliderShape::trimesh(Vec::new()), Vector3::x()).unwrap_err(),
+            ShapeCastError::UnsupportedShape
+        );
+        assert_eq!(
+            cast(
+                &ColliderShape::convex_hull(vec![Vector3::zeros(), Vector3::x()]),
+                Vector3::x()
+            )
+            .unwrap_err(),
+            ShapeCastError::InvalidShape
+        );
+    }
 }
@@ -39,7 +39,7 @@ use crate::{
     graph::{BaseSceneGraph, SceneGraphNode},
     scene::{
         base::{Base, BaseBuilder},
-        collider::InteractionGroups,
+        collider::{ColliderChanges, InteractionGroups},
         dim2::{
             physics::{ContactPair, IntersectionPair, PhysicsWorld},
             rigidbody::RigidBody,
@@ -180,6 +180,13 @@ pub struct TileMapShape {
     pub tile_map: GeometrySource,
     /// Name of a collider layer in the tile map's tile set.
     pub layer_name: ImmutableString,
+    /// If true, the shape is built from the outline of the tile colliders instead of from
+    /// the triangles of every tile. Collinear outline edges of neighbouring tiles are merged,
+    /// which greatly reduces the size of the shape for long walls. Unlike the triangles,
+    /// the outline only collides at its edges and does not detect objects that are entirely
+    /// inside of it.
+    #[visit(optional)]
+    pub welded_outline: bool,
 }
 
 /// Possible collider shapes.
@@ -558,16 +565,80 @@ impl Collider {
         physics.intersections_with(self.native.get())
     }
 
+    /// Returns `true` if any of the properties defined by the given flags was changed and is not
+    /// yet synced with the physics engine. See [`ColliderChanges`] docs for more info.
+    pub fn has_change(&self, flags: ColliderChanges) -> bool {
+        self.changes().intersects(flags)
+    }
+
+    /// Returns a set of properties that were changed and are not yet synced with the physics engine.
+    pub fn changes(&self) -> ColliderChanges {
+        let mut changes = ColliderChanges::empty();
+        changes.set(ColliderChanges::SHAPE, self.shape.need_sync());
+        changes.set(ColliderChanges::FRICTION, self.friction.need_sync());
+        changes.set(ColliderChanges::DENSITY, self.density.need_sync());
+        changes.set(ColliderChanges::RESTITUTION, self.restitution.need_sync());
+        changes.set(ColliderChanges::IS_SENSOR, self.is_sensor.need_sync());
+        changes.set(
+            ColliderChanges::COLLISION_GROUPS,
+            self.collision_groups.need_sync(),
+        );
+        changes.set(
+            ColliderChanges::SOLVER_GROUPS,
+            self.solver_groups.need_sync(),
+        );
+        changes.set(
+            ColliderChanges::FRICTION_COMBINE_RULE,
+            self.friction_combine_rule.need_sync(),
+        );
+        changes.set(
+            ColliderChanges::RESTITUTION_COMBINE_RULE,
+            self.restitution_combine_rule.need_sync(),
+        );
+        changes
+    }
+
+    /// Marks the properties defined by the given flags as synced with the physics engine, so the
+    /// engine will not sync them on its own. This is useful for custom physics integrations that
+    /// apply the changes on their own (for example, to update only friction without rebuilding
+    /// the shape).
+    ///
+    /// # Important notes
+    ///
+    /// Acknowledging changes that were not actually applied to the physics engine will desync the
+    /// collider node and its native representation.
+    pub fn acknowledge_changes(&self, flags: ColliderChanges) {
+        if flags.contains(ColliderChanges::SHAPE) {
+            self.shape.mark_synced();
+        }
+        if flags.contains(ColliderChanges::FRICTION) {
+            self.friction.mark_synced();
+        }
+        if flags.contains(ColliderChanges::DENSITY) {
+            self.density.mark_synced();
+        }
+        if flags.contains(ColliderChanges::RESTITUTION) {
+            self.restitution.mark_synced();
+        }
+        if flags.contains(ColliderChanges::IS_SENSOR) {
+            self.is_sensor.mark_synced();
+        }
+        if flags.contains(ColliderChanges::COLLISION_GROUPS) {
+            self.collision_groups.mark_synced();
+        }
+        if flags.contains(ColliderChanges::SOLVER_GROUPS) {
+            self.solver_groups.mark_synced();
+        }
+        if flags.contains(ColliderChanges::FRICTION_COMBINE_RULE) {
+            self.friction_combine_rule.mark_synced();
+        }
+        if flags.contains(ColliderChanges::RESTITUTION_COMBINE_RULE) {
+            self.restitution_combine_rule.mark_synced();
+        }
+    }
+
     pub(crate) fn needs_sync_model(&self) -> bool {
-        self.shape.need_sync()
-            || self.friction.need_sync()
-            || self.density.need_sync()
-            || self.restitution.need_sync()
-            || self.is_sensor.need_sync()
-            || self.collision_groups.need_sync()
-            || self.solver_groups.need_sync()
-            || self.friction_combine_rule.need_sync()
-            || self.restitution_combine_rule.need_sync()
+        !self.changes().is_empty()
     }
 }
 
@@ -799,15 +870,21 @@ impl ColliderBuilder {
 #[cfg(test)]
 mod test {
 
-    use crate::core::algebra::Vector2;
+    use crate::core::{
+        algebra::{Point2, Vector2, Vector3},
+        pool::Handle,
+    };
     use crate::scene::{
         base::BaseBuilder,
         dim2::{
-            collider::{ColliderBuilder, ColliderShape},
+            collider::{Collider, ColliderBuilder, ColliderShape},
+            physics::{QueryFilter, RayCastOptions},
             rigidbody::RigidBodyBuilder,
         },
         graph::Graph,
+        node::Node,
         rigidbody::RigidBodyType,
+        transform::TransformBuilder,
     };
 
     #[test]
@@ -867,4 +944,74 @@ mod test {
                 .count()
         );
     }
+
+    /// Builds two static unit squares at x = 0 and x = 5 and returns their bodies and colliders.
+    fn two_squares(graph: &mut Graph) -> [(Handle<Node>, Handle<Node>); 2] {
+        [0.0, 5.0].map(|x| {
+            let collider = ColliderBuilder::new(BaseBuilder::new())
+                .with_shape(ColliderShape::cuboid(0.5, 0.5))
+                .build(graph);
+            let body = RigidBodyBuilder::new(
+                BaseBuilder::new()
+                    .with_local_transform(
+                        TransformBuilder::new()
+                            .with_local_position(Vector3::new(x, 0.0, 0.0))
+                            .build(),
+                    )
+                    .with_children(&[collider]),
+            )
+            .with_body_type(RigidBodyType::Static)
+            .build(graph);
+            (body, collider)
+        })
+    }
+
+    #[test]
+    fn test_cast_ray_filtered_2d() {
+        let mut graph = Graph::new();
+        let [(_, first), (second_body, second)] = two_squares(&mut graph);
+        graph.update(Vector2::new(800.0, 600.0), 1.0, Default::default());
+        graph.update(Vector2::new(800.0, 600.0), 1.0, Default::default());
+
+        let cast = |filter: QueryFilter| {
+            let mut hits = Vec::new();
+            graph.physics2d.cast_ray_filtered(
+                &graph,
+                RayCastOptions {
+                    ray_origin: Point2::new(-10.0, 0.0),
+                    ray_direction: Vector2::x(),
+                    max_len: 100.0,
+                    groups: Default::default(),
+                    sort_results: true,
+                },
+                filter,
+                &mut hits,
+            );
+            hits.into_iter().map(|hit| hit.collider).collect::<Vec<_>>()
+        };
+
+        assert_eq!(cast(QueryFilter::default()), vec![first, second]);
+        assert_eq!(
+            cast(QueryFilter {
+                exclude_collider: Some(first),
+                ..Default::default()
+            }),
+            vec![second]
+        );
+        assert_eq!(
+            cast(QueryFilter {
+                exclude_rigid_body: Some(second_body),
+                ..Default::default()
+            }),
+            vec![first]
+        );
+        let predicate = |handle: Handle<Node>, _: &Collider| handle != first;
+        assert_eq!(
+            cast(QueryFilter {
+                predicate: Some(&predicate),
+                ..Default::default()
+            }),
+            vec![second]
+        );
+    }
 }
@@ -52,7 +52,7 @@ use crate::{
             Graph, NodePool,
         },
         node::{Node, NodeTrait},
-        tilemap::TileMap,
+        tilemap::{collision_outline, weld_collision_segments, TileMap},
     },
 };
 pub use rapier2d::geometry::shape::*;
@@ -328,6 +328,7 @@ fn tile_map_to_collider_shape(
     owner_inv_transform: Matrix4<f32>,
     nodes: &NodePool,
     collider_name: &ImmutableString,
+    welded_outline: bool,
 ) -> Option<SharedShape> {
     let tile_map = nodes.try_borrow(tile_map.0)?.component_ref::<TileMap>()?;
 
@@ -361,6 +362,22 @@ fn tile_map_to_collider_shape(
         }
     }
 
+    if welded_outline {
+        let outline = collision_outline(&vertices, &triangles);
+        let segments = weld_collision_segments(&outline);
+        if segments.is_empty() {
+            return None;
+        }
+        let points = segments
+            .iter()
+            .flat_map(|s| [Point2::from(s.start), Point2::from(s.end)])
+            .collect::<Vec<_>>();
+        let indices = (0..segments.len() as u32)
+            .map(|i| [2 * i, 2 * i + 1])
+            .collect::<Vec<_>>();
+        return Some(SharedShape::polyline(points, Some(indices)));
+    }
+
     SharedShape::trimesh(vertices, triangles).ok()
 }
 
@@ -398,7 +415,14 @@ fn collider_shape_into_native_shape(
         ColliderShape::TileMap(TileMapShape {
             tile_map,
             layer_name: collider_layer_name,
-        }) => tile_map_to_collider_shape(tile_map, owner_inv_transform, nodes, collider_layer_name),
+            welded_outline,
+        }) => tile_map_to_collider_shape(
+            tile_map,
+            owner_inv_transform,
+            nodes,
+            collider_layer_name,
+            *welded_outline,
+        ),
     }
 }
 
@@ -533,7 +557,33 @@ pub struct QueryFilter<'a> {
     /// If set, any collider attached to this rigid-body will be excluded from the scene query.
     pub exclude_rigid_body: Option<Handle<Node>>,
     /// If set, any collider for which this closure returns false will be excluded from the scene query.
-    pub predicate: Option<&'a dyn Fn(Handle<Node>, &collider::Collider) -> bool>,
+    pub predicate: Option<&'a dyn Fn(Handle<Node>, &dim2::collider::Collider) -> bool>,
+}
+
+/// Converts the filter into the filter of the physics engine, using the given predicate in place of
+/// the predicate of the filter.
+fn native_query_filter<'b>(
+    graph: &Graph,
+    filter: &QueryFilter,
+    predicate: &'b dyn Fn(ColliderHandle, &Collider) -> bool,
+) -> rapier2d::pipeline::QueryFilter<'b> {
+    rapier2d::pipeline::QueryFilter {
+        flags: rapier2d::pipeline::QueryFilterFlags::from_bits(filter.flags.bits()).unwrap(),
+        groups: filter.groups.map(|g| {
+            InteractionGroups::new(u32_to_group(g.memberships.0), u32_to_group(g.filter.0))
+        }),
+        exclude_collider: filter
+            .exclude_collider
+            .and_then(|h| graph.try_get(h))
+            .and_then(|n| n.component_ref::<dim2::collider::Collider>())
+            .map(|c| c.native.get()),
+        exclude_rigid_body: filter
+            .exclude_rigid_body
+            .and_then(|h| graph.try_get(h))
+            .and_then(|n| n.component_ref::<dim2::rigidbody::RigidBody>())
+            .map(|c| c.native.get()),
+        predicate: Some(predicate),
+    }
 }
 
 /// The result of a time-of-impact (TOI) computation.
@@ -713,6 +763,52 @@ impl PhysicsWorld {
 
     /// Casts a ray with given options.
     pub fn cast_ray<S: QueryResultsStorage>(&self, opts: RayCastOptions, query_buffer: &mut S) {
+        let filter = rapier2d::pipeline::QueryFilter::new().groups(InteractionGroups::new(
+            u32_to_group(opts.groups.memberships.0),
+            u32_to_group(opts.groups.filter.0),
+        ));
+        self.cast_ray_with_native_filter(opts, filter, query_buffer)
+    }
+
+    /// Casts a ray with given options, skipping every collider that is rejected by the given filter,
+    /// for example the colliders of the body that casts the ray. If the filter has no groups,
+    /// the groups of the options are used instead.
+    pub fn cast_ray_filtered<S: QueryResultsStorage>(
+        &self,
+        graph: &Graph,
+        opts: RayCastOptions,
+        filter: QueryFilter,
+        query_buffer: &mut S,
+    ) {
+        let predicate = |handle: ColliderHandle, _: &Collider| -> bool {
+            if let Some(pred) = filter.predicate {
+                let h = Handle::decode_from_u128(self.colliders.get(handle).unwrap().user_data);
+                pred(
+                    h,
+                    graph
+                        .node(h)
+                        .component_ref::<dim2::collider::Collider>()
+                        .unwrap(),
+                )
+            } else {
+                true
+            }
+        };
+        let groups = filter.groups.unwrap_or(opts.groups);
+        let filter = QueryFilter {
+            groups: Some(groups),
+            ..filter
+        };
+        let native_filter = native_query_filter(graph, &filter, &predicate);
+        self.cast_ray_with_native_filter(opts, native_filter, query_buffer)
+    }
+
+    fn cast_ray_with_native_filter<S: QueryResultsStorage>(
+        &self,
+        opts: RayCastOptions,
+        filter: rapier2d::pipeline::QueryFilter,
+        query_buffer: &mut S,
+    ) {
         let time = instant::Instant::now();
 
         let mut query = self.query.borrow_mut();
@@ -737,10 +833,7 @@ impl PhysicsWorld {
             &ray,
             opts.max_len,
             true,
-            rapier2d::pipeline::QueryFilter::new().groups(InteractionGroups::new(
-                u32_to_group(opts.groups.memberships.0),
-                u32_to_group(opts.groups.filter.0),
-            )),
+            filter,
             |handle, intersection| {
                 query_buffer.push(Intersection {
                     collider: Handle::decode_from_u128(
@@ -805,30 +898,17 @@ impl PhysicsWorld {
                 let h = Handle::decode_from_u128(self.colliders.get(handle).unwrap().user_data);
                 pred(
                     h,
-                    graph.node(h).component_ref::<collider::Collider>().unwrap(),
+                    graph
+                        .node(h)
+                        .component_ref::<dim2::collider::Collider>()
+                        .unwrap(),
                 )
             } else {
                 true
             }
         };
 
-        let filter = rapier2d::pipeline::QueryFilter {
-            flags: rapier2d::pipeline::QueryFilterFlags::from_bits(filter.flags.bits()).unwrap(),
-            groups: filter.groups.map(|g| {
-                InteractionGroups::new(u32_to_group(g.memberships.0), u32_to_group(g.filter.0))
-            }),
-            exclude_collider: filter
-                .exclude_collider
-                .and_then(|h| graph.try_get(h))
-                .and_then(|n| n.component_ref::<dim2::collider::Collider>())
-                .map(|c| c.native.get()),
-            exclude_rigid_body: filter
-                .exclude_collider
-                .and_then(|h| graph.try_get(h))
-                .and_then(|n| n.component_ref::<dim2::rigidbody::RigidBody>())
-                .map(|c| c.native.get()),
-            predicate: Some(&predicate),
-        };
+        let filter = native_query_filter(graph, &filter, &predicate);
 
         let query = self.query.borrow_mut();
 
@@ -1337,6 +1417,16 @@ impl PhysicsWorld {
             .contact_pairs()
             .filter_map(|c| ContactPair::from_native(c, self))
     }
+
+    /// Returns a handle of the scene node that owns the native collider with the given handle, or
+    /// [`Handle::NONE`] if there's no such collider. This is the inverse of the `native` handle
+    /// that every collider node stores.
+    pub fn collider_node(&self, handle: ColliderHandle) -> Handle<Node> {
+        self.colliders
+            .get(handle)
+            .map(|collider| Handle::decode_from_u128(collider.user_data))
+            .unwrap_or_default()
+    }
 }
 
 impl Default for PhysicsWorld {
@@ -40,7 +40,7 @@ use crate::{
     },
     scene::{
         self,
-        collider::{self, ColliderShape, GeometrySource},
+        collider::{self, ColliderChanges, ColliderShape, GeometrySource},
         debug::SceneDrawingContext,
         graph::{isometric_global_transform, Graph, NodePool},
         joint::{JointLocalFrames, JointParams},
@@ -61,8 +61,8 @@ use rapier3d::{
         RigidBodyActivation, RigidBodyBuilder, RigidBodyHandle, RigidBodySet, RigidBodyType,
     },
     geometry::{
-        Collider, ColliderBuilder, ColliderHandle, ColliderSet, Cuboid, DefaultBroadPhase,
-        InteractionGroups, NarrowPhase, Ray, SharedShape,
+        Collider, ColliderBuilder, ColliderHandle, ColliderMassProps, ColliderSet, Cuboid,
+        DefaultBroadPhase, InteractionGroups, NarrowPhase, Ray, SharedShape,
     },
     parry::{query::ShapeCastOptions, shape::HeightField},
     pipeline::{DebugRenderPipeline, EventHandler, PhysicsPipeline, QueryPipeline},
@@ -721,6 +721,27 @@ fn make_heightfield(terrain: &Terrain) -> Option<SharedShape> {
     )]))
 }
 
+/// Computes mass properties of a native collider from the density, the explicit mass and the
+/// explicit center of mass of the collider node. Explicit mass has priority over density.
+fn collider_mass_properties(
+    collider_node: &scene::collider::Collider,
+    shape: &dyn Shape,
+) -> ColliderMassProps {
+    let mass_props = match (collider_node.mass(), collider_node.density()) {
+        (Some(mass), _) => ColliderMassProps::Mass(mass),
+        (None, Some(density)) => ColliderMassProps::Density(density),
+        (None, None) => ColliderMassProps::default(),
+    };
+
+    if let Some(center_of_mass) = collider_node.center_of_mass() {
+        let mut mass_properties = mass_props.mass_properties(shape);
+        mass_properties.local_com = Point3::from(center_of_mass);
+        ColliderMassProps::MassProperties(Box::new(mass_properties))
+    } else {
+        mass_props
+    }
+}
+
 // Converts descriptor in a shared shape.
 fn collider_shape_into_native_shape(
     shape: &ColliderShape,
@@ -772,6 +793,13 @@ fn collider_shape_into_native_shape(
             .try_borrow(polyhedron.geometry_source.0)
             .and_then(|n| n.cast::<Mesh>())
             .map(|mesh| make_polyhedron_shape(owner_inv_global_transform, mesh)),
+        ColliderShape::ConvexHull(hull) => SharedShape::convex_hull(
+            &hull
+                .points
+                .iter()
+                .map(|p| Point3::from(*p))
+                .collect::<Vec<_>>(),
+        ),
     }
 }
 
@@ -1073,6 +1101,32 @@ pub struct QueryFilter<'a> {
     pub predicate: Option<&'a dyn Fn(Handle<Node>, &collider::Collider) -> bool>,
 }
 
+/// Converts the filter into the filter of the physics engine, using the given predicate in place of
+/// the predicate of the filter.
+fn native_query_filter<'b>(
+    graph: &Graph,
+    filter: &QueryFilter,
+    predicate: &'b dyn Fn(ColliderHandle, &Collider) -> bool,
+) -> rapier3d::pipeline::QueryFilter<'b> {
+    rapier3d::pipeline::QueryFilter {
+        flags: rapier3d::pipeline::QueryFilterFlags::from_bits(filter.flags.bits()).unwrap(),
+        groups: filter.groups.map(|g| {
+            InteractionGroups::new(u32_to_group(g.memberships.0), u32_to_group(g.filter.0))
+        }),
+        exclude_collider: filter
+            .exclude_collider
+            .and_then(|h| graph.try_get(h))
+            .and_then(|n| n.component_ref::<collider::Collider>())
+            .map(|c| c.native.get()),
+        exclude_rigid_body: filter
+            .exclude_rigid_body
+            .and_then(|h| graph.try_get(h))
+            .and_then(|n| n.component_ref::<rigidbody::RigidBody>())
+            .map(|c| c.native.get()),
+        predicate: Some(predicate),
+    }
+}
+
 /// The result of a time-of-impact (TOI) computation.
 #[derive(Copy, Clone, Debug)]
 pub struct TOI {
@@ -1098,6 +1152,29 @@ pub struct TOI {
     pub status: collider::TOIStatus,
 }
 
+/// An error of [`PhysicsWorld::cast_collider_shape`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShapeCastError {
+    /// The shape is a triangle mesh or a height field, which cannot be swept.
+    UnsupportedShape,
+    /// The native shape could not be built, for example because a convex hull has too few points
+    /// or the geometry source of a polyhedron is not a mesh.
+    InvalidShape,
+}
+
+impl std::fmt::Display for ShapeCastError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShapeCastError::UnsupportedShape => {
+                write!(f, "Triangle mesh and height field shapes cannot be cast.")
+            }
+            ShapeCastError::InvalidShape => write!(f, "Unable to build the shape to cast."),
+        }
+    }
+}
+
+impl std::error::Error for ShapeCastError {}
+
 impl PhysicsWorld {
     /// Creates a new instance of the physics world.
     pub(super) fn new() -> Self {
@@ -1250,6 +1327,49 @@ impl PhysicsWorld {
 
     /// Casts a ray with given options.
     pub fn cast_ray<S: QueryResultsStorage>(&self, opts: RayCastOptions, query_buffer: &mut S) {
+        let filter = rapier3d::pipeline::QueryFilter::new().groups(InteractionGroups::new(
+            u32_to_group(opts.groups.memberships.0),
+            u32_to_group(opts.groups.filter.0),
+        ));
+        self.cast_ray_with_native_filter(opts, filter, query_buffer)
+    }
+
+    /// Casts a ray with given options, skipping every collider that is rejected by the given filter,
+    /// for example the colliders of the body that casts the ray. If the filter has no groups,
+    /// the groups of the options are used instead.
+    pub fn cast_ray_filtered<S: QueryResultsStorage>(
+        &self,
+        graph: &Graph,
+        opts: RayCastOptions,
+        filter: QueryFilter,
+        query_buffer: &mut S,
+    ) {
+        let predicate = |handle: ColliderHandle, _: &Collider| -> bool {
+            if let Some(pred) = filter.predicate {
+                let h = Handle::decode_from_u128(self.colliders.get(handle).unwrap().user_data);
+                pred(
+                    h,
+                    graph.node(h).component_ref::<collider::Collider>().unwrap(),
+                )
+            } else {
+                true
+            }
+        };
+        let groups = filter.groups.unwrap_or(opts.groups);
+        let filter = QueryFilter {
+            groups: Some(groups),
+            ..filter
+        };
+        let native_filter = native_query_filter(graph, &filter, &predicate);
+        self.cast_ray_with_native_filter(opts, native_filter, query_buffer)
+    }
+
+    fn cast_ray_with_native_filter<S: QueryResultsStorage>(
+        &self,
+        opts: RayCastOptions,
+        filter: rapier3d::pipeline::QueryFilter,
+        query_buffer: &mut S,
+    ) {
         let time = instant::Instant::now();
 
         let mut query = self.query.borrow_mut();
@@ -1274,10 +1394,7 @@ impl PhysicsWorld {
             &ray,
             opts.max_len,
             true,
-            rapier3d::pipeline::QueryFilter::new().groups(InteractionGroups::new(
-                u32_to_group(opts.groups.memberships.0),
-                u32_to_group(opts.groups.filter.0),
-            )),
+            filter,
             |handle, intersection| {
                 query_buffer.push(Intersection {
                     collider: Handle::decode_from_u128(
@@ -1349,25 +1466,13 @@ impl PhysicsWorld {
             }
         };
 
-        let filter = rapier3d::pipeline::QueryFilter {
-            flags: rapier3d::pipeline::QueryFilterFlags::from_bits(filter.flags.bits()).unwrap(),
-            groups: filter.groups.map(|g| {
-                InteractionGroups::new(u32_to_group(g.memberships.0), u32_to_group(g.filter.0))
-            }),
-            exclude_collider: filter
-                .exclude_collider
-                .and_then(|h| graph.try_get(h))
-                .and_then(|n| n.component_ref::<collider::Collider>())
-                .map(|c| c.native.get()),
-            exclude_rigid_body: filter
-                .exclude_collider
-                .and_then(|h| graph.try_get(h))
-                .and_then(|n| n.component_ref::<rigidbody::RigidBody>())
-                .map(|c| c.native.get()),
-            predicate: Some(&predicate),
-        };
+        let filter = native_query_filter(graph, &filter, &predicate);
 
-        let query = self.query.borrow_mut();
+        let mut query = self.query.borrow_mut();
+
+        // See the comment in `cast_ray_with_native_filter`, the acceleration structure must be
+        // up to date with the colliders.
+        query.update(&self.colliders);
 
         let opts = ShapeCastOptions {
             max_time_of_impact: max_toi,
@@ -1401,6 +1506,41 @@ impl PhysicsWorld {
             })
     }
 
+    /// Casts the given collider shape at a constant linear velocity and retrieves the first collider
+    /// it hits, in the same way as [`Self::cast_shape`]. The shape is converted to the native shape
+    /// in the same way as the shapes of colliders, except that the vertices of a polyhedron are taken
+    /// in world space of its mesh, relative to `shape_pos`. Only colliders with collision groups
+    /// compatible with `groups` are taken into account. Penetrating shapes stop the cast immediately.
+    ///
+    /// Triangle meshes and height fields cannot be swept and are rejected with
+    /// [`ShapeCastError::UnsupportedShape`].
+    pub fn cast_collider_shape(
+        &self,
+        graph: &Graph,
+        shape: &ColliderShape,
+        shape_pos: &Isometry3<f32>,
+        shape_vel: &Vector3<f32>,
+        max_toi: f32,
+        groups: collider::InteractionGroups,
+    ) -> Result<Option<(Handle<Node>, TOI)>, ShapeCastError> {
+        if matches!(
+            shape,
+            ColliderShape::Trimesh(_) | ColliderShape::Heightfield(_)
+        ) {
+            return Err(ShapeCastError::UnsupportedShape);
+        }
+        let native =
+            collider_shape_into_native_shape(shape, Matrix4::identity(), Handle::NONE, &graph.pool)
+                .ok_or(ShapeCastError::InvalidShape)?;
+        let filter = QueryFilter {
+            groups: Some(groups),
+            ..Default::default()
+        };
+        Ok(self.cast_shape(
+            graph, &*native.0, shape_pos, shape_vel, max_toi, true, filter,
+        ))
+    }
+
     pub(crate) fn set_rigid_body_position(
         &mut self,
         rigid_body: &scene::rigidbody::RigidBody,
@@ -1675,14 +1815,19 @@ impl PhysicsWorld {
                     collider_node
                         .restitution_combine_rule
                         .try_sync_model(|v| native.set_restitution_combine_rule(v.into()));
+                    let mass_changed = collider_node.density.need_sync()
+                        || collider_node.mass.need_sync()
+                        || collider_node.center_of_mass.need_sync()
+                        || (collider_node.has_change(ColliderChanges::SHAPE)
+                            && collider_node.center_of_mass().is_some());
                     let mut remove_collider = false;
-                    collider_node.shape.try_sync_model(|v| {
+                    if collider_node.has_change(ColliderChanges::SHAPE) {
                         let inv_global_transform = isometric_global_transform(nodes, handle)
                             .try_inverse()
                             .unwrap_or_default();
 
                         if let Some(shape) = collider_shape_into_native_shape(
-                            &v,
+                            &collider_node.scaled_shape(),
                             inv_global_transform,
                             handle,
                             nodes,
@@ -1691,7 +1836,22 @@ impl PhysicsWorld {
                         } else {
                             remove_collider = true;
                         }
-                    });
+                        collider_node.acknowledge_changes(ColliderChanges::SHAPE);
+                    }
+                    if mass_changed {
+                        match collider_mass_properties(collider_node, native.shape()) {
+                            ColliderMassProps::Density(density) => native.set_density(density),
+                            ColliderMassProps::Mass(mass) => native.set_mass(mass),
+                            ColliderMassProps::MassProperties(mass_properties) => {
+                                native.set_mass_properties(*mass_properties)
+                            }
+                        }
+                        collider_node.acknowledge_changes(
+                            ColliderChanges::DENSITY
+                                | ColliderChanges::MASS
+                                | ColliderChanges::CENTER_OF_MASS,
+                        );
+                    }
                     if remove_collider {
                         self.remove_collider(collider_node.native.get());
                         collider_node.native.set(ColliderHandle::invalid());
@@ -1708,11 +1868,12 @@ impl PhysicsWorld {
                     .unwrap();
                 let rigid_body_native = parent_body.native.get();
                 if let Some(shape) = collider_shape_into_native_shape(
-                    collider_node.shape(),
+                    &collider_node.scaled_shape(),
                     inv_global_transform,
                     handle,
                     nodes,
                 ) {
+                    let mass_properties = collider_mass_properties(collider_node, &*shape);
                     let mut builder = ColliderBuilder::new(shape)
                         .position(Isometry3 {
                             rotation: **collider_node.local_transform().rotation(),
@@ -1734,14 +1895,13 @@ impl PhysicsWorld {
                         ))
                         .sensor(collider_node.is_sensor());
 
-                    if let Some(density) = collider_node.density() {
-                        builder = builder.density(density);
-                    }
+                    builder.mass_properties = mass_properties;
 
                     let native_handle =
                         self.add_collider(handle, rigid_body_native, builder.build());
 
                     collider_node.native.set(native_handle);
+                    collider_node.native_scale.set(collider_node.global_scale());
 
                     Log::writeln(
                         MessageKind::Information,
@@ -1895,12 +2055,79 @@ impl PhysicsWorld {
             .filter_map(|c| ContactPair::from_native(c, self))
     }
 
+    pub(crate) fn contact_forces_with(
+        &self,
+        collider: ColliderHandle,
+    ) -> impl Iterator<Item = (ContactPair, f32)> + '_ {
+        self.narrow_phase
+            .contact_pairs_with(collider)
+            .filter(|c| {
+                [c.collider1, c.collider2].iter().all(|h| {
+                    self.colliders
+                        .get(*h)
+                        .is_some_and(|collider| !collider.is_sensor())
+                })
+            })
+            .filter_map(|c| {
+                let max_impulse = c
+                    .manifolds
+                    .iter()
+                    .flat_map(|m| m.points.iter())
+                    .map(|p| p.data.impulse.abs())
+                    .fold(0.0f32, f32::max);
+                Some((ContactPair::from_native(c, self)?, max_impulse))
+            })
+    }
+
     /// Returns an iterator over all contact pairs generated in this frame.
     pub fn contacts(&self) -> impl Iterator<Item = ContactPair> + '_ {
         self.narrow_phase
             .contact_pairs()
             .filter_map(|c| ContactPair::from_native(c, self))
     }
+
+    /// Returns an iterator over pairs of collider nodes that have at least one active contact in
+    /// this frame. It is a cheaper alternative to [`Self::contacts`] when only the contact graph
+    /// itself is needed. Intersections with sensors are not included, use
+    /// [`Self::all_intersection_pairs`] to get them.
+    pub fn all_contact_pairs(&self) -> impl Iterator<Item = (Handle<Node>, Handle<Node>)> + '_ {
+        self.narrow_phase
+            .contact_pairs()
+            .filter(|c| c.has_any_active_contact)
+            .filter_map(|c| self.collider_node_pair(c.collider1, c.collider2))
+    }
+
+    /// Returns an iterator over pairs of collider nodes that intersect in this frame, where at
+    /// least one of the colliders is a sensor.
+    pub fn all_intersection_pairs(
+        &self,
+    ) -> impl Iterator<Item = (Handle<Node>, Handle<Node>)> + '_ {
+        self.narrow_phase
+            .intersection_pairs()
+            .filter(|(_, _, intersecting)| *intersecting)
+            .filter_map(|(collider1, collider2, _)| self.collider_node_pair(collider1, collider2))
+    }
+
+    fn collider_node_pair(
+        &self,
+        collider1: ColliderHandle,
+        collider2: ColliderHandle,
+    ) -> Option<(Handle<Node>, Handle<Node>)> {
+        Some((
+            Handle::decode_from_u128(self.colliders.get(collider1)?.user_data),
+            Handle::decode_from_u128(self.colliders.get(collider2)?.user_data),
+        ))
+    }
+
+    /// Returns a handle of the scene node that owns the native collider with the given handle, or
+    /// [`Handle::NONE`] if there's no such collider. This is the inverse of the `native` handle
+    /// that every collider node stores.
+    pub fn collider_node(&self, handle: ColliderHandle) -> Handle<Node> {
+        self.colliders
+            .get(handle)
+            .map(|collider| Handle::decode_from_u128(collider.user_data))
+            .unwrap_or_default()
+    }
 }
 
 impl Default for PhysicsWorld {
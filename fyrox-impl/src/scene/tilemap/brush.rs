@@ -46,6 +46,7 @@ use crate::{
     scene::debug::SceneDrawingContext,
 };
 use std::{
+    cell::Cell,
     error::Error,
     fmt::{Display, Formatter},
     path::{Path, PathBuf},
@@ -100,7 +101,11 @@ pub struct TileMapBrushPage {
     pub icon: TileDefinitionHandle,
     /// The tiles on this page, organized by position.
     #[reflect(hidden)]
-    pub tiles: Tiles,
+    tiles: Tiles,
+    /// The bounds of `tiles`, calculated when first requested and kept until the tiles change.
+    #[reflect(hidden)]
+    #[visit(skip)]
+    bounds: Cell<Option<OptionTileRect>>,
 }
 
 impl TileSource for TileMapBrushPage {
@@ -113,13 +118,68 @@ impl TileSource for TileMapBrushPage {
 }
 
 impl TileMapBrushPage {
+    /// Create a page with the given icon and tiles.
+    pub fn new(icon: TileDefinitionHandle, tiles: Tiles) -> Self {
+        Self {
+            icon,
+            tiles,
+            bounds: Cell::default(),
+        }
+    }
+    /// The tiles on this page, organized by position.
+    pub fn tiles(&self) -> &Tiles {
+        &self.tiles
+    }
+    /// Mutable access to the tiles on this page. The bounding rect of the page will be
+    /// recalculated the next time it is requested.
+    pub fn tiles_mut(&mut self) -> &mut Tiles {
+        self.bounds.set(None);
+        &mut self.tiles
+    }
+    /// Put the given tile at the given position, returning the tile that was previously at that position.
+    pub fn insert_tile(
+        &mut self,
+        position: Vector2<i32>,
+        handle: TileDefinitionHandle,
+    ) -> Option<TileDefinitionHandle> {
+        if let Some(mut bounds) = self.bounds.get() {
+            bounds.push(position);
+            self.bounds.set(Some(bounds));
+        }
+        self.tiles.insert(position, handle)
+    }
+    /// Remove the tile at the given position, returning the removed tile.
+    pub fn remove_tile(&mut self, position: Vector2<i32>) -> Option<TileDefinitionHandle> {
+        let handle = self.tiles.remove(&position)?;
+        self.bounds.set(None);
+        Some(handle)
+    }
     /// The smallest Rect that contains all the tiles on this page.
+    /// The rect is cached until the tiles are modified.
     pub fn bounding_rect(&self) -> OptionTileRect {
-        let mut result = OptionTileRect::default();
-        for pos in self.tiles.keys() {
-            result.push(*pos);
+        if let Some(bounds) = self.bounds.get() {
+            return bounds;
         }
-        result
+        let bounds = self.tiles.bounding_rect();
+        self.bounds.set(Some(bounds));
+        bounds
+    }
+    /// Choose a tile to represent this page: the tile that is closest to the center of
+    /// the bounding rect of the page. Ties are broken by choosing the lowest row, and then
+    /// the leftmost column. None if the page has no tiles.
+    pub fn suggest_icon(&self) -> Option<TileDefinitionHandle> {
+        let center = self.bounding_rect().map(|rect| rect.center())?;
+        self.tiles
+            .iter()
+            .min_by_key(|(position, _)| {
+                let offset = *position - center;
+                (
+                    offset.x * offset.x + offset.y * offset.y,
+                    position.y,
+                    position.x,
+                )
+            })
+            .map(|(_, handle)| *handle)
     }
     /// The tile definition handle at the given position.
     pub fn find_tile_at_position(&self, position: Vector2<i32>) -> Option<TileDefinitionHandle> {
@@ -178,15 +238,32 @@ pub struct TileMapBrush {
     /// users to customize the organization of pages.
     #[reflect(hidden)]
     pub pages: TileGridMap<TileMapBrushPage>,
-    /// A count of changes since last save. New changes add +1. Reverting to previous
-    /// states add -1. Reverting to a state before the last save can result in negative
-    /// values. Saving is unnecessary whenever this value is 0.
+    /// Whether there were changes since the last save, see [`Self::is_dirty`].
     #[reflect(hidden)]
     #[visit(skip)]
-    pub change_count: ChangeFlag,
+    change_count: ChangeFlag,
 }
 
 impl TileMapBrush {
+    /// True if the brush was changed since it was last saved.
+    #[inline]
+    pub fn is_dirty(&self) -> bool {
+        self.change_count.needs_save()
+    }
+    /// Record that the brush has been saved, so it has no unsaved changes.
+    #[inline]
+    pub fn mark_saved(&mut self) {
+        self.change_count.reset();
+    }
+    /// Record that the brush has been changed, so it needs to be saved.
+    /// Editors should call this after modifying the pages of the brush directly.
+    /// The brush only remembers whether it has unsaved changes, not how many, because
+    /// its [`ChangeFlag`] is a single flag. That is why there is no way to add a number
+    /// of changes at once.
+    #[inline]
+    pub fn mark_changed(&mut self) {
+        self.change_count.set();
+    }
     /// True if there is a tile at the given position.
     pub fn has_tile_at(&self, page: Vector2<i32>, tile: Vector2<i32>) -> bool {
         let Some(page) = self.pages.get(&page) else {
@@ -211,6 +288,73 @@ impl TileMapBrush {
         }
         result
     }
+    /// Move the page at `from` to `to`, keeping its tiles and icon. Returns false and does
+    /// nothing if there is no page at `from` or if there is already some other page at `to`.
+    pub fn move_page(&mut self, from: Vector2<i32>, to: Vector2<i32>) -> bool {
+        if from == to {
+            return self.has_page_at(from);
+        }
+        if self.has_page_at(to) {
+            return false;
+        }
+        let Some(page) = self.pages.remove(&from) else {
+            return false;
+        };
+        self.pages.insert(to, page);
+        self.mark_changed();
+        true
+    }
+    /// Exchange the pages at the given positions. If there is a page at only one of the positions,
+    /// then that page is moved to the other position.
+    pub fn swap_pages(&mut self, a: Vector2<i32>, b: Vector2<i32>) {
+        if a == b {
+            return;
+        }
+        let page_a = self.pages.remove(&a);
+        let page_b = self.pages.remove(&b);
+        if page_a.is_none() && page_b.is_none() {
+            return;
+        }
+        if let Some(page) = page_a {
+            self.pages.insert(b, page);
+        }
+        if let Some(page) = page_b {
+            self.pages.insert(a, page);
+        }
+        self.mark_changed();
+    }
+    /// Give an icon to every page that has [`TileDefinitionHandle::EMPTY`] as its icon,
+    /// using [`TileMapBrushPage::suggest_icon`]. Pages without tiles keep the empty icon.
+    pub fn ensure_icons(&mut self) {
+        let mut changed = false;
+        for page in self.pages.values_mut() {
+            if !page.icon.is_empty() {
+                continue;
+            }
+            if let Some(icon) = page.suggest_icon() {
+                page.icon = icon;
+                changed = true;
+            }
+        }
+        if changed {
+            self.mark_changed();
+        }
+    }
+    /// Remove the page at the given position from this brush, if there is a page at that position.
+    /// The tile set of the brush is not affected.
+    pub fn remove_page(&mut self, page: Vector2<i32>) -> Option<TileMapBrushPage> {
+        let page = self.pages.remove(&page)?;
+        self.mark_changed();
+        Some(page)
+    }
+    /// The number of pages in this brush.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+    /// The number of tiles on all pages of this brush.
+    pub fn total_tile_count(&self) -> usize {
+        self.pages.values().map(|page| page.tiles.len()).sum()
+    }
     /// The handle of the tile that represents the page at the given position.
     pub fn page_icon(&self, page: Vector2<i32>) -> Option<TileDefinitionHandle> {
         self.pages.get(&page).map(|p| p.icon)
@@ -273,6 +417,22 @@ impl TileMapBrush {
         self.tile_set.is_none()
     }
 
+    /// Return true if this brush uses the given tile set, which means that the handles of this brush
+    /// are valid for tile maps that use the given tile set. The check compares resource identity
+    /// (the same resource or the same file path) and does not inspect the content of the tile sets.
+    pub fn is_compatible_with(&self, tile_set: &TileSetResource) -> bool {
+        let Some(own_tile_set) = self.tile_set.as_ref() else {
+            return false;
+        };
+        if own_tile_set == tile_set {
+            return true;
+        }
+        match (own_tile_set.kind(), tile_set.kind()) {
+            (ResourceKind::External(a), ResourceKind::External(b)) => a == b,
+            _ => false,
+        }
+    }
+
     fn palette_render_loop_without_tile_set<F>(
         &self,
         stage: TilePaletteStage,
@@ -367,6 +527,72 @@ impl TileMapBrush {
         }
     }
 
+    /// Find the references of this brush that do not resolve to a tile in the tile set of this brush,
+    /// such as references to tiles that have been deleted from the tile set. Each broken reference is
+    /// identified by its position within the brush: for [`TilePaletteStage::Tiles`] the handle
+    /// contains the page and the tile coordinates, and for [`TilePaletteStage::Pages`] both the page
+    /// and the tile coordinates of the handle are the coordinates of the page whose icon is broken,
+    /// so [`ResourceTilePosition::new`] can be used to turn each result into a position.
+    /// If the tile set is missing or not loaded, then every reference is returned.
+    /// Page icons that are [`TileDefinitionHandle::EMPTY`] are not references, so they are skipped.
+    pub fn validate(&self) -> Vec<(TilePaletteStage, TileDefinitionHandle)> {
+        let mut tile_set = self.tile_set.as_ref().map(|tile_set| tile_set.state());
+        let tile_set = tile_set.as_mut().and_then(|state| state.data());
+        let resolves = |position: ResourceTilePosition| {
+            let Some(tile_set) = tile_set.as_ref() else {
+                return false;
+            };
+            self.redirect_handle(position)
+                .is_some_and(|handle| tile_set.has_tile_at(handle.page(), handle.tile()))
+        };
+        let mut result = Vec::new();
+        for (&page_position, page) in self.pages.iter() {
+            if !page.icon.is_empty() && !resolves(ResourceTilePosition::Page(page_position)) {
+                if let Some(handle) = TileDefinitionHandle::try_new(page_position, page_position) {
+                    result.push((TilePaletteStage::Pages, handle));
+                }
+            }
+            for &tile_position in page.tiles.keys() {
+                if !resolves(ResourceTilePosition::Tile(page_position, tile_position)) {
+                    if let Some(handle) =
+                        TileDefinitionHandle::try_new(page_position, tile_position)
+                    {
+                        result.push((TilePaletteStage::Tiles, handle));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Remove the tiles of this brush that do not resolve to a tile in the tile set of this brush,
+    /// and replace the page icons that do not resolve with [`TileDefinitionHandle::EMPTY`].
+    /// See [`Self::validate`]. Returns the number of removed tiles and cleared icons.
+    /// If the tile set is missing or not loaded, then nothing is removed.
+    pub fn prune_dangling(&mut self) -> usize {
+        if !self
+            .tile_set
+            .as_ref()
+            .is_some_and(|tile_set| tile_set.is_ok())
+        {
+            return 0;
+        }
+        let broken = self.validate();
+        for (stage, handle) in broken.iter() {
+            let Some(page) = self.pages.get_mut(&handle.page()) else {
+                continue;
+            };
+            match stage {
+                TilePaletteStage::Pages => page.icon = TileDefinitionHandle::EMPTY,
+                TilePaletteStage::Tiles => drop(page.remove_tile(handle.tile())),
+            }
+        }
+        if !broken.is_empty() {
+            self.mark_changed();
+        }
+        broken.len()
+    }
+
     /// The `TileMaterialBounds` taken from the tile set for the tile in the brush at the given position.
     pub fn get_tile_bounds(&self, position: ResourceTilePosition) -> Option<TileMaterialBounds> {
         let handle = self.redirect_handle(position)?;
@@ -391,6 +617,22 @@ impl TileMapBrush {
         Ok(tile_map_brush)
     }
 
+    /// Save this tile map brush to the given path using the given resource io, so it can be
+    /// saved on platforms where the file system is not directly available. The change flag
+    /// is reset on success.
+    pub async fn save_to(
+        &mut self,
+        path: &Path,
+        io: &dyn ResourceIo,
+    ) -> Result<(), TileMapBrushResourceError> {
+        let mut visitor = Visitor::new();
+        self.visit("TileMapBrush", &mut visitor)?;
+        let bytes = visitor.save_binary_to_vec()?;
+        io.write_file(path, bytes).await?;
+        self.mark_saved();
+        Ok(())
+    }
+
     fn save(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
         let mut visitor = Visitor::new();
         self.visit("TileMapBrush", &mut visitor)?;
@@ -441,3 +683,226 @@ impl ResourceLoader for TileMapBrushLoader {
 
 /// An alias to `Resource<TileMapBrush>`.
 pub type TileMapBrushResource = Resource<TileMapBrush>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{asset::io::FsResourceIo, core::futures::executor::block_on};
+
+    fn page(icon: TileDefinitionHandle) -> TileMapBrushPage {
+        let mut tiles = Tiles::default();
+        tiles.insert(Vector2::new(0, 0), icon);
+        TileMapBrushPage::new(icon, tiles)
+    }
+
+    #[test]
+    fn page_bounding_rect_cache() {
+        let handle = TileDefinitionHandle::new(0, 0, 1, 1);
+        let mut tiles = Tiles::default();
+        for x in 0..64 {
+            for y in 0..64 {
+                tiles.insert(Vector2::new(x, y), handle);
+            }
+        }
+        let mut page = TileMapBrushPage::new(handle, tiles);
+        let rect = page.bounding_rect();
+        assert_eq!(*rect, Some(TileRect::new(0, 0, 64, 64)));
+        // Modify the tiles behind the back of the cache to prove that repeated calls
+        // do not iterate over the tiles again.
+        page.tiles.insert(Vector2::new(100, 100), handle);
+        for _ in 0..1000 {
+            assert_eq!(page.bounding_rect(), rect);
+        }
+        page.tiles.remove(&Vector2::new(100, 100));
+
+        page.insert_tile(Vector2::new(-1, 70), handle);
+        assert_eq!(*page.bounding_rect(), Some(TileRect::new(-1, 0, 65, 71)));
+        page.remove_tile(Vector2::new(-1, 70));
+        assert_eq!(page.bounding_rect(), rect);
+        page.tiles_mut().clear();
+        assert!(page.bounding_rect().is_none());
+    }
+
+    #[test]
+    fn suggest_and_ensure_icons() {
+        let handle = |x| TileDefinitionHandle::new(0, 0, x, 0);
+        let mut tiles = Tiles::default();
+        for x in 0..5 {
+            tiles.insert(Vector2::new(x, 0), handle(x as i16));
+        }
+        tiles.insert(Vector2::new(0, 4), handle(9));
+        let mut brush = TileMapBrush::default();
+        brush.pages.insert(
+            Vector2::new(0, 0),
+            TileMapBrushPage::new(TileDefinitionHandle::EMPTY, tiles),
+        );
+        // The center of the 5x5 bounds is (2, 2), so the middle of the bottom row is the closest.
+        assert_eq!(
+            brush.pages[&Vector2::new(0, 0)].suggest_icon(),
+            Some(handle(2))
+        );
+
+        // A sparse page gets one of its tiles.
+        let mut tiles = Tiles::default();
+        tiles.insert(Vector2::new(-10, -10), handle(7));
+        tiles.insert(Vector2::new(10, 10), handle(8));
+        brush.pages.insert(
+            Vector2::new(1, 0),
+            TileMapBrushPage::new(TileDefinitionHandle::EMPTY, tiles),
+        );
+        brush.pages.insert(
+            Vector2::new(2, 0),
+            TileMapBrushPage::new(TileDefinitionHandle::EMPTY, Tiles::default()),
+        );
+        brush.pages.insert(Vector2::new(3, 0), page(handle(5)));
+
+        brush.ensure_icons();
+        assert!(brush.is_dirty());
+        assert_eq!(brush.page_icon(Vector2::new(0, 0)), Some(handle(2)));
+        assert_eq!(brush.page_icon(Vector2::new(1, 0)), Some(handle(7)));
+        assert_eq!(
+            brush.page_icon(Vector2::new(2, 0)),
+            Some(TileDefinitionHandle::EMPTY)
+        );
+        assert_eq!(brush.page_icon(Vector2::new(3, 0)), Some(handle(5)));
+    }
+
+    #[test]
+    fn move_and_swap_pages() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 1);
+        let b = TileDefinitionHandle::new(0, 0, 2, 2);
+        let mut brush = TileMapBrush::default();
+        brush.pages.insert(Vector2::new(0, 0), page(a));
+        brush.pages.insert(Vector2::new(1, 0), page(b));
+
+        // Occupied or missing positions are refused without any change.
+        assert!(!brush.move_page(Vector2::new(0, 0), Vector2::new(1, 0)));
+        assert!(!brush.move_page(Vector2::new(5, 5), Vector2::new(6, 6)));
+        assert!(!brush.is_dirty());
+
+        assert!(brush.move_page(Vector2::new(0, 0), Vector2::new(0, 3)));
+        assert!(brush.is_dirty());
+        assert!(!brush.has_page_at(Vector2::new(0, 0)));
+        assert_eq!(brush.page_icon(Vector2::new(0, 3)), Some(a));
+        assert!(brush.has_tile_at(Vector2::new(0, 3), Vector2::new(0, 0)));
+
+        brush.mark_saved();
+        brush.swap_pages(Vector2::new(0, 3), Vector2::new(1, 0));
+        assert!(brush.is_dirty());
+        assert_eq!(brush.page_icon(Vector2::new(0, 3)), Some(b));
+        assert_eq!(brush.page_icon(Vector2::new(1, 0)), Some(a));
+
+        // Swapping with an empty position moves the page.
+        brush.swap_pages(Vector2::new(1, 0), Vector2::new(2, 0));
+        assert!(!brush.has_page_at(Vector2::new(1, 0)));
+        assert_eq!(brush.page_icon(Vector2::new(2, 0)), Some(a));
+        assert_eq!(brush.pages.len(), 2);
+    }
+
+    #[test]
+    fn remove_page_and_counts() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 1);
+        let mut brush = TileMapBrush::default();
+        let mut big_page = page(a);
+        big_page.insert_tile(Vector2::new(1, 0), a);
+        brush.pages.insert(Vector2::new(0, 0), big_page);
+        brush.pages.insert(Vector2::new(1, 0), page(a));
+        assert_eq!(brush.page_count(), 2);
+        assert_eq!(brush.total_tile_count(), 3);
+
+        assert!(brush.remove_page(Vector2::new(5, 5)).is_none());
+        assert!(!brush.is_dirty());
+        let removed = brush.remove_page(Vector2::new(0, 0)).unwrap();
+        assert_eq!(removed.tiles().len(), 2);
+        assert!(brush.is_dirty());
+        assert_eq!(brush.page_count(), 1);
+        assert_eq!(brush.total_tile_count(), 1);
+    }
+
+    #[test]
+    fn save_to() {
+        if !Path::new("test_output").exists() {
+            std::fs::create_dir_all("test_output").unwrap();
+        }
+        let path = Path::new("test_output/save_to.tile_map_brush");
+        let a = TileDefinitionHandle::new(0, 0, 1, 1);
+        let mut brush = TileMapBrush::default();
+        brush.pages.insert(Vector2::new(2, 3), page(a));
+        brush.mark_changed();
+        block_on(brush.save_to(path, &FsResourceIo)).unwrap();
+        assert!(!brush.is_dirty());
+
+        let resource_manager = ResourceManager::new(Arc::new(Default::default()));
+        let loaded = block_on(TileMapBrush::from_file(
+            path,
+            resource_manager,
+            &FsResourceIo,
+        ))
+        .unwrap();
+        assert_eq!(loaded.page_icon(Vector2::new(2, 3)), Some(a));
+        assert_eq!(loaded.total_tile_count(), 1);
+    }
+
+    #[test]
+    fn validate() {
+        let valid = TileDefinitionHandle::new(0, 0, 1, 1);
+        let deleted = TileDefinitionHandle::new(0, 0, 2, 2);
+        let mut tiles = TileGridMap::default();
+        tiles.insert(valid.tile(), TileDefinition::default());
+        let mut tile_set = TileSet::default();
+        tile_set.insert_page(
+            valid.page(),
+            TileSetPage {
+                icon: valid,
+                source: TileSetPageSource::Freeform(tiles),
+            },
+        );
+
+        let mut brush = TileMapBrush::default();
+        let mut first = page(valid);
+        first.insert_tile(Vector2::new(3, 0), deleted);
+        brush.pages.insert(Vector2::new(0, 0), first);
+        brush.pages.insert(Vector2::new(1, 0), page(deleted));
+        let mut broken = brush.validate();
+        broken.sort_by_key(|(stage, handle)| (*stage == TilePaletteStage::Tiles, handle.tile().x));
+        assert_eq!(broken.len(), 5);
+
+        brush.tile_set = Some(TileSetResource::new_ok(ResourceKind::Embedded, tile_set));
+        let mut broken = brush.validate();
+        broken.sort_by_key(|(stage, handle)| (*stage == TilePaletteStage::Tiles, handle.tile().x));
+        assert_eq!(
+            broken,
+            vec![
+                (
+                    TilePaletteStage::Pages,
+                    TileDefinitionHandle::new(1, 0, 1, 0)
+                ),
+                (
+                    TilePaletteStage::Tiles,
+                    TileDefinitionHandle::new(1, 0, 0, 0)
+                ),
+                (
+                    TilePaletteStage::Tiles,
+                    TileDefinitionHandle::new(0, 0, 3, 0)
+                ),
+            ]
+        );
+
+        assert_eq!(brush.prune_dangling(), 3);
+        assert!(brush.is_dirty());
+        assert!(brush.validate().is_empty());
+        assert_eq!(
+            brush.page_icon(Vector2::new(1, 0)),
+            Some(TileDefinitionHandle::EMPTY)
+        );
+        assert_eq!(brush.page_icon(Vector2::new(0, 0)), Some(valid));
+        assert_eq!(brush.total_tile_count(), 1);
+        brush.mark_saved();
+        assert_eq!(brush.prune_dangling(), 0);
+        assert!(!brush.is_dirty());
+        // Without a tile set nothing is removed.
+        brush.tile_set = None;
+        assert_eq!(brush.prune_dangling(), 0);
+        assert_eq!(brush.total_tile_count(), 1);
+    }
+}
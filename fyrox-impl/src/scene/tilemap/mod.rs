@@ -551,9 +551,7 @@ impl TileBook {
             TileBook::TileSet(r) => {
                 r.header().kind.is_external() && r.data_ref().change_count.needs_save()
             }
-            TileBook::Brush(r) => {
-                r.header().kind.is_external() && r.data_ref().change_count.needs_save()
-            }
+            TileBook::Brush(r) => r.header().kind.is_external() && r.data_ref().is_dirty(),
         }
     }
     /// Attempt to save the resource to its file, if it has one and if `change_count` not zero.
@@ -573,10 +571,10 @@ impl TileBook {
                 }
             }
             TileBook::Brush(r) => {
-                if r.header().kind.is_external() && r.data_ref().change_count.needs_save() {
+                if r.header().kind.is_external() && r.data_ref().is_dirty() {
                     let result = r.save_back();
                     if result.is_ok() {
-                        r.data_ref().change_count.reset();
+                        r.data_ref().mark_saved();
                     }
                     result
                 } else {
@@ -617,7 +615,7 @@ impl TileBook {
                 .and_then(|r| {
                     r.pages
                         .get(&page)
-                        .map(|p| p.tiles.keys().copied().collect())
+                        .map(|p| p.tiles().keys().copied().collect())
                 })
                 .unwrap_or_default(),
         }
@@ -28,11 +28,13 @@ use crate::{
     asset::{Resource, ResourceData},
     core::{
         algebra::{Matrix4, Point2, Point3, Vector2},
+        math::segment::LineSegment2,
         reflect::prelude::*,
         type_traits::prelude::*,
         visitor::prelude::*,
     },
 };
+use fxhash::FxHashMap;
 use std::{
     error::Error,
     fmt::{Debug, Display, Formatter},
@@ -158,6 +160,92 @@ impl TileCollider {
     }
 }
 
+/// Merge collinear, connected segments into longer segments, so that long walls made of many
+/// tiles may be represented by a few segments instead of one segment per tile edge.
+///
+/// Two segments are merged only if the end of one is exactly the start of the other and both
+/// point in the same direction along the same line. Segments that overlap, touch with opposite
+/// orientation, or meet at an angle are left as they are, so the collision surface is not changed.
+/// Degenerate segments (with equal end points) are removed.
+pub fn weld_collision_segments(segments: &[LineSegment2<f32>]) -> Vec<LineSegment2<f32>> {
+    fn key(point: &Vector2<f32>) -> [u32; 2] {
+        // Adding zero turns -0.0 into 0.0, so both produce the same key.
+        [(point.x + 0.0).to_bits(), (point.y + 0.0).to_bits()]
+    }
+    fn is_same_direction(a: &LineSegment2<f32>, b: &LineSegment2<f32>) -> bool {
+        let u = a.vector();
+        let v = b.vector();
+        let cross = u.x * v.y - u.y * v.x;
+        cross.abs() <= f32::EPSILON * u.norm() * v.norm() && u.dot(&v) > 0.0
+    }
+    let mut welded = segments
+        .iter()
+        .filter(|s| !s.is_degenerate())
+        .cloned()
+        .map(Some)
+        .collect::<Vec<_>>();
+    let mut starts = FxHashMap::<[u32; 2], Vec<usize>>::default();
+    for (i, segment) in welded.iter().enumerate() {
+        if let Some(segment) = segment {
+            starts.entry(key(&segment.start)).or_default().push(i);
+        }
+    }
+    for i in 0..welded.len() {
+        while let Some(current) = welded[i].clone() {
+            let Some(candidates) = starts.get_mut(&key(&current.end)) else {
+                break;
+            };
+            let Some(position) = candidates.iter().position(|&j| {
+                j != i
+                    && welded[j]
+                        .as_ref()
+                        .is_some_and(|next| is_same_direction(&current, next))
+            }) else {
+                break;
+            };
+            let j = candidates.swap_remove(position);
+            let next = welded[j].take().unwrap();
+            welded[i] = Some(LineSegment2::new(&current.start, &next.end));
+        }
+    }
+    welded.into_iter().flatten().collect()
+}
+
+/// Find the edges of the given triangles that are not shared with another triangle, so they form
+/// the outline of the area covered by the triangles. Each edge keeps the orientation it has in
+/// its triangle. Two edges are shared only if their end points are exactly equal, which is
+/// the case for the edges between neighbouring tiles of a tile map.
+pub fn collision_outline(
+    vertices: &[Point2<f32>],
+    triangles: &[[u32; 3]],
+) -> Vec<LineSegment2<f32>> {
+    fn key(point: &Point2<f32>) -> [u32; 2] {
+        [(point.x + 0.0).to_bits(), (point.y + 0.0).to_bits()]
+    }
+    let mut edges = FxHashMap::<[[u32; 2]; 2], (usize, LineSegment2<f32>)>::default();
+    for triangle in triangles {
+        for i in 0..3 {
+            let (Some(a), Some(b)) = (
+                vertices.get(triangle[i] as usize),
+                vertices.get(triangle[(i + 1) % 3] as usize),
+            ) else {
+                continue;
+            };
+            let (ka, kb) = (key(a), key(b));
+            let edge_key = if ka <= kb { [ka, kb] } else { [kb, ka] };
+            edges
+                .entry(edge_key)
+                .or_insert_with(|| (0, LineSegment2::new(&a.coords, &b.coords)))
+                .0 += 1;
+        }
+    }
+    edges
+        .into_values()
+        .filter(|(count, _)| *count == 1)
+        .map(|(_, segment)| segment)
+        .collect()
+}
+
 /// A resource to hold triangle data for a tile collider arranged in rectangle from (0,0) to (1,1).
 pub type CustomTileColliderResource = Resource<CustomTileCollider>;
 /// Triangle data for a tile collider arranged in rectangle from (0,0) to (1,1).
@@ -461,6 +549,88 @@ impl<'a> Iterator for TokenIter<'a> {
 mod tests {
     use super::*;
 
+    fn segment(x0: f32, y0: f32, x1: f32, y1: f32) -> LineSegment2<f32> {
+        LineSegment2::new(&Vector2::new(x0, y0), &Vector2::new(x1, y1))
+    }
+    fn assert_segments(mut actual: Vec<LineSegment2<f32>>, expected: &[LineSegment2<f32>]) {
+        actual.sort_by(|a, b| {
+            (a.start.x, a.start.y, a.end.x, a.end.y)
+                .partial_cmp(&(b.start.x, b.start.y, b.end.x, b.end.y))
+                .unwrap()
+        });
+        assert_eq!(actual.len(), expected.len(), "{actual:?}");
+        for (a, b) in actual.iter().zip(expected.iter()) {
+            assert_eq!((a.start, a.end), (b.start, b.end));
+        }
+    }
+    #[test]
+    fn weld_wall() {
+        let segments = (0..100)
+            .rev()
+            .map(|x| segment(x as f32, 0.0, x as f32 + 1.0, 0.0))
+            .collect::<Vec<_>>();
+        assert_segments(
+            weld_collision_segments(&segments),
+            &[segment(0.0, 0.0, 100.0, 0.0)],
+        );
+    }
+    #[test]
+    fn weld_corner() {
+        let segments = [
+            segment(0.0, 0.0, 1.0, 0.0),
+            segment(1.0, 0.0, 2.0, 0.0),
+            segment(2.0, 0.0, 2.0, 1.0),
+            segment(2.0, 1.0, 2.0, 2.0),
+        ];
+        assert_segments(
+            weld_collision_segments(&segments),
+            &[segment(0.0, 0.0, 2.0, 0.0), segment(2.0, 0.0, 2.0, 2.0)],
+        );
+    }
+    #[test]
+    fn weld_keeps_opposite_and_disconnected() {
+        let segments = [
+            segment(0.0, 0.0, 1.0, 0.0),
+            segment(2.0, 0.0, 1.0, 0.0),
+            segment(3.0, 0.0, 4.0, 0.0),
+            segment(5.0, 5.0, 5.0, 5.0),
+        ];
+        assert_segments(
+            weld_collision_segments(&segments),
+            &[
+                segment(0.0, 0.0, 1.0, 0.0),
+                segment(2.0, 0.0, 1.0, 0.0),
+                segment(3.0, 0.0, 4.0, 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn outline_of_two_tiles() {
+        let vertices = [
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(0.0, 1.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(2.0, 0.0),
+            Point2::new(2.0, 1.0),
+            Point2::new(1.0, 1.0),
+        ];
+        let triangles = [[0, 1, 2], [0, 2, 3], [4, 5, 6], [4, 6, 7]];
+        let outline = collision_outline(&vertices, &triangles);
+        assert_eq!(outline.len(), 6);
+        assert_segments(
+            weld_collision_segments(&outline),
+            &[
+                segment(0.0, 0.0, 2.0, 0.0),
+                segment(0.0, 1.0, 0.0, 0.0),
+                segment(2.0, 0.0, 2.0, 1.0),
+                segment(2.0, 1.0, 0.0, 1.0),
+            ],
+        );
+    }
+
     #[test]
     fn empty() {
         let mut iter = TokenIter::new("");
@@ -169,6 +169,27 @@ impl OptionTileRect {
             false
         }
     }
+    /// Checks if the other rectangle lies entirely within the bounds of the rectangle.
+    /// An empty rectangle contains nothing.
+    #[inline]
+    pub fn contains_rect(&self, other: TileRect) -> bool {
+        if let Some(rect) = &self.0 {
+            rect.contains_rect(other)
+        } else {
+            false
+        }
+    }
+    /// Returns the point of the rectangle that is closest to the given point,
+    /// or `None` if the rectangle is empty.
+    #[inline]
+    pub fn clamp_point(&self, p: Vector2<i32>) -> Option<Vector2<i32>> {
+        self.0.map(|rect| rect.clamp_point(p))
+    }
+    /// Returns the number of cells in the rectangle, which is zero for an empty rectangle.
+    #[inline]
+    pub fn area(&self) -> i64 {
+        self.0.map(|rect| rect.area()).unwrap_or_default()
+    }
     /// Checks if the rectangle intersects with some other rectangle.
     #[inline]
     pub fn intersects(&self, other: TileRect) -> bool {
@@ -295,6 +316,29 @@ impl TileRect {
             && pt.y < self.position.y + self.size.y
     }
 
+    /// Checks if the other rectangle lies entirely within the bounds of the rectangle.
+    #[inline]
+    pub fn contains_rect(&self, other: TileRect) -> bool {
+        other.position.x >= self.position.x
+            && other.position.y >= self.position.y
+            && other.position.x + other.size.x <= self.position.x + self.size.x
+            && other.position.y + other.size.y <= self.position.y + self.size.y
+    }
+
+    /// Returns the point of the rectangle that is closest to the given point.
+    #[inline]
+    pub fn clamp_point(&self, p: Vector2<i32>) -> Vector2<i32> {
+        let min = self.left_bottom_corner();
+        let max = self.right_top_corner().sup(&min);
+        p.sup(&min).inf(&max)
+    }
+
+    /// Returns the number of cells in the rectangle.
+    #[inline]
+    pub fn area(&self) -> i64 {
+        self.size.x.max(0) as i64 * self.size.y.max(0) as i64
+    }
+
     /// Returns center point of the rectangle.
     #[inline]
     pub fn center(&self) -> Vector2<i32> {
@@ -440,6 +484,27 @@ impl TileRect {
 mod tests {
     use super::*;
     #[test]
+    fn rect_area_and_containment() {
+        let rect = TileRect::new(-1, -2, 4, 6);
+        assert_eq!(rect.area(), 24);
+        assert!(rect.contains_rect(rect));
+        assert!(rect.contains_rect(TileRect::new(0, 0, 3, 4)));
+        assert!(!rect.contains_rect(TileRect::new(0, 0, 4, 4)));
+        assert!(!rect.contains_rect(TileRect::new(-2, 0, 1, 1)));
+        assert_eq!(rect.clamp_point(Vector2::new(0, 0)), Vector2::new(0, 0));
+        assert_eq!(rect.clamp_point(Vector2::new(10, -10)), Vector2::new(2, -2));
+        let empty = OptionTileRect::default();
+        assert_eq!(empty.area(), 0);
+        assert!(!empty.contains_rect(TileRect::new(0, 0, 1, 1)));
+        assert_eq!(empty.clamp_point(Vector2::new(1, 1)), None);
+        let rect = OptionTileRect::from(rect);
+        assert_eq!(rect.area(), 24);
+        assert_eq!(
+            rect.clamp_point(Vector2::new(-5, 5)),
+            Some(Vector2::new(-1, 3))
+        );
+    }
+    #[test]
     fn iter() {
         let mut iter = TileRect::new(2, 3, 3, 2).iter();
         assert_eq!(iter.next(), Some(Vector2::new(2, 3)));
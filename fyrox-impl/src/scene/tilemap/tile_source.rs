@@ -26,9 +26,13 @@
 use fyrox_core::swap_hash_map_entry;
 
 use crate::{
-    core::{algebra::Vector2, reflect::prelude::*, visitor::prelude::*},
+    core::{
+        algebra::Vector2,
+        reflect::prelude::*,
+        visitor::{prelude::*, PodVecView},
+    },
     fxhash::FxHashMap,
-    rand::{seq::IteratorRandom, thread_rng},
+    rand::{seq::IteratorRandom, thread_rng, Rng},
 };
 use std::{
     cmp::Ordering,
@@ -59,9 +63,62 @@ fn position_to_vector(source: PalettePosition) -> Vector2<i32> {
 #[derive(Default, Debug, Clone, PartialEq, Reflect)]
 pub struct TileGridMap<V: Debug>(FxHashMap<Vector2<i32>, V>);
 
+impl<V: Debug> TileGridMap<V> {
+    /// Creates an empty grid with space for at least the given number of tiles.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(FxHashMap::with_capacity_and_hasher(
+            capacity,
+            Default::default(),
+        ))
+    }
+}
+
+/// Grids are stored sorted by their keys, so that identical grids always produce identical output.
+/// All the keys are stored in a single array of coordinates, followed by the values in the same order.
+/// Grids that were saved as plain hash maps are still supported when loading.
 impl<V: Visit + Default + Debug> Visit for TileGridMap<V> {
     fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
-        self.0.visit(name, visitor)
+        if visitor.is_reading() {
+            // Old format is a plain hash map, which is recognizable by its "Count" field.
+            let is_legacy = {
+                let mut region = visitor.enter_region(name)?;
+                let mut count = 0u32;
+                count.visit("Count", &mut region).is_ok()
+            };
+            if is_legacy {
+                return self.0.visit(name, visitor);
+            }
+        }
+
+        let mut region = visitor.enter_region(name)?;
+
+        if region.is_reading() {
+            let mut keys = Vec::<i32>::new();
+            PodVecView::from_pod_vec(&mut keys).visit("Keys", &mut region)?;
+            let mut values = region.enter_region("Values")?;
+            self.0.clear();
+            for (i, key) in keys.chunks_exact(2).enumerate() {
+                let mut region = values.enter_region(&format!("Item{i}"))?;
+                let mut value = V::default();
+                value.visit("ItemData", &mut region)?;
+                self.0.insert(Vector2::new(key[0], key[1]), value);
+            }
+        } else {
+            let mut entries = self.0.iter_mut().collect::<Vec<_>>();
+            entries.sort_unstable_by_key(|(key, _)| (key.x, key.y));
+            let mut keys = entries
+                .iter()
+                .flat_map(|(key, _)| [key.x, key.y])
+                .collect::<Vec<_>>();
+            PodVecView::from_pod_vec(&mut keys).visit("Keys", &mut region)?;
+            let mut values = region.enter_region("Values")?;
+            for (i, (_, value)) in entries.into_iter().enumerate() {
+                let mut region = values.enter_region(&format!("Item{i}"))?;
+                value.visit("ItemData", &mut region)?;
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -186,9 +243,28 @@ impl TileDefinitionHandle {
     pub fn tile(&self) -> Vector2<i32> {
         position_to_vector(self.tile)
     }
+    /// Construct a handle for the tile at the given offset from this handle's tile, on the same page.
+    /// None is returned if the resulting tile coordinates cannot be represented as i16 coordinates.
+    pub fn offset_tile(&self, delta: Vector2<i32>) -> Option<Self> {
+        let tile = self.tile();
+        let x = tile.x.checked_add(delta.x)?;
+        let y = tile.y.checked_add(delta.y)?;
+        Self::try_new(self.page(), Vector2::new(x, y))
+    }
+    /// True if both handles refer to tiles on the same page.
+    pub fn same_page(&self, other: &Self) -> bool {
+        self.page == other.page
+    }
     /// Convert a string into a tile definition handle by finding four numbers.
     /// The first two numbers are the page coodrinates. The second two numbers are the tile coordinates.
     /// None is returned if there are more than four numbers, fewer than four numbers, or any number produces an error in parsing.
+    ///
+    /// This parser is lenient: every character other than a digit or `-` is treated as a separator,
+    /// and such characters may appear anywhere, including the start and the end of the string.
+    /// This allows it to accept the [`Display`] format `(1,2):(3,4)`, the compact format `1,2,3,4`
+    /// and even formats such as `"page":[1,2],"tile":[3,4]`, but it also means that malformed
+    /// strings such as `x1y2z3w4` are accepted. Use [`parse_display`](Self::parse_display)
+    /// to accept only the exact [`Display`] format.
     pub fn parse(s: &str) -> Option<Self> {
         let mut iter = s
             .split(|c: char| c != '-' && !c.is_ascii_digit())
@@ -203,6 +279,32 @@ impl TileDefinitionHandle {
             Some(Self::new(a, b, c, d))
         }
     }
+    /// Convert a string into a tile definition handle, accepting only the exact output
+    /// of [`Display`], such as `(1,2):(3,4)`. Unlike [`parse`](Self::parse), this rejects
+    /// whitespace, other separators, and alternative spellings of numbers such as `+1` or `01`,
+    /// so that `TileDefinitionHandle::parse_display(&handle.to_string())` always round-trips.
+    pub fn parse_display(s: &str) -> Option<Self> {
+        let (page, tile) = s.split_once("):(")?;
+        let (a, b) = page.strip_prefix('(')?.split_once(',')?;
+        let (c, d) = tile.strip_suffix(')')?.split_once(',')?;
+        let handle = Self::new(
+            a.parse().ok()?,
+            b.parse().ok()?,
+            c.parse().ok()?,
+            d.parse().ok()?,
+        );
+        // Reject everything that would not be produced by formatting the handle.
+        (handle.to_string() == s).then_some(handle)
+    }
+    /// Convert the handle into a string of four comma-separated numbers, such as `1,2,3,4`.
+    /// The first two numbers are the page coordinates, and the second two numbers are the tile coordinates.
+    /// The result can be converted back using [`parse`](Self::parse).
+    pub fn to_compact_string(&self) -> String {
+        format!(
+            "{},{},{},{}",
+            self.page.x, self.page.y, self.tile.x, self.tile.y
+        )
+    }
 }
 
 /// A region of tiles to be filled from some source of tiles.
@@ -254,6 +356,32 @@ impl TileRegion {
         self.bounds = self.bounds.deflate(dw, dh);
         self
     }
+    /// Copy the region and replace its bounds with the overlap of its bounds and the bounds of
+    /// the other region. The bounds are empty if the regions do not overlap.
+    /// The origin of `self` is preserved.
+    pub fn intersect(&self, other: &TileRegion) -> Self {
+        let mut bounds = self.bounds;
+        match *other.bounds {
+            Some(other_bounds) => bounds.clip(other_bounds),
+            None => bounds = OptionTileRect::default(),
+        }
+        Self {
+            origin: self.origin,
+            bounds,
+        }
+    }
+    /// Copy the region and replace its bounds with the smallest rectangle that contains
+    /// the bounds of both regions. The origin of `self` is preserved.
+    pub fn union(&self, other: &TileRegion) -> Self {
+        let mut bounds = self.bounds;
+        if let Some(other_bounds) = *other.bounds {
+            bounds.extend_to_contain(other_bounds);
+        }
+        Self {
+            origin: self.origin,
+            bounds,
+        }
+    }
     /// Iterator over `(target, source)` pairs where `target` is the position to put the tile
     /// and `source` is the position to get the tile from within the tile source.
     /// Every position within `bounds` will appear once as the `target`.
@@ -261,6 +389,29 @@ impl TileRegion {
     pub fn iter(&self) -> impl Iterator<Item = (Vector2<i32>, Vector2<i32>)> + '_ {
         self.bounds.iter().map(|p| (p, p - self.origin))
     }
+    /// Iterator over `(target, source)` pairs for placing blocks of `scale` tiles, such as
+    /// 2x2 "mega tiles". Targets step by `scale` starting from `origin` while sources advance by 1,
+    /// so `target` is the left-bottom corner of the block that should be filled using
+    /// the tile at `source`. Only blocks that fit entirely within `bounds` are produced;
+    /// partial blocks at the edges are clipped away. Components of `scale` less than 1 are
+    /// treated as 1.
+    pub fn iter_scaled(
+        &self,
+        scale: Vector2<i32>,
+    ) -> impl Iterator<Item = (Vector2<i32>, Vector2<i32>)> + '_ {
+        let scale = scale.map(|s| s.max(1));
+        let blocks = self.bounds.and_then(|bounds| {
+            let min = bounds.left_bottom_corner() - self.origin;
+            let max = bounds.right_top_corner() - self.origin + Vector2::repeat(1) - scale;
+            // Block indices of the first and the last blocks that fit into the bounds.
+            let start = Vector2::new(-(-min.x).div_euclid(scale.x), -(-min.y).div_euclid(scale.y));
+            let end = Vector2::new(max.x.div_euclid(scale.x), max.y.div_euclid(scale.y));
+            (start.x <= end.x && start.y <= end.y).then(|| TileRect::from_points(start, end))
+        });
+        OptionTileRect::from(blocks)
+            .iter()
+            .map(move |p| (self.origin + p.component_mul(&scale), p))
+    }
 }
 
 /// A trait for types that can produce a TileDefinitionHandle upon demand,
@@ -306,6 +457,47 @@ impl TileSource for RandomTileSource<'_> {
     }
 }
 
+/// A tile source that produces a random tile from the included set of tiles, where each tile
+/// is chosen with a probability that is proportional to its weight. Tiles that have no weight
+/// in the map have the weight of 1.0, and negative weights are treated as zero.
+pub struct WeightedRandomTileSource<'a>(
+    pub &'a Stamp,
+    pub &'a FxHashMap<TileDefinitionHandle, f32>,
+);
+
+impl WeightedRandomTileSource<'_> {
+    fn weight(&self, handle: &TileDefinitionHandle) -> f32 {
+        self.1.get(handle).copied().unwrap_or(1.0).max(0.0)
+    }
+}
+
+impl TileSource for WeightedRandomTileSource<'_> {
+    fn transformation(&self) -> OrthoTransformation {
+        self.0.transformation()
+    }
+    fn get_at(&self, _position: Vector2<i32>) -> Option<TileDefinitionHandle> {
+        let total = self.0.values().map(|h| self.weight(h)).sum::<f32>();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut choice = thread_rng().gen_range(0.0..total);
+        let mut last = None;
+        for handle in self.0.values() {
+            let weight = self.weight(handle);
+            if weight <= 0.0 {
+                continue;
+            }
+            if choice < weight {
+                return Some(*handle);
+            }
+            choice -= weight;
+            last = Some(*handle);
+        }
+        // Rounding errors may leave a tiny remainder.
+        last
+    }
+}
+
 /// A tile source that produces a random tile from the included set of tiles.
 pub struct PartialRandomTileSource<'a>(pub &'a Stamp, pub OptionTileRect);
 
@@ -343,12 +535,219 @@ impl<S: TileSource> TileSource for RepeatTileSource<'_, S> {
     }
 }
 
+/// A tile source that adapts another source so that it infinitely repeats the tiles
+/// within the given rect, like [`RepeatTileSource`], except that on the mirrored axes every other
+/// repetition is reflected, producing a ping-pong pattern. This allows seamless tiling of tiles
+/// that only match their own mirror images at the edges.
+///
+/// Within a reflected repetition the tiles themselves should also be flipped. Since a tile source
+/// has a single transformation for all of its tiles, the flipped tiles are found using
+/// the transform set pages of `tile_set` with [`TileSet::get_transformed_version`].
+/// If there is no tile set, or the tile has no transformed version, the unflipped tile is used.
+pub struct MirrorRepeatTileSource<'a, S> {
+    /// The tiles to repeat
+    pub source: &'a S,
+    /// The region within the stamp to repeat
+    pub region: TileRegion,
+    /// Which axes have every other repetition reflected.
+    pub mirror: Vector2<bool>,
+    /// The tile set to find the flipped versions of tiles.
+    pub tile_set: Option<&'a TileSet>,
+}
+
+/// Wraps a coordinate into `0..size`, reflecting every other period if `mirror` is true.
+/// Returns the wrapped coordinate and whether it was reflected.
+fn wrap_coordinate(value: i32, size: i32, mirror: bool) -> (i32, bool) {
+    if mirror {
+        let m = value.rem_euclid(2 * size);
+        if m >= size {
+            (2 * size - 1 - m, true)
+        } else {
+            (m, false)
+        }
+    } else {
+        (value.rem_euclid(size), false)
+    }
+}
+
+impl<S: TileSource> TileSource for MirrorRepeatTileSource<'_, S> {
+    fn transformation(&self) -> OrthoTransformation {
+        self.source.transformation()
+    }
+    fn get_at(&self, position: Vector2<i32>) -> Option<TileDefinitionHandle> {
+        let rect = (*self.region.bounds)?;
+        let rect_pos = rect.position;
+        let size = rect.size;
+        let pos = position + self.region.origin - rect_pos;
+        let (x, x_flipped) = wrap_coordinate(pos.x, size.x, self.mirror.x);
+        let (y, y_flipped) = wrap_coordinate(pos.y, size.y, self.mirror.y);
+        let handle = self.source.get_at(Vector2::new(x, y) + rect_pos)?;
+        let transform = match (x_flipped, y_flipped) {
+            (false, false) => return Some(handle),
+            (true, false) => OrthoTransformation::new(true, 0),
+            (false, true) => OrthoTransformation::new(true, 2),
+            (true, true) => OrthoTransformation::new(false, 2),
+        };
+        Some(
+            self.tile_set
+                .and_then(|tile_set| tile_set.get_transformed_version(transform, handle))
+                .unwrap_or(handle),
+        )
+    }
+}
+
+/// The offsets of the eight neighbors of a cell in the order of the bits of an autotile mask:
+/// N, NE, E, SE, S, SW, W, NW. North is the positive y direction.
+const AUTOTILE_NEIGHBORS: [Vector2<i32>; 8] = [
+    Vector2::new(0, 1),
+    Vector2::new(1, 1),
+    Vector2::new(1, 0),
+    Vector2::new(1, -1),
+    Vector2::new(0, -1),
+    Vector2::new(-1, -1),
+    Vector2::new(-1, 0),
+    Vector2::new(-1, 1),
+];
+
+/// A tile source that chooses tiles based on which of the eight neighbors of each position
+/// are occupied in some already-placed [`Tiles`], as in classic 47-tile autotiling.
+///
+/// The occupied neighbors are encoded as an 8-bit mask where bit 0 is north, and the following
+/// bits go clockwise: N = 1, NE = 2, E = 4, SE = 8, S = 16, SW = 32, W = 64, NW = 128.
+/// North is the positive y direction. The mask is used as a key into `table` to find the tile.
+///
+/// If the exact mask is missing from the table, the mask is reduced by clearing every diagonal
+/// bit whose two adjacent orthogonal neighbors are not both occupied, since such diagonals
+/// do not change the look of a tile in a 47-tile set. If the reduced mask is also missing,
+/// no tile is produced and the position is left unchanged.
+///
+/// Positions given to [`TileSource::get_at`] are offset by `origin` before looking into `tiles`,
+/// so `origin` should be the origin of the [`TileRegion`] when used with a rect fill,
+/// or the start of the line when used with a line. `tiles` should contain the cells of
+/// the stroke itself, or else they will not count as neighbors of each other.
+pub struct AutotileSource<'a> {
+    /// The tiles to use for each neighbor mask.
+    pub table: &'a FxHashMap<u8, TileDefinitionHandle>,
+    /// The tiles whose positions count as occupied.
+    pub tiles: &'a Tiles,
+    /// The position within `tiles` that corresponds to the (0,0) position of this source.
+    pub origin: Vector2<i32>,
+}
+
+impl AutotileSource<'_> {
+    /// The neighbor mask of the given position within `tiles`. See [`AutotileSource`] for
+    /// the meaning of each bit.
+    pub fn mask_at(&self, position: Vector2<i32>) -> u8 {
+        AUTOTILE_NEIGHBORS
+            .iter()
+            .enumerate()
+            .filter(|(_, offset)| self.tiles.contains_key(&(position + *offset)))
+            .fold(0, |mask, (i, _)| mask | (1 << i))
+    }
+    /// Clears each diagonal bit of the given mask unless both of the adjacent orthogonal
+    /// bits are set. This reduces the 256 possible masks to the 47 masks of a classic
+    /// autotile set.
+    pub fn reduce_mask(mask: u8) -> u8 {
+        let mut result = mask;
+        for diagonal in [1, 3, 5, 7] {
+            let before = 1 << (diagonal - 1);
+            let after = 1 << ((diagonal + 1) % 8);
+            if mask & before == 0 || mask & after == 0 {
+                result &= !(1 << diagonal);
+            }
+        }
+        result
+    }
+}
+
+impl TileSource for AutotileSource<'_> {
+    fn transformation(&self) -> OrthoTransformation {
+        OrthoTransformation::default()
+    }
+    fn get_at(&self, position: Vector2<i32>) -> Option<TileDefinitionHandle> {
+        let mask = self.mask_at(position + self.origin);
+        self.table
+            .get(&mask)
+            .or_else(|| self.table.get(&Self::reduce_mask(mask)))
+            .copied()
+    }
+}
+
+/// A tile source that chooses tiles by evaluating a noise function, such as Perlin noise,
+/// at each position and comparing the result against a list of thresholds.
+///
+/// Each band is a pair of a threshold and a tile. The tile of the band with the greatest threshold
+/// that is less than or equal to the noise value is chosen, so the order of the bands does not matter.
+/// If the noise value is below every threshold, no tile is produced and the position is left
+/// unchanged.
+///
+/// Positions given to [`TileSource::get_at`] are offset by `origin` before evaluating the noise,
+/// so that the noise is sampled at tile map positions when `origin` is the origin of
+/// the [`TileRegion`] being filled. The noise function is responsible for its own seeding,
+/// so the same function always produces the same tiles.
+pub struct NoiseTileSource<'a, F> {
+    /// The noise function.
+    pub noise: F,
+    /// The `(threshold, tile)` bands.
+    pub bands: &'a [(f32, TileDefinitionHandle)],
+    /// The position where the noise is sampled for the (0,0) position of this source.
+    pub origin: Vector2<i32>,
+}
+
+impl<F: Fn(Vector2<i32>) -> f32> TileSource for NoiseTileSource<'_, F> {
+    fn transformation(&self) -> OrthoTransformation {
+        OrthoTransformation::default()
+    }
+    fn get_at(&self, position: Vector2<i32>) -> Option<TileDefinitionHandle> {
+        let value = (self.noise)(position + self.origin);
+        self.bands
+            .iter()
+            .filter(|(threshold, _)| *threshold <= value)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, handle)| *handle)
+    }
+}
+
+/// Determines what [`Tiles::extend_with`] does when a tile is inserted into a position
+/// that is already occupied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Replace the existing tile with the new one.
+    #[default]
+    Overwrite,
+    /// Keep the existing tile and ignore the new one.
+    KeepExisting,
+    /// Stop at the first occupied position and report it.
+    FailOnConflict,
+}
+
 /// A set of tiles.
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Tiles(TileGridMap<TileDefinitionHandle>);
 
+/// A saved copy of [`Tiles`], created by [`Tiles::snapshot`] and consumed by [`Tiles::restore`].
+/// It allows tools to try a series of edits and roll them back as a whole.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TilesSnapshot(TileGridMap<TileDefinitionHandle>);
+
+/// The point of the bounding rectangle of the tiles of a stamp that becomes (0,0) when
+/// the stamp is built, see [`Stamp::build_with_pivot`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StampPivot {
+    /// The center of the rectangle.
+    #[default]
+    Center,
+    /// The top left corner of the rectangle.
+    TopLeft,
+    /// The bottom left corner of the rectangle.
+    BottomLeft,
+    /// The given position relative to the bottom left corner of the rectangle.
+    Custom(Vector2<i32>),
+}
+
 /// A set of tiles and a transformation, which represents the tiles that the user has selected
-/// to draw with.
+/// to draw with. Each tile is stored as a full [`TileDefinitionHandle`], so the tiles of a stamp
+/// may come from any number of pages of a tile set.
 #[derive(Clone, Debug, Default, Visit)]
 pub struct Stamp(OrthoTransformation, OrthoTransformMap<TileDefinitionHandle>);
 
@@ -361,9 +760,58 @@ impl TileSource for Tiles {
     }
 }
 
+impl BoundedTileSource for Tiles {
+    fn bounding_rect(&self) -> OptionTileRect {
+        Tiles::bounding_rect(self)
+    }
+}
+
+/// Tiles are stored as two flat arrays: the sorted coordinates and the handles in the same order,
+/// four `i16` per handle. Tiles that were saved as a [`TileGridMap`] are still supported when loading.
 impl Visit for Tiles {
     fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
-        self.0.visit(name, visitor)
+        let mut keys = Vec::<i32>::new();
+        let mut handles = Vec::<i16>::new();
+        if visitor.is_reading() {
+            let is_compact = {
+                let mut region = visitor.enter_region(name)?;
+                PodVecView::from_pod_vec(&mut handles)
+                    .visit("Handles", &mut region)
+                    .is_ok()
+                    && PodVecView::from_pod_vec(&mut keys)
+                        .visit("Keys", &mut region)
+                        .is_ok()
+            };
+            if !is_compact {
+                return self.0.visit(name, visitor);
+            }
+            if keys.len() % 2 != 0 || keys.len() / 2 != handles.len() / 4 {
+                return Err(VisitError::User(format!(
+                    "Tiles have {} coordinates and {} handle components",
+                    keys.len(),
+                    handles.len()
+                )));
+            }
+            self.0.clear();
+            for (key, handle) in keys.chunks_exact(2).zip(handles.chunks_exact(4)) {
+                self.0.insert(
+                    Vector2::new(key[0], key[1]),
+                    TileDefinitionHandle::new(handle[0], handle[1], handle[2], handle[3]),
+                );
+            }
+            return Ok(());
+        }
+
+        let mut entries = self.0.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|(key, _)| (key.x, key.y));
+        for (key, handle) in entries {
+            keys.extend([key.x, key.y]);
+            handles.extend([handle.page.x, handle.page.y, handle.tile.x, handle.tile.y]);
+        }
+        let mut region = visitor.enter_region(name)?;
+        PodVecView::from_pod_vec(&mut handles).visit("Handles", &mut region)?;
+        PodVecView::from_pod_vec(&mut keys).visit("Keys", &mut region)?;
+        Ok(())
     }
 }
 
@@ -395,6 +843,13 @@ impl Stamp {
     pub fn tile_iter(&self) -> impl Iterator<Item = TileDefinitionHandle> + '_ {
         self.1.values().copied()
     }
+    /// Iterate over the positions and tile handles of the stamp, sorted by rows, then by columns.
+    /// Unlike [`OrthoTransformMap::iter`], the order is always the same for the same tiles.
+    pub fn iter_sorted(&self) -> impl Iterator<Item = (Vector2<i32>, &TileDefinitionHandle)> {
+        let mut tiles = self.iter().collect::<Vec<_>>();
+        tiles.sort_unstable_by_key(|(p, _)| (p.y, p.x));
+        tiles.into_iter()
+    }
     /// Create a repeating tile source from this stamp to repeat from `start` to `end.`
     pub fn repeat(&self, start: Vector2<i32>, end: Vector2<i32>) -> RepeatTileSource<Stamp> {
         let bounds = self.bounding_rect();
@@ -422,12 +877,34 @@ impl Stamp {
         self.1.clear();
         self.0 = OrthoTransformation::identity();
     }
+    /// Create a stamp from the tiles within the given region of `tiles`, such as a selection that
+    /// is being copied. The tiles are moved so that their center is (0,0), as in [`Self::build`],
+    /// and the transform is identity. An empty region produces an empty stamp.
+    pub fn from_region(tiles: &Tiles, region: OptionTileRect) -> Stamp {
+        let mut stamp = Stamp::default();
+        let selected = region
+            .iter()
+            .filter_map(|position| Some((position, tiles.get_at(position)?)))
+            .collect::<Vec<_>>();
+        stamp.build(selected.into_iter());
+        stamp
+    }
     /// Clear this stamp and fill it with the given tiles.
     /// The tiles are moved so that their center is (0,0).
     /// The transform is set to identity.
     pub fn build<I: Iterator<Item = (Vector2<i32>, TileDefinitionHandle)> + Clone>(
         &mut self,
         source: I,
+    ) {
+        self.build_with_pivot(source, StampPivot::Center);
+    }
+    /// Clear this stamp and fill it with the given tiles.
+    /// The tiles are moved so that the given pivot of their bounding rectangle is (0,0).
+    /// The transform is set to identity.
+    pub fn build_with_pivot<I: Iterator<Item = (Vector2<i32>, TileDefinitionHandle)> + Clone>(
+        &mut self,
+        source: I,
+        pivot: StampPivot,
     ) {
         self.clear();
         let mut rect = OptionTileRect::default();
@@ -437,16 +914,60 @@ impl Stamp {
         let Some(rect) = *rect else {
             return;
         };
-        let center = rect.center();
+        let pivot = match pivot {
+            StampPivot::Center => rect.center(),
+            StampPivot::TopLeft => rect.left_top_corner(),
+            StampPivot::BottomLeft => rect.left_bottom_corner(),
+            StampPivot::Custom(offset) => rect.left_bottom_corner() + offset,
+        };
         for (p, h) in source {
-            self.insert(p - center, h);
+            self.insert(p - pivot, h);
         }
     }
+    /// Positions of the cells that would change if this stamp was drawn on the given tiles
+    /// at the given origin. Cells that already contain the tile that the stamp would put there
+    /// are excluded. The tile set is used to find the transformed versions of the tiles
+    /// of the stamp, in the same way as [`TransTilesUpdate::build_tiles_update`] does.
+    /// The positions are sorted by rows, then by columns.
+    pub fn changed_cells(
+        &self,
+        origin: Vector2<i32>,
+        tiles: &Tiles,
+        tile_set: &OptionTileSet,
+    ) -> Vec<Vector2<i32>> {
+        let trans = self.transformation();
+        let mut result = self
+            .iter()
+            .filter_map(|(local_position, handle)| {
+                let position = origin + local_position;
+                let handle = tile_set
+                    .get_transformed_version(trans, *handle)
+                    .unwrap_or(*handle);
+                (tiles.get(&position) != Some(&handle)).then_some(position)
+            })
+            .collect::<Vec<_>>();
+        result.sort_unstable_by_key(|p| (p.y, p.x));
+        result
+    }
     /// Rotate the stamp by the given number of 90-degree turns.
     pub fn rotate(&mut self, amount: i8) {
         self.0 = self.0.rotated(amount);
         self.1 = std::mem::take(&mut self.1).rotated(amount);
     }
+    /// Rotate the stamp counter-clockwise by the given angle in degrees, snapped to the nearest
+    /// multiple of 90 degrees, since tiles only support orthogonal rotations.
+    /// Angles exactly halfway between two multiples are snapped away from zero.
+    /// Returns the difference between the given angle and the angle of the applied rotation,
+    /// so that a UI can warn the user when the requested angle was not exact.
+    pub fn rotate_degrees(&mut self, degrees: f32) -> f32 {
+        let turns = (degrees / 90.0).round();
+        let residual = degrees - turns * 90.0;
+        let amount = turns.rem_euclid(4.0) as i8;
+        if amount != 0 {
+            self.rotate(amount);
+        }
+        residual
+    }
     /// Flip along the x axis.
     pub fn x_flip(&mut self) {
         self.0 = self.0.x_flipped();
@@ -462,6 +983,29 @@ impl Stamp {
         self.0 = self.0.transformed(amount);
         self.1 = std::mem::take(&mut self.1).transformed(amount);
     }
+    /// The number of 90-degree counter-clockwise turns that follow the flip reported by
+    /// [`Self::is_x_flipped`] or [`Self::is_y_flipped`]. The value is 0, 1, 2, or 3 for stamps
+    /// that are not flipped, and 0 or 1 for flipped stamps, since a flip along one axis followed
+    /// by a half turn is the same as a flip along the other axis.
+    pub fn rotation_steps(&self) -> i8 {
+        if self.0.is_flipped() {
+            self.0.rotation() % 2
+        } else {
+            self.0.rotation()
+        }
+    }
+    /// True if the stamp is flipped along the x axis, followed by [`Self::rotation_steps`].
+    pub fn is_x_flipped(&self) -> bool {
+        self.0.is_flipped() && self.0.rotation() < 2
+    }
+    /// True if the stamp is flipped along the y axis, followed by [`Self::rotation_steps`].
+    pub fn is_y_flipped(&self) -> bool {
+        self.0.is_flipped() && self.0.rotation() >= 2
+    }
+    /// Undo all rotations and flips of the stamp, keeping its tiles.
+    pub fn reset_transform(&mut self) {
+        self.transform(self.0.inverted());
+    }
 }
 
 impl Deref for Stamp {
@@ -514,6 +1058,76 @@ impl Tiles {
         result
     }
 
+    /// Fold the values of the property with the given UUID across all tiles, using the given
+    /// tile set to find the value of the property for each tile. Only explicitly set values are
+    /// folded: tiles that have no data in the tile set or no value for the property are skipped,
+    /// instead of using the default value of the property.
+    /// The tile set is borrowed once for the whole pass, so this can be used to efficiently
+    /// count tiles with some flag or to sum some value over an entire map.
+    pub fn aggregate_property<T, F>(
+        &self,
+        tile_set: &TileSet,
+        property_id: Uuid,
+        init: T,
+        mut f: F,
+    ) -> T
+    where
+        F: FnMut(T, &TileSetPropertyValue) -> T,
+    {
+        let mut result = init;
+        for handle in self.values() {
+            let value = tile_set
+                .get_tile_data((*handle).into())
+                .and_then(|data| data.properties.get(&property_id));
+            if let Some(value) = value {
+                result = f(result, value);
+            }
+        }
+        result
+    }
+
+    /// Insert all the given tiles, resolving conflicts with tiles that are already present
+    /// according to the given policy. With [`MergePolicy::FailOnConflict`] the insertion stops at
+    /// the first conflict and its position is returned as an error; the tiles that were inserted
+    /// before the conflict are kept.
+    pub fn extend_with<I>(&mut self, iter: I, policy: MergePolicy) -> Result<(), Vector2<i32>>
+    where
+        I: IntoIterator<Item = (Vector2<i32>, TileDefinitionHandle)>,
+    {
+        for (position, handle) in iter {
+            match policy {
+                MergePolicy::Overwrite => {
+                    self.insert(position, handle);
+                }
+                MergePolicy::KeepExisting => {
+                    self.entry(position).or_insert(handle);
+                }
+                MergePolicy::FailOnConflict => {
+                    if self.contains_key(&position) {
+                        return Err(position);
+                    }
+                    self.insert(position, handle);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the current state of the tiles, so that it can be brought back later with
+    /// [`Self::restore`]. The snapshot is a full copy of the tiles, so its cost is proportional
+    /// to the number of tiles. Changes are not tracked, because the tiles can be modified
+    /// directly through [`DerefMut`].
+    pub fn snapshot(&self) -> TilesSnapshot {
+        TilesSnapshot(self.0.clone())
+    }
+
+    /// Returns the tiles to the state of the given snapshot by replacing all the tiles with
+    /// the copy that is stored in the snapshot.
+    pub fn restore(&mut self, snapshot: TilesSnapshot) {
+        let TilesSnapshot(saved) = snapshot;
+        self.0 = saved;
+    }
+
     /// Clears the tile container.
     #[inline]
     pub fn clear(&mut self) {
@@ -521,6 +1135,49 @@ impl Tiles {
     }
 }
 
+/// A table of tile set page moves that can be used to fix tile definition handles after
+/// pages of a tile set were moved. Each entry maps the old position of a page to its new position.
+/// Handles of tiles on pages that are not in the table are left unchanged, and the position
+/// of a tile within its page is always preserved.
+#[derive(Clone, Debug, Default)]
+pub struct HandleRemap {
+    /// Page moves, old position to new position.
+    pub pages: FxHashMap<Vector2<i32>, Vector2<i32>>,
+}
+
+impl HandleRemap {
+    /// Creates a new remap from the given table of page moves.
+    pub fn new(pages: FxHashMap<Vector2<i32>, Vector2<i32>>) -> Self {
+        Self { pages }
+    }
+    /// Returns the inverse of this remap, which moves the pages back.
+    pub fn inverse(&self) -> Self {
+        Self::new(self.pages.iter().map(|(old, new)| (*new, *old)).collect())
+    }
+    /// Returns the handle that the given handle should be replaced with. The handle is returned
+    /// unchanged if its page was not moved or if the new page position cannot be represented
+    /// by a handle.
+    pub fn remap(&self, handle: TileDefinitionHandle) -> TileDefinitionHandle {
+        self.pages
+            .get(&handle.page())
+            .and_then(|page| TileDefinitionHandle::try_new(*page, handle.tile()))
+            .unwrap_or(handle)
+    }
+    /// Rewrites all the handles of the given tiles.
+    pub fn apply_to_tiles(&self, tiles: &mut Tiles) {
+        for handle in tiles.values_mut() {
+            *handle = self.remap(*handle);
+        }
+    }
+    /// Rewrites all the handles of the given brush, including the icons of its pages.
+    pub fn apply_to_brush(&self, brush: &mut TileMapBrush) {
+        for page in brush.pages.values_mut() {
+            page.icon = self.remap(page.icon);
+            self.apply_to_tiles(page.tiles_mut());
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -532,6 +1189,531 @@ mod tests {
         assert_eq!(std::mem::size_of::<TileDefinitionHandle>(), 8);
     }
 
+    #[test]
+    fn grid_map_with_capacity() {
+        const N: usize = 100;
+        let mut grid = TileGridMap::with_capacity(N);
+        let capacity = grid.capacity();
+        assert!(capacity >= N);
+        for x in 0..N as i32 {
+            grid.insert(Vector2::new(x, -x), x);
+        }
+        // No reallocation happened.
+        assert_eq!(grid.capacity(), capacity);
+    }
+
+    #[test]
+    fn autotile() {
+        let mut tiles = Tiles::default();
+        for p in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            tiles.insert(
+                Vector2::new(p.0, p.1),
+                TileDefinitionHandle::new(0, 0, 0, 0),
+            );
+        }
+        let lone = TileDefinitionHandle::new(0, 0, 1, 0);
+        let corner = TileDefinitionHandle::new(0, 0, 2, 0);
+        let east = TileDefinitionHandle::new(0, 0, 3, 0);
+        // N, NE, E
+        let table = FxHashMap::from_iter([(0, lone), (0b111, corner), (0b100, east)]);
+        let source = AutotileSource {
+            table: &table,
+            tiles: &tiles,
+            origin: Vector2::new(0, 0),
+        };
+        assert_eq!(source.mask_at(Vector2::new(0, 0)), 0b111);
+        assert_eq!(source.get_at(Vector2::new(0, 0)), Some(corner));
+        assert_eq!(source.get_at(Vector2::new(5, 5)), Some(lone));
+        // Only NE is occupied, which reduces to an empty mask.
+        assert_eq!(source.mask_at(Vector2::new(-1, -1)), 0b10);
+        assert_eq!(source.get_at(Vector2::new(-1, -1)), Some(lone));
+        // E and SE are occupied, which reduces to only E.
+        let source = AutotileSource {
+            origin: Vector2::new(-1, -1),
+            ..source
+        };
+        assert_eq!(source.get_at(Vector2::new(0, 2)), Some(east));
+        // W and NW are occupied, which reduces to only W, and W has no tile.
+        assert_eq!(source.get_at(Vector2::new(3, 1)), None);
+        assert_eq!(AutotileSource::reduce_mask(0xFF), 0xFF);
+        assert_eq!(AutotileSource::reduce_mask(0b1010_1010), 0);
+    }
+
+    #[test]
+    fn noise_source() {
+        let water = TileDefinitionHandle::new(0, 0, 0, 0);
+        let sand = TileDefinitionHandle::new(0, 0, 1, 0);
+        let grass = TileDefinitionHandle::new(0, 0, 2, 0);
+        let bands = [(0.5, grass), (-1.0, water), (0.0, sand)];
+        let source = NoiseTileSource {
+            noise: |p: Vector2<i32>| p.x as f32 / 4.0,
+            bands: &bands,
+            origin: Vector2::new(-8, 0),
+        };
+        assert_eq!(source.get_at(Vector2::new(0, 0)), None);
+        assert_eq!(source.get_at(Vector2::new(4, 0)), Some(water));
+        assert_eq!(source.get_at(Vector2::new(8, 0)), Some(sand));
+        assert_eq!(source.get_at(Vector2::new(9, 0)), Some(sand));
+        assert_eq!(source.get_at(Vector2::new(10, 0)), Some(grass));
+    }
+
+    #[test]
+    fn mirror_repeat() {
+        let mut tiles = Tiles::default();
+        for x in 0..3 {
+            tiles.insert(
+                Vector2::new(x, 0),
+                TileDefinitionHandle::new(0, 0, x as i16, 0),
+            );
+        }
+        let source = MirrorRepeatTileSource {
+            source: &tiles,
+            region: TileRegion {
+                origin: Vector2::new(0, 0),
+                bounds: OptionTileRect::from_points(Vector2::new(0, 0), Vector2::new(2, 0)),
+            },
+            mirror: Vector2::new(true, false),
+            tile_set: None,
+        };
+        let row = (-4..10)
+            .map(|x| source.get_at(Vector2::new(x, 0)).unwrap().tile().x)
+            .collect::<Vec<_>>();
+        assert_eq!(row, [2, 2, 1, 0, 0, 1, 2, 2, 1, 0, 0, 1, 2, 2]);
+        // The unmirrored axis repeats as usual.
+        assert_eq!(
+            source.get_at(Vector2::new(4, 7)),
+            source.get_at(Vector2::new(4, 0))
+        );
+        assert_eq!(wrap_coordinate(-1, 3, true), (0, true));
+        assert_eq!(wrap_coordinate(2, 3, true), (2, false));
+        assert_eq!(wrap_coordinate(3, 3, true), (2, true));
+        assert_eq!(wrap_coordinate(6, 3, true), (0, false));
+        assert_eq!(wrap_coordinate(3, 3, false), (0, false));
+    }
+
+    #[test]
+    fn stamp_iter_sorted() {
+        let mut stamp = Stamp::default();
+        let positions = [(1, 1), (-1, 0), (0, 1), (2, -3), (0, 0)];
+        for (i, (x, y)) in positions.into_iter().enumerate() {
+            stamp.insert(
+                Vector2::new(x, y),
+                TileDefinitionHandle::new(0, 0, i as i16, 0),
+            );
+        }
+        let sorted = stamp
+            .iter_sorted()
+            .map(|(p, h)| (p.x, p.y, h.tile().x))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sorted,
+            [(2, -3, 3), (-1, 0, 1), (0, 0, 4), (0, 1, 2), (1, 1, 0)]
+        );
+    }
+
+    #[test]
+    fn stamp_from_region() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 0);
+        let b = TileDefinitionHandle::new(0, 0, 2, 0);
+        let mut tiles = Tiles::default();
+        tiles.insert(Vector2::new(10, 10), a);
+        tiles.insert(Vector2::new(12, 10), b);
+        tiles.insert(Vector2::new(20, 20), a);
+        let stamp = Stamp::from_region(
+            &tiles,
+            OptionTileRect::from_points(Vector2::new(10, 10), Vector2::new(12, 11)),
+        );
+        assert!(stamp.transformation().is_identity());
+        let sorted = stamp
+            .iter_sorted()
+            .map(|(p, h)| (p, *h))
+            .collect::<Vec<_>>();
+        assert_eq!(sorted, [(Vector2::new(-1, 0), a), (Vector2::new(1, 0), b)]);
+        assert!(Stamp::from_region(&tiles, OptionTileRect::default()).is_empty());
+    }
+
+    #[test]
+    fn parse_handle() {
+        let handle = TileDefinitionHandle::new(1, -2, 3, -4);
+        assert_eq!(TileDefinitionHandle::parse("(1,-2):(3,-4)"), Some(handle));
+        assert_eq!(TileDefinitionHandle::parse("1,-2,3,-4"), Some(handle));
+        assert_eq!(
+            TileDefinitionHandle::parse(r#""page":[1,-2],"tile":[3,-4]"#),
+            Some(handle)
+        );
+        assert_eq!(TileDefinitionHandle::parse("1,2,3"), None);
+        assert_eq!(TileDefinitionHandle::parse("1,2,3,4,5"), None);
+        assert_eq!(
+            TileDefinitionHandle::parse(&handle.to_compact_string()),
+            Some(handle)
+        );
+    }
+
+    fn save_grid(grid: &mut TileGridMap<TileDefinitionHandle>) -> Vec<u8> {
+        let mut visitor = Visitor::new();
+        grid.visit("Grid", &mut visitor).unwrap();
+        visitor.save_binary_to_vec().unwrap()
+    }
+
+    fn load_grid(data: &[u8]) -> TileGridMap<TileDefinitionHandle> {
+        let mut visitor = Visitor::load_from_memory(data).unwrap();
+        let mut grid = TileGridMap::default();
+        grid.visit("Grid", &mut visitor).unwrap();
+        grid
+    }
+
+    #[test]
+    fn visit_grid() {
+        let entries = (0..20)
+            .map(|i| {
+                (
+                    Vector2::new(i % 5 - 2, i / 5),
+                    TileDefinitionHandle::new(0, 0, i as i16, -i as i16),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut grid = TileGridMap::default();
+        grid.extend(entries.iter().cloned());
+        let mut reversed = TileGridMap::with_capacity(100);
+        reversed.extend(entries.iter().rev().cloned());
+        let data = save_grid(&mut grid);
+        assert_eq!(data, save_grid(&mut reversed));
+        assert_eq!(load_grid(&data), grid);
+        assert_eq!(load_grid(&save_grid(&mut TileGridMap::default())).len(), 0);
+    }
+
+    #[test]
+    fn visit_tiles() {
+        let entries = (0..20)
+            .map(|i| {
+                (
+                    Vector2::new(i % 5 - 2, i / 5),
+                    TileDefinitionHandle::new(1, -1, i as i16, -i as i16),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut tiles = Tiles::default();
+        tiles.extend(entries.iter().cloned());
+        let mut reversed = Tiles::default();
+        reversed.extend(entries.iter().rev().cloned());
+        let save = |tiles: &mut Tiles| {
+            let mut visitor = Visitor::new();
+            tiles.visit("Grid", &mut visitor).unwrap();
+            visitor.save_binary_to_vec().unwrap()
+        };
+        let load = |data: &[u8]| {
+            let mut visitor = Visitor::load_from_memory(data).unwrap();
+            let mut tiles = Tiles::default();
+            tiles.visit("Grid", &mut visitor).unwrap();
+            tiles
+        };
+        let data = save(&mut tiles);
+        assert_eq!(data, save(&mut reversed));
+        assert_eq!(load(&data), tiles);
+        let mut grid = tiles.0.clone();
+        let grid_data = save_grid(&mut grid);
+        assert!(data.len() < grid_data.len());
+        assert_eq!(load(&grid_data), tiles);
+    }
+
+    #[test]
+    fn visit_legacy_grid() {
+        let mut map = FxHashMap::default();
+        map.insert(Vector2::new(1, 2), TileDefinitionHandle::new(3, 4, 5, 6));
+        map.insert(Vector2::new(-1, 0), TileDefinitionHandle::new(0, 0, 1, 1));
+        let mut visitor = Visitor::new();
+        map.visit("Grid", &mut visitor).unwrap();
+        let grid = load_grid(&visitor.save_binary_to_vec().unwrap());
+        assert_eq!(*grid, map);
+    }
+
+    #[test]
+    fn offset_handle() {
+        let handle = TileDefinitionHandle::new(1, 2, 3, 4);
+        let right = handle.offset_tile(Vector2::new(1, 0)).unwrap();
+        assert_eq!(right, TileDefinitionHandle::new(1, 2, 4, 4));
+        assert!(right.same_page(&handle));
+        assert!(!handle.same_page(&TileDefinitionHandle::new(2, 2, 3, 4)));
+        assert_eq!(
+            handle.offset_tile(Vector2::new(-5, -6)),
+            Some(TileDefinitionHandle::new(1, 2, -2, -2))
+        );
+        assert_eq!(handle.offset_tile(Vector2::new(i16::MAX as i32, 0)), None);
+        assert_eq!(handle.offset_tile(Vector2::new(i32::MAX, i32::MIN)), None);
+    }
+
+    #[test]
+    fn parse_display_handle() {
+        let handle = TileDefinitionHandle::new(1, -2, 3, -4);
+        assert_eq!(handle.to_compact_string(), "1,-2,3,-4");
+        assert_eq!(
+            TileDefinitionHandle::parse_display(&handle.to_string()),
+            Some(handle)
+        );
+        assert_eq!(
+            TileDefinitionHandle::parse_display(&TileDefinitionHandle::EMPTY.to_string()),
+            Some(TileDefinitionHandle::EMPTY)
+        );
+        for invalid in [
+            "1,-2,3,-4",
+            "x(1,-2):(3,-4)",
+            "(1,-2):(3,-4)x",
+            "( 1,-2):(3,-4)",
+            "(+1,-2):(3,-4)",
+            "(01,-2):(3,-4)",
+            "(1,-2,0):(3,-4)",
+            "(1,-2):(3,-4):(5,6)",
+        ] {
+            assert_eq!(
+                TileDefinitionHandle::parse_display(invalid),
+                None,
+                "{invalid}"
+            );
+        }
+    }
+
+    fn rotation_of(degrees: f32) -> (i8, f32) {
+        let mut stamp = Stamp::default();
+        let residual = stamp.rotate_degrees(degrees);
+        (stamp.transformation().rotation(), residual)
+    }
+
+    #[test]
+    fn rotate_degrees() {
+        assert_eq!(rotation_of(0.0), (0, 0.0));
+        assert_eq!(rotation_of(44.0), (0, 44.0));
+        assert_eq!(rotation_of(46.0), (1, -44.0));
+        assert_eq!(rotation_of(90.0), (1, 0.0));
+        assert_eq!(rotation_of(180.0), (2, 0.0));
+        assert_eq!(rotation_of(-44.0), (0, -44.0));
+        assert_eq!(rotation_of(-46.0), (3, 44.0));
+        assert_eq!(rotation_of(-90.0), (3, 0.0));
+        assert_eq!(rotation_of(365.0), (0, 5.0));
+    }
+
+    #[test]
+    fn iter_scaled() {
+        let region = TileRegion::from_points(Vector2::new(0, 0), Vector2::new(3, 3));
+        let mut result = region.iter_scaled(Vector2::new(2, 2)).collect::<Vec<_>>();
+        result.sort_by_key(|(t, _)| (t.x, t.y));
+        assert_eq!(
+            result,
+            vec![
+                (Vector2::new(0, 0), Vector2::new(0, 0)),
+                (Vector2::new(0, 2), Vector2::new(0, 1)),
+                (Vector2::new(2, 0), Vector2::new(1, 0)),
+                (Vector2::new(2, 2), Vector2::new(1, 1)),
+            ]
+        );
+        // The partial column and row at the edge are clipped.
+        let region = TileRegion::from_points(Vector2::new(0, 0), Vector2::new(4, 4));
+        assert_eq!(region.iter_scaled(Vector2::new(2, 2)).count(), 4);
+        // Origin outside of the bounds.
+        let region = TileRegion {
+            origin: Vector2::new(-1, 0),
+            bounds: TileRect::new(0, 0, 4, 2).into(),
+        };
+        let result = region.iter_scaled(Vector2::new(2, 2)).collect::<Vec<_>>();
+        assert_eq!(result, vec![(Vector2::new(1, 0), Vector2::new(1, 0))]);
+    }
+
+    #[test]
+    fn region_intersect_union() {
+        let a = TileRegion::from_points(Vector2::new(0, 0), Vector2::new(3, 3));
+        let b = TileRegion::from_points(Vector2::new(5, 2), Vector2::new(2, 5));
+        let c = TileRegion::from_points(Vector2::new(10, 10), Vector2::new(11, 11));
+        let overlap = a.intersect(&b);
+        assert_eq!(overlap.origin, a.origin);
+        assert_eq!(*overlap.bounds, Some(TileRect::new(2, 2, 2, 2)));
+        let union = a.union(&b);
+        assert_eq!(union.origin, a.origin);
+        assert_eq!(*union.bounds, Some(TileRect::new(0, 0, 6, 6)));
+        let empty = a.intersect(&c);
+        assert_eq!(empty.origin, a.origin);
+        assert!(empty.bounds.is_none());
+        assert_eq!(empty.iter().count(), 0);
+        assert_eq!(empty.union(&c).bounds, c.bounds);
+        assert!(a.intersect(&TileRegion::default()).bounds.is_none());
+    }
+
+    #[test]
+    fn extend_with() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 1);
+        let b = TileDefinitionHandle::new(0, 0, 2, 2);
+        let first = [(Vector2::new(0, 0), a), (Vector2::new(1, 0), a)];
+        let second = [(Vector2::new(1, 0), b), (Vector2::new(2, 0), b)];
+
+        let mut tiles = Tiles::default();
+        tiles.extend_with(first, MergePolicy::Overwrite).unwrap();
+        tiles.extend_with(second, MergePolicy::Overwrite).unwrap();
+        assert_eq!(tiles.get(&Vector2::new(1, 0)), Some(&b));
+        assert_eq!(tiles.len(), 3);
+
+        let mut tiles = Tiles::default();
+        tiles.extend_with(first, MergePolicy::KeepExisting).unwrap();
+        tiles
+            .extend_with(second, MergePolicy::KeepExisting)
+            .unwrap();
+        assert_eq!(tiles.get(&Vector2::new(1, 0)), Some(&a));
+        assert_eq!(tiles.get(&Vector2::new(2, 0)), Some(&b));
+
+        let mut tiles = Tiles::default();
+        tiles
+            .extend_with(first, MergePolicy::FailOnConflict)
+            .unwrap();
+        assert_eq!(
+            tiles.extend_with(second, MergePolicy::FailOnConflict),
+            Err(Vector2::new(1, 0))
+        );
+        assert_eq!(tiles.get(&Vector2::new(1, 0)), Some(&a));
+    }
+
+    #[test]
+    fn snapshot_restore() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 1);
+        let b = TileDefinitionHandle::new(0, 0, 2, 2);
+        let mut tiles = Tiles::default();
+        for x in 0..4 {
+            tiles.insert(Vector2::new(x, 0), a);
+        }
+        let original = tiles.clone();
+
+        let snapshot = tiles.snapshot();
+        tiles.insert(Vector2::new(0, 0), b);
+        tiles.remove(&Vector2::new(1, 0));
+        tiles.insert(Vector2::new(5, 5), b);
+        assert_ne!(tiles, original);
+
+        tiles.restore(snapshot);
+        assert_eq!(tiles, original);
+    }
+
+    #[test]
+    fn handle_remap_round_trip() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 2);
+        let b = TileDefinitionHandle::new(3, 3, 4, 5);
+        let mut tiles = Tiles::default();
+        tiles.insert(Vector2::new(0, 0), a);
+        tiles.insert(Vector2::new(1, 0), b);
+        let original = tiles.clone();
+
+        let mut pages = FxHashMap::default();
+        pages.insert(Vector2::new(0, 0), Vector2::new(-1, 7));
+        let remap = HandleRemap::new(pages);
+        remap.apply_to_tiles(&mut tiles);
+        assert_eq!(
+            tiles.get(&Vector2::new(0, 0)),
+            Some(&TileDefinitionHandle::new(-1, 7, 1, 2))
+        );
+        assert_eq!(tiles.get(&Vector2::new(1, 0)), Some(&b));
+
+        remap.inverse().apply_to_tiles(&mut tiles);
+        assert_eq!(tiles, original);
+    }
+
+    #[test]
+    fn stamp_changed_cells() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 1);
+        let b = TileDefinitionHandle::new(0, 0, 2, 2);
+        let mut stamp = Stamp::default();
+        stamp.insert(Vector2::new(0, 0), a);
+        stamp.insert(Vector2::new(1, 0), b);
+        let mut tiles = Tiles::default();
+        tiles.insert(Vector2::new(10, 10), a);
+        tiles.insert(Vector2::new(11, 10), a);
+        let tile_set = TileSetResource::new_ok(ResourceKind::Embedded, TileSet::default());
+        let mut tile_set = TileSetRef::new(&tile_set);
+        let changed = stamp.changed_cells(Vector2::new(10, 10), &tiles, &tile_set.as_loaded());
+        assert_eq!(changed, vec![Vector2::new(11, 10)]);
+        let changed = stamp.changed_cells(Vector2::new(0, 0), &tiles, &tile_set.as_loaded());
+        assert_eq!(changed, vec![Vector2::new(0, 0), Vector2::new(1, 0)]);
+    }
+
+    #[test]
+    fn multi_page_stamp() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 1);
+        let b = TileDefinitionHandle::new(5, -3, 1, 1);
+        let c = TileDefinitionHandle::new(-2, 4, 0, 7);
+        let mut stamp = Stamp::default();
+        stamp.build(
+            [
+                (Vector2::new(10, 10), a),
+                (Vector2::new(11, 10), b),
+                (Vector2::new(12, 10), c),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(stamp.get_at(Vector2::new(-1, 0)), Some(a));
+        assert_eq!(stamp.get_at(Vector2::new(0, 0)), Some(b));
+        assert_eq!(stamp.get_at(Vector2::new(1, 0)), Some(c));
+
+        let tile_set = TileSetResource::new_ok(ResourceKind::Embedded, TileSet::default());
+        let mut tile_set = TileSetRef::new(&tile_set);
+        let mut update = TransTilesUpdate::default();
+        update.draw_tiles(Vector2::new(100, 0), &stamp);
+        let update = update.build_tiles_update(&tile_set.as_loaded());
+        assert_eq!(update.get(&Vector2::new(99, 0)), Some(&Some(a)));
+        assert_eq!(update.get(&Vector2::new(100, 0)), Some(&Some(b)));
+        assert_eq!(update.get(&Vector2::new(101, 0)), Some(&Some(c)));
+    }
+
+    #[test]
+    fn aggregate_property() {
+        let page = Vector2::new(0, 0);
+        let property = Uuid::new_v4();
+        let mut tile_set = TileSet::default();
+        tile_set.insert_page(
+            page,
+            TileSetPage {
+                icon: TileDefinitionHandle::new(0, 0, 0, 0),
+                source: TileSetPageSource::new_free(),
+            },
+        );
+        tile_set.properties.push(TileSetPropertyLayer {
+            uuid: property,
+            name: "Spawn".into(),
+            prop_type: TileSetPropertyType::I32,
+            named_values: Vec::new(),
+        });
+        let a = TileDefinitionHandle::new(0, 0, 0, 0);
+        let b = TileDefinitionHandle::new(0, 0, 1, 0);
+        let plain = TileDefinitionHandle::new(0, 0, 2, 0);
+        let missing = TileDefinitionHandle::new(0, 0, 3, 0);
+        let mut update = TileSetUpdate::default();
+        for (handle, value) in [(a, 3), (b, 4)] {
+            update.set_material_with_properties(
+                page,
+                handle.tile(),
+                TileMaterialBounds::default(),
+                [(property, TileSetPropertyValue::I32(value))]
+                    .into_iter()
+                    .collect(),
+            );
+        }
+        update.insert(
+            plain,
+            TileDataUpdate::Material(TileMaterialBounds::default()),
+        );
+        update.swap_with_tile_set(&mut tile_set);
+
+        let mut tiles = Tiles::default();
+        for (x, handle) in [a, a, b, plain, missing].into_iter().enumerate() {
+            tiles.insert(Vector2::new(x as i32, 0), handle);
+        }
+        let sum = |acc: i32, value: &TileSetPropertyValue| match value {
+            TileSetPropertyValue::I32(v) => acc + v,
+            _ => acc,
+        };
+        // The tile without a value and the missing tile are skipped.
+        assert_eq!(tiles.aggregate_property(&tile_set, property, 0, sum), 10);
+        let count = tiles.aggregate_property(&tile_set, property, 0, |acc, _| acc + 1);
+        assert_eq!(count, 3);
+        assert_eq!(
+            tiles.aggregate_property(&tile_set, Uuid::new_v4(), -1, sum),
+            -1
+        );
+    }
+
     #[test]
     fn zero_handle() {
         assert_eq!(
@@ -539,4 +1721,106 @@ mod tests {
             TileDefinitionHandle::default()
         );
     }
+
+    #[test]
+    fn build_with_pivot() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 1);
+        let b = TileDefinitionHandle::new(0, 0, 2, 2);
+        for source in [Vector2::new(0, 0), Vector2::new(17, -4)] {
+            let mut stamp = Stamp::default();
+            stamp.build_with_pivot([(source, a)].into_iter(), StampPivot::BottomLeft);
+            assert_eq!(stamp.get_at(Vector2::new(0, 0)), Some(a));
+        }
+
+        let tiles = [(Vector2::new(10, 10), a), (Vector2::new(12, 13), b)];
+        let mut stamp = Stamp::default();
+        stamp.build_with_pivot(tiles.into_iter(), StampPivot::BottomLeft);
+        assert_eq!(stamp.get_at(Vector2::new(0, 0)), Some(a));
+        assert_eq!(stamp.get_at(Vector2::new(2, 3)), Some(b));
+        stamp.build_with_pivot(tiles.into_iter(), StampPivot::TopLeft);
+        assert_eq!(stamp.get_at(Vector2::new(0, -3)), Some(a));
+        assert_eq!(stamp.get_at(Vector2::new(2, 0)), Some(b));
+        stamp.build_with_pivot(tiles.into_iter(), StampPivot::Custom(Vector2::new(2, 3)));
+        assert_eq!(stamp.get_at(Vector2::new(-2, -3)), Some(a));
+        assert_eq!(stamp.get_at(Vector2::new(0, 0)), Some(b));
+
+        let mut centered = Stamp::default();
+        centered.build(tiles.into_iter());
+        stamp.build_with_pivot(tiles.into_iter(), StampPivot::Center);
+        assert_eq!(stamp.get_at(Vector2::new(-1, -2)), Some(a));
+        assert_eq!(centered.get_at(Vector2::new(-1, -2)), Some(a));
+    }
+
+    #[test]
+    fn stamp_transform_queries() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 1);
+        let mut stamp = Stamp::default();
+        stamp.build([(Vector2::new(0, 0), a), (Vector2::new(2, 1), a)].into_iter());
+        let original = stamp.keys().collect::<FxHashSet<_>>();
+        assert_eq!(stamp.rotation_steps(), 0);
+        assert!(!stamp.is_x_flipped() && !stamp.is_y_flipped());
+
+        stamp.rotate(3);
+        assert_eq!(stamp.rotation_steps(), 3);
+        assert!(!stamp.is_x_flipped() && !stamp.is_y_flipped());
+
+        let mut stamp_x = stamp.clone();
+        stamp_x.reset_transform();
+        stamp_x.x_flip();
+        assert!(stamp_x.is_x_flipped() && !stamp_x.is_y_flipped());
+        assert_eq!(stamp_x.rotation_steps(), 0);
+        stamp_x.rotate(1);
+        assert!(stamp_x.is_x_flipped());
+        assert_eq!(stamp_x.rotation_steps(), 1);
+
+        let mut stamp_y = stamp.clone();
+        stamp_y.reset_transform();
+        stamp_y.y_flip();
+        assert!(stamp_y.is_y_flipped() && !stamp_y.is_x_flipped());
+        assert_eq!(stamp_y.rotation_steps(), 0);
+
+        for mut stamp in [stamp, stamp_x, stamp_y] {
+            stamp.reset_transform();
+            assert!(stamp.transformation().is_identity());
+            assert_eq!(stamp.keys().collect::<FxHashSet<_>>(), original);
+            assert_eq!(stamp.tile_iter().count(), 2);
+        }
+    }
+
+    #[test]
+    fn weighted_random_tile_source() {
+        let a = TileDefinitionHandle::new(0, 0, 0, 0);
+        let b = TileDefinitionHandle::new(0, 0, 1, 0);
+        let c = TileDefinitionHandle::new(0, 0, 2, 0);
+        let mut stamp = Stamp::default();
+        stamp.build(
+            [
+                (Vector2::new(0, 0), a),
+                (Vector2::new(1, 0), b),
+                (Vector2::new(2, 0), c),
+            ]
+            .into_iter(),
+        );
+        let mut weights = FxHashMap::default();
+        weights.insert(a, 9.0);
+        weights.insert(c, 0.0);
+        let source = WeightedRandomTileSource(&stamp, &weights);
+        let mut counts = FxHashMap::<TileDefinitionHandle, usize>::default();
+        for _ in 0..10000 {
+            *counts
+                .entry(source.get_at(Vector2::new(0, 0)).unwrap())
+                .or_default() += 1;
+        }
+        // `b` has the default weight of 1.0, `c` is never chosen.
+        assert!(!counts.contains_key(&c));
+        assert!(counts[&a] > 8500, "{counts:?}");
+        assert!(counts[&b] > 500, "{counts:?}");
+
+        weights.insert(a, 0.0);
+        weights.insert(b, -1.0);
+        assert_eq!(
+            WeightedRandomTileSource(&stamp, &weights).get_at(Vector2::new(0, 0)),
+            None
+        );
+    }
 }
@@ -179,6 +179,45 @@ pub struct TileData {
     pub properties: FxHashMap<Uuid, TileSetPropertyValue>,
 }
 
+impl TileData {
+    /// Iterate over the ids and values of the properties of the tile.
+    pub fn iter_properties(&self) -> impl Iterator<Item = (Uuid, &TileSetPropertyValue)> {
+        self.properties.iter().map(|(uuid, value)| (*uuid, value))
+    }
+    /// The value of the integer property with the given id, or `None` if the tile has no such
+    /// property or the property is not an integer.
+    pub fn get_i32(&self, property_id: Uuid) -> Option<i32> {
+        match self.properties.get(&property_id)? {
+            TileSetPropertyValue::I32(v) => Some(*v),
+            _ => None,
+        }
+    }
+    /// The value of the float property with the given id, or `None` if the tile has no such
+    /// property or the property is not a float.
+    pub fn get_f32(&self, property_id: Uuid) -> Option<f32> {
+        match self.properties.get(&property_id)? {
+            TileSetPropertyValue::F32(v) => Some(*v),
+            _ => None,
+        }
+    }
+    /// The value of the string property with the given id, or `None` if the tile has no such
+    /// property or the property is not a string.
+    pub fn get_string(&self, property_id: Uuid) -> Option<&str> {
+        match self.properties.get(&property_id)? {
+            TileSetPropertyValue::String(v) => Some(v.as_str()),
+            _ => None,
+        }
+    }
+    /// The values of the nine-slice property with the given id, or `None` if the tile has no such
+    /// property or the property is not a nine-slice.
+    pub fn get_nine_slice(&self, property_id: Uuid) -> Option<&[i8; 9]> {
+        match self.properties.get(&property_id)? {
+            TileSetPropertyValue::NineSlice(v) => Some(&v.0),
+            _ => None,
+        }
+    }
+}
+
 impl OrthoTransform for TileData {
     fn x_flipped(mut self) -> Self {
         for (_, value) in self.properties.iter_mut() {
@@ -500,10 +539,19 @@ fn swap_transform_tile(
     update: &mut TileDataUpdate,
 ) {
     let e0 = map0.entry(position);
-    let TileDataUpdate::TransformSet(handle) = update else {
-        panic!()
-    };
-    swap_hash_map_entry(e0, handle);
+    match update {
+        TileDataUpdate::TransformSet(handle) => swap_hash_map_entry(e0, handle),
+        TileDataUpdate::Erase => {
+            let mut handle = None;
+            swap_hash_map_entry(e0, &mut handle);
+            *update = TileDataUpdate::TransformSet(handle);
+        }
+        TileDataUpdate::DoNothing => (),
+        _ => {
+            Log::err("Tile set update is incompatible with a transform or animation page.");
+            *update = TileDataUpdate::DoNothing;
+        }
+    }
 }
 
 fn swap_animation_tile(
@@ -512,10 +560,19 @@ fn swap_animation_tile(
     update: &mut TileDataUpdate,
 ) {
     let e0 = map0.entry(position);
-    let TileDataUpdate::TransformSet(handle) = update else {
-        panic!()
-    };
-    swap_hash_map_entry(e0, handle);
+    match update {
+        TileDataUpdate::TransformSet(handle) => swap_hash_map_entry(e0, handle),
+        TileDataUpdate::Erase => {
+            let mut handle = None;
+            swap_hash_map_entry(e0, &mut handle);
+            *update = TileDataUpdate::TransformSet(handle);
+        }
+        TileDataUpdate::DoNothing => (),
+        _ => {
+            Log::err("Tile set update is incompatible with a transform or animation page.");
+            *update = TileDataUpdate::DoNothing;
+        }
+    }
 }
 
 /// A tile set contains three forms of tile, depending on the type of page.
@@ -1971,3 +2028,43 @@ impl ResourceLoader for TileSetLoader {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_data_properties() {
+        let int_id = Uuid::from_u128(1);
+        let float_id = Uuid::from_u128(2);
+        let string_id = Uuid::from_u128(3);
+        let missing_id = Uuid::from_u128(4);
+        let mut data = TileData::default();
+        data.properties.insert(int_id, TileSetPropertyValue::I32(5));
+        data.properties
+            .insert(float_id, TileSetPropertyValue::F32(0.5));
+        data.properties.insert(
+            string_id,
+            TileSetPropertyValue::String(ImmutableString::new("lava")),
+        );
+
+        assert_eq!(data.get_i32(int_id), Some(5));
+        assert_eq!(data.get_f32(float_id), Some(0.5));
+        assert_eq!(data.get_string(string_id), Some("lava"));
+
+        assert_eq!(data.get_i32(missing_id), None);
+        assert_eq!(data.get_f32(missing_id), None);
+        assert_eq!(data.get_string(missing_id), None);
+
+        assert_eq!(data.get_i32(float_id), None);
+        assert_eq!(data.get_f32(string_id), None);
+        assert_eq!(data.get_string(int_id), None);
+
+        let mut ids = data.iter_properties().map(|(id, _)| id).collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, vec![int_id, float_id, string_id]);
+        assert!(data
+            .iter_properties()
+            .any(|(id, value)| id == float_id && *value == TileSetPropertyValue::F32(0.5)));
+    }
+}
@@ -38,8 +38,11 @@
 //! update is the final step before finally applying the modification to a tile map.
 
 use super::*;
-use crate::core::{algebra::Vector2, color::Color, log::Log, type_traits::prelude::*};
-use fxhash::FxHashMap;
+use crate::core::{
+    algebra::Vector2, color::Color, log::Log, math::grid::BresenhamLineIter,
+    type_traits::prelude::*,
+};
+use fxhash::{FxHashMap, FxHashSet};
 use fyrox_core::swap_hash_map_entry;
 use std::{
     borrow::Cow,
@@ -47,71 +50,64 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
-struct BresenhamLineIter {
-    dx: i32,
-    dy: i32,
-    x: i32,
-    y: i32,
-    error: i32,
-    end_x: i32,
-    is_steep: bool,
-    y_step: i32,
-}
-
-impl BresenhamLineIter {
-    fn new(start: Vector2<i32>, end: Vector2<i32>) -> BresenhamLineIter {
-        let (mut x0, mut y0) = (start.x, start.y);
-        let (mut x1, mut y1) = (end.x, end.y);
-
-        let is_steep = (y1 - y0).abs() > (x1 - x0).abs();
-        if is_steep {
-            std::mem::swap(&mut x0, &mut y0);
-            std::mem::swap(&mut x1, &mut y1);
-        }
-
-        if x0 > x1 {
-            std::mem::swap(&mut x0, &mut x1);
-            std::mem::swap(&mut y0, &mut y1);
-        }
-
-        let dx = x1 - x0;
+/// Calculates the cells of the outline of the ellipse that is inscribed in the rectangle with
+/// the given corners, using the midpoint ellipse algorithm. The algorithm works with the
+/// doubled radii, so it handles both odd and even sizes of the rectangle. Rectangles that are a
+/// single cell wide or tall produce a straight line. Some cells may be repeated.
+fn ellipse_outline(start: Vector2<i32>, end: Vector2<i32>) -> Vec<Vector2<i32>> {
+    let rect = TileRect::from_points(start, end);
+    let (mut x0, mut y0) = (rect.position.x as i64, rect.position.y as i64);
+    let (mut x1, mut y1) = (x0 + rect.size.x as i64 - 1, y0 + rect.size.y as i64 - 1);
 
-        BresenhamLineIter {
-            dx,
-            dy: (y1 - y0).abs(),
-            x: x0,
-            y: y0,
-            error: dx / 2,
-            end_x: x1,
-            is_steep,
-            y_step: if y0 < y1 { 1 } else { -1 },
-        }
+    if x0 == x1 || y0 == y1 {
+        return BresenhamLineIter::new(start, end).collect();
     }
-}
 
-impl Iterator for BresenhamLineIter {
-    type Item = Vector2<i32>;
+    let a = x1 - x0;
+    let b = y1 - y0;
+    let b1 = b & 1;
+    // Error increments.
+    let mut dx = 4 * (1 - a) * b * b;
+    let mut dy = 4 * (b1 + 1) * a * a;
+    let mut error = dx + dy + b1 * a * a;
 
-    fn next(&mut self) -> Option<Vector2<i32>> {
-        if self.x > self.end_x {
-            None
-        } else {
-            let ret = if self.is_steep {
-                Vector2::new(self.y, self.x)
-            } else {
-                Vector2::new(self.x, self.y)
-            };
+    // Start at the middle row (or rows) of the rectangle.
+    y0 += (b + 1) / 2;
+    y1 = y0 - b1;
+    let a8 = 8 * a * a;
+    let b8 = 8 * b * b;
 
-            self.x += 1;
-            self.error -= self.dy;
-            if self.error < 0 {
-                self.y += self.y_step;
-                self.error += self.dx;
-            }
-
-            Some(ret)
+    let mut points = Vec::new();
+    let mut push = |x: i64, y: i64| points.push(Vector2::new(x as i32, y as i32));
+    while x0 <= x1 {
+        push(x1, y0);
+        push(x0, y0);
+        push(x0, y1);
+        push(x1, y1);
+        let e2 = 2 * error;
+        if e2 <= dy {
+            y0 += 1;
+            y1 -= 1;
+            dy += a8;
+            error += dy;
+        }
+        if e2 >= dx || 2 * error > dy {
+            x0 += 1;
+            x1 -= 1;
+            dx += b8;
+            error += dx;
         }
     }
+    // Flat ellipses stop too early, finish their tips.
+    while y0 - y1 < b {
+        push(x0 - 1, y0);
+        push(x1 + 1, y0);
+        push(x0 - 1, y1);
+        push(x1 + 1, y1);
+        y0 += 1;
+        y1 -= 1;
+    }
+    points
 }
 
 /// This represents a change to some pages of a tile set, without specifying which tile set.
@@ -281,15 +277,37 @@ impl TileDataUpdate {
             _ => panic!(),
         }
     }
+    /// Remove `TileData` and turn this object into `Erase`, if this is a MaterialTile.
+    /// Otherwise, return `None` and leave this object unchanged.
+    pub fn try_take_data(&mut self) -> Option<TileData> {
+        match self {
+            TileDataUpdate::MaterialTile(_) => Some(self.take_data()),
+            _ => None,
+        }
+    }
+    /// Remove `TileDefinition` and turn this object into `Erase`, if this is a FreeformTile.
+    /// Otherwise, return `None` and leave this object unchanged.
+    pub fn try_take_definition(&mut self) -> Option<TileDefinition> {
+        match self {
+            TileDataUpdate::FreeformTile(_) => Some(self.take_definition()),
+            _ => None,
+        }
+    }
     /// Swap whatever value is in this tile update with the corresponding value in the given TileData.
     /// If this update has no data to swap, then do nothing and set this update to `DoNothing`.
     pub fn swap_with_data(&mut self, data: &mut TileData) {
+        if let Err(err) = self.try_swap_with_data(data) {
+            Log::err(err.to_string());
+            *self = Self::DoNothing;
+        }
+    }
+    /// Swap whatever value is in this tile update with the corresponding value in the given TileData.
+    /// If this update has no data to swap, return an error and leave both this update and the data
+    /// unchanged.
+    pub fn try_swap_with_data(&mut self, data: &mut TileData) -> Result<(), TileDataSwapError> {
         match self {
             TileDataUpdate::DoNothing => (),
-            TileDataUpdate::Erase => {
-                Log::err("Tile data swap error");
-                *self = Self::DoNothing;
-            }
+            TileDataUpdate::Erase => return Err(TileDataSwapError::Erase),
             TileDataUpdate::MaterialTile(tile_data) => std::mem::swap(tile_data, data),
             TileDataUpdate::FreeformTile(tile_definition) => {
                 std::mem::swap(&mut tile_definition.data, data)
@@ -332,19 +350,111 @@ impl TileDataUpdate {
                     *self = TileDataUpdate::Property(*uuid, None);
                 }
             },
-            TileDataUpdate::TransformSet(_) => {
-                Log::err("Tile data swap error");
-                *self = Self::DoNothing;
-            }
-            TileDataUpdate::Material(_) => {
-                Log::err("Tile data swap error");
-                *self = Self::DoNothing;
-            }
+            TileDataUpdate::TransformSet(_) => return Err(TileDataSwapError::TransformSet),
+            TileDataUpdate::Material(_) => return Err(TileDataSwapError::Material),
         }
+        Ok(())
+    }
+}
+
+/// An error of swapping a [`TileDataUpdate`] with [`TileData`], for updates that have no data that
+/// could be swapped. Each variant names the kind of the update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileDataSwapError {
+    /// The update is [`TileDataUpdate::Erase`].
+    Erase,
+    /// The update is [`TileDataUpdate::TransformSet`].
+    TransformSet,
+    /// The update is [`TileDataUpdate::Material`].
+    Material,
+}
+
+impl std::fmt::Display for TileDataSwapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            TileDataSwapError::Erase => "an erase",
+            TileDataSwapError::TransformSet => "a transform set",
+            TileDataSwapError::Material => "a material",
+        };
+        write!(f, "Tile data swap error: {kind} update has no tile data.")
     }
 }
 
+impl std::error::Error for TileDataSwapError {}
+
 impl TileSetUpdate {
+    /// Construct an update that changes the data of the tile at the given handle from `old` to `new`,
+    /// using the narrowest update that makes the change, so that undo history and saved diffs
+    /// stay small. Since an update holds a single change for each tile:
+    ///
+    /// * If nothing differs, the update is empty.
+    /// * If only the color differs, the update is [`TileDataUpdate::Color`].
+    /// * If only one property differs, the update is [`TileDataUpdate::Property`].
+    /// * If only colliders differ, the update is [`TileDataUpdate::Collider`], where removed
+    ///   colliders are replaced by [`TileCollider::None`].
+    /// * Otherwise the update is [`TileDataUpdate::MaterialTile`] with a copy of `new`,
+    ///   which is only suitable for tiles of material pages.
+    pub fn from_diff(old: &TileData, new: &TileData, handle: TileDefinitionHandle) -> Self {
+        let mut update = Self::default();
+        let color_changed = old.color != new.color;
+        let mut changed_properties = old
+            .properties
+            .keys()
+            .chain(new.properties.keys())
+            .filter(|uuid| old.properties.get(uuid) != new.properties.get(uuid))
+            .copied()
+            .collect::<FxHashSet<_>>();
+        let mut changed_colliders = FxHashMap::default();
+        for uuid in old.colliders.keys().chain(new.colliders.keys()) {
+            let new_value = new.colliders.get(uuid);
+            if old.colliders.get(uuid) != new_value {
+                changed_colliders.insert(*uuid, new_value.cloned().unwrap_or(TileCollider::None));
+            }
+        }
+        let change_count = usize::from(color_changed)
+            + changed_properties.len()
+            + usize::from(!changed_colliders.is_empty());
+        let data_update = match change_count {
+            0 => return update,
+            1 if color_changed => TileDataUpdate::Color(new.color),
+            1 if !changed_colliders.is_empty() => TileDataUpdate::Collider(changed_colliders),
+            1 => {
+                let uuid = changed_properties.drain().next().unwrap();
+                TileDataUpdate::Property(uuid, new.properties.get(&uuid).cloned())
+            }
+            _ => TileDataUpdate::MaterialTile(new.clone()),
+        };
+        update.insert(handle, data_update);
+        update
+    }
+    /// Apply this update to the given tile set and modify this update so that it contains
+    /// the data that was replaced, just as [`Tiles::swap_tiles`] does for tile maps.
+    /// Calling this twice with the same tile set leaves the tile set unchanged.
+    /// See [`TileSet::swap`] for details.
+    ///
+    /// These updates are invertible: `MaterialTile`, `FreeformTile`, `Color`, `Property`,
+    /// `PropertySlice`, `Collider`, and `TransformSet`. `Material` is invertible on freeform
+    /// pages, and an empty freeform cell receives a new tile that has the given material.
+    /// `Erase` becomes an update that restores the erased tile, and an update that creates
+    /// a tile becomes `Erase`.
+    ///
+    /// An update that refers to a missing page is not applied, and an update that is
+    /// incompatible with its page is logged and replaced by `DoNothing`.
+    pub fn swap_with_tile_set(&mut self, tile_set: &mut TileSet) {
+        tile_set.swap(self);
+    }
+    /// Iterates over the `(page, tile)` coordinates of every tile that this update changes.
+    pub fn changed_cells(&self) -> impl Iterator<Item = (Vector2<i32>, Vector2<i32>)> + '_ {
+        self.keys().map(|handle| (handle.page(), handle.tile()))
+    }
+    /// Iterates over the distinct coordinates of the pages that this update changes, in no
+    /// particular order.
+    pub fn changed_pages(&self) -> impl Iterator<Item = Vector2<i32>> {
+        self.keys()
+            .map(|handle| handle.page())
+            .collect::<FxHashSet<_>>()
+            .into_iter()
+    }
     /// Attempt to fill this TileSetUpdate based upon a TransTilesUpdate.
     /// The TransTilesUpdate contains only positions, transformations, and TileDefinitionHandles for the tiles that are to be written.
     /// In order to construct a TileSetUpdate, we use the given TileSet to copy tile bounds and tile definition data
@@ -519,32 +629,64 @@ impl TileSetUpdate {
         property_id: Uuid,
         value: i8,
     ) {
-        use TileSetPropertyValue as PropValue;
         let index = TileSetPropertyValue::nine_position_to_index(subposition);
         if let Some(handle) = TileDefinitionHandle::try_new(page, position) {
-            match self.entry(handle) {
-                Entry::Occupied(mut e) => match e.get_mut() {
-                    TileDataUpdate::PropertySlice(uuid, d0) if *uuid == property_id => {
-                        d0[index] = Some(value);
-                    }
-                    TileDataUpdate::Property(uuid, Some(PropValue::NineSlice(d0)))
-                        if *uuid == property_id =>
-                    {
-                        d0.0[index] = value;
-                    }
-                    d0 => {
-                        let mut data = [0; 9];
-                        data[index] = value;
-                        *d0 = TileDataUpdate::Property(
-                            property_id,
-                            Some(PropValue::NineSlice(NineI8(data))),
-                        );
+            self.set_property_slice_at(handle, index, property_id, value);
+        }
+    }
+    /// Set the given value to the slice with the given index of the given property of the given tile.
+    fn set_property_slice_at(
+        &mut self,
+        handle: TileDefinitionHandle,
+        index: usize,
+        property_id: Uuid,
+        value: i8,
+    ) {
+        use TileSetPropertyValue as PropValue;
+        match self.entry(handle) {
+            Entry::Occupied(mut e) => match e.get_mut() {
+                TileDataUpdate::PropertySlice(uuid, d0) if *uuid == property_id => {
+                    d0[index] = Some(value);
+                }
+                TileDataUpdate::Property(uuid, Some(PropValue::NineSlice(d0)))
+                    if *uuid == property_id =>
+                {
+                    d0.0[index] = value;
+                }
+                d0 => {
+                    let mut data = [0; 9];
+                    data[index] = value;
+                    *d0 = TileDataUpdate::Property(
+                        property_id,
+                        Some(PropValue::NineSlice(NineI8(data))),
+                    );
+                }
+            },
+            Entry::Vacant(e) => {
+                let mut data = [None; 9];
+                data[index] = Some(value);
+                let _ = e.insert(TileDataUpdate::PropertySlice(property_id, data));
+            }
+        }
+    }
+    /// Fold the given update into this one, so that several edits can be applied as one.
+    /// Entries of `other` override the entries of this update for the same tile, except for
+    /// [`TileDataUpdate::PropertySlice`]: its slices are written over the slices of the same
+    /// property in this update, exactly as if they were set with [`Self::set_property_slice`].
+    pub fn merge(&mut self, other: TileSetUpdate) {
+        for (handle, update) in other.0 {
+            match update {
+                TileDataUpdate::PropertySlice(property_id, slices)
+                    if slices.iter().any(Option::is_some) =>
+                {
+                    for (index, value) in slices.into_iter().enumerate() {
+                        if let Some(value) = value {
+                            self.set_property_slice_at(handle, index, property_id, value);
+                        }
                     }
-                },
-                Entry::Vacant(e) => {
-                    let mut data = [None; 9];
-                    data[index] = Some(value);
-                    let _ = e.insert(TileDataUpdate::PropertySlice(property_id, data));
+                }
+                update => {
+                    self.insert(handle, update);
                 }
             }
         }
@@ -575,10 +717,59 @@ impl TileSetUpdate {
             self.insert(handle, TileDataUpdate::Material(value));
         }
     }
+    /// Set the given material on the given tile along with the given property values,
+    /// as a single update. Only freeform tiles store their own material, so this produces
+    /// a [`TileDataUpdate::FreeformTile`] that replaces the whole tile, and any property
+    /// or collider that is not in `properties` is cleared.
+    pub fn set_material_with_properties(
+        &mut self,
+        page: Vector2<i32>,
+        position: Vector2<i32>,
+        material_bounds: TileMaterialBounds,
+        properties: FxHashMap<Uuid, TileSetPropertyValue>,
+    ) {
+        if let Some(handle) = TileDefinitionHandle::try_new(page, position) {
+            let data = TileData {
+                properties,
+                ..TileData::default()
+            };
+            self.insert(
+                handle,
+                TileDataUpdate::FreeformTile(TileDefinition {
+                    material_bounds,
+                    data,
+                }),
+            );
+        }
+    }
 }
 
 type RotTileHandle = (OrthoTransformation, TileDefinitionHandle);
 
+/// An axis of a tile grid along which a gradient changes, see [`TransTilesUpdate::rect_fill_dithered`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GradientAxis {
+    /// The gradient goes from the left side to the right side.
+    #[default]
+    X,
+    /// The gradient goes from the bottom side to the top side.
+    Y,
+}
+
+/// Axes across which the tiles are mirrored, see [`TransTilesUpdate::draw_tiles_mirrored`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MirrorAxis {
+    /// No mirroring.
+    #[default]
+    None,
+    /// Mirror across a vertical line, so the x coordinates are reflected.
+    X,
+    /// Mirror across a horizontal line, so the y coordinates are reflected.
+    Y,
+    /// Mirror across both lines, which produces three mirrored copies.
+    Both,
+}
+
 /// This is a step in the process of performing an edit to a tile map, brush, or tile set.
 /// It provides handles for the tiles to be written and the transformation to apply to those
 /// tiles. A None indicates that the tile is to be erased.
@@ -618,7 +809,78 @@ impl DerefMut for TransTilesUpdate {
     }
 }
 
+/// A row or a column of a nine-slice brush.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BorderSlot {
+    Min,
+    Middle,
+    Max,
+}
+
+impl BorderSlot {
+    /// Chooses the slot by whether the neighbours at the lower and higher coordinates are
+    /// outside of the region.
+    fn of(min_outside: bool, max_outside: bool) -> Self {
+        match (min_outside, max_outside) {
+            (true, false) => Self::Min,
+            (false, true) => Self::Max,
+            _ => Self::Middle,
+        }
+    }
+    /// Returns the coordinate of the slot within a brush that starts at `min` and has the given
+    /// size. Middle slots of brushes larger than 3x3 are repeated across the target coordinates.
+    fn select(self, min: i32, size: i32, target: i32) -> i32 {
+        match self {
+            Self::Min => min,
+            Self::Max => min + size - 1,
+            Self::Middle if size > 2 => min + 1 + target.rem_euclid(size - 2),
+            Self::Middle => min,
+        }
+    }
+}
+
+/// Returns the first and the last of the middle rows or columns of a nine-slice brush that
+/// starts at `min` and has the given size. A brush that is one tile wide uses that tile for
+/// the middle too, while a brush that is two tiles wide has no middle.
+fn nine_slice_middle(min: i32, size: i32) -> Option<(i32, i32)> {
+    match size {
+        1 => Some((min, min)),
+        2 => None,
+        _ => Some((min + 1, min + size - 2)),
+    }
+}
+
+/// Offsets of the neighbours of a cell that share an edge with it.
+const FOUR_NEIGHBOURS: [Vector2<i32>; 4] = [
+    Vector2::new(-1, 0),
+    Vector2::new(1, 0),
+    Vector2::new(0, -1),
+    Vector2::new(0, 1),
+];
+
+/// Offsets of the neighbours of a cell that share an edge or a corner with it.
+const EIGHT_NEIGHBOURS: [Vector2<i32>; 8] = [
+    Vector2::new(-1, 0),
+    Vector2::new(1, 0),
+    Vector2::new(0, -1),
+    Vector2::new(0, 1),
+    Vector2::new(-1, -1),
+    Vector2::new(1, -1),
+    Vector2::new(-1, 1),
+    Vector2::new(1, 1),
+];
+
 impl TransTilesUpdate {
+    /// Creates an empty update with space for at least the given number of tiles. Use it when
+    /// the amount of tiles to be written is roughly known in advance, to avoid rehashing during
+    /// large fills.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(TileGridMap::with_capacity(capacity))
+    }
+    /// Reserves space for at least the given number of additional tiles.
+    pub fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
     /// Construct a TilesUpdate by finding the transformed version of each tile
     /// in the given tile set.
     pub fn build_tiles_update(&self, tile_set: &OptionTileSet) -> TilesUpdate {
@@ -646,30 +908,178 @@ impl TransTilesUpdate {
         start_point: Vector2<i32>,
         brush: &S,
     ) {
+        self.flood_fill_bounded(
+            tiles,
+            start_point,
+            brush,
+            usize::MAX,
+            OptionTileRect::default(),
+        );
+    }
+    /// Fills the given tiles at the given point using tiles from the given source, just like
+    /// [`Self::flood_fill`], but stops once `max_cells` cells were written. If `clip` is not
+    /// empty, the fill does not spread outside of it. Returns the amount of cells that were
+    /// actually written. A partial fill is a regular update, so it can be applied and undone
+    /// the same way as a complete one.
+    pub fn flood_fill_bounded<T: BoundedTileSource, S: TileSource>(
+        &mut self,
+        tiles: &T,
+        start_point: Vector2<i32>,
+        brush: &S,
+        max_cells: usize,
+        clip: OptionTileRect,
+    ) -> usize {
+        let allowed_definition = tiles.get_at(start_point);
+        self.flood_fill_inner(
+            tiles,
+            start_point,
+            brush,
+            |definition| definition == allowed_definition,
+            &FOUR_NEIGHBOURS,
+            max_cells,
+            clip,
+        )
+    }
+    /// Fills the given tiles at the given point using tiles from the given source, just like
+    /// [`Self::flood_fill`], but the fill also spreads through the corners of the cells, so
+    /// regions that only touch diagonally are filled as well.
+    pub fn flood_fill_diagonal<T: BoundedTileSource, S: TileSource>(
+        &mut self,
+        tiles: &T,
+        start_point: Vector2<i32>,
+        brush: &S,
+    ) {
+        let allowed_definition = tiles.get_at(start_point);
+        self.flood_fill_inner(
+            tiles,
+            start_point,
+            brush,
+            |definition| definition == allowed_definition,
+            &EIGHT_NEIGHBOURS,
+            usize::MAX,
+            OptionTileRect::default(),
+        );
+    }
+    /// Fills the given tiles at the given point using tiles from the given source, just like
+    /// [`Self::flood_fill`], but the cells that may be filled are defined by the given predicate
+    /// instead of being equal to the cell at the start point. The predicate is called with the
+    /// tile at each cell, or `None` for free cells. Nothing is filled if the start point does not
+    /// satisfy the predicate. This allows to fill a region of any tiles from some set of tiles,
+    /// such as a region of various ground tiles.
+    pub fn flood_fill_matching<T, S, P>(
+        &mut self,
+        tiles: &T,
+        start_point: Vector2<i32>,
+        brush: &S,
+        predicate: P,
+    ) where
+        T: BoundedTileSource,
+        S: TileSource,
+        P: Fn(Option<TileDefinitionHandle>) -> bool,
+    {
+        self.flood_fill_inner(
+            tiles,
+            start_point,
+            brush,
+            predicate,
+            &FOUR_NEIGHBOURS,
+            usize::MAX,
+            OptionTileRect::default(),
+        );
+    }
+    /// Fills the cells that satisfy the predicate and are connected to the start point through
+    /// the given neighbour offsets, until `max_cells` cells are written. The fill is limited
+    /// to the given clip rectangle, unless it is empty. Returns the amount of written cells.
+    #[allow(clippy::too_many_arguments)]
+    fn flood_fill_inner<T, S, P>(
+        &mut self,
+        tiles: &T,
+        start_point: Vector2<i32>,
+        brush: &S,
+        predicate: P,
+        neighbours: &[Vector2<i32>],
+        max_cells: usize,
+        clip: OptionTileRect,
+    ) -> usize
+    where
+        T: BoundedTileSource,
+        S: TileSource,
+        P: Fn(Option<TileDefinitionHandle>) -> bool,
+    {
         let mut bounds = tiles.bounding_rect();
         bounds.push(start_point);
+        if let Some(clip) = *clip {
+            bounds.clip(clip);
+        }
 
-        let allowed_definition = tiles.get_at(start_point);
-        let mut stack = vec![start_point];
+        let mut written = 0;
+        let mut stack = if bounds.contains(start_point) {
+            vec![start_point]
+        } else {
+            Vec::new()
+        };
         while let Some(position) = stack.pop() {
+            if written >= max_cells {
+                break;
+            }
             let definition = tiles.get_at(position);
-            if definition == allowed_definition && !self.contains_key(&position) {
+            if predicate(definition) && !self.contains_key(&position) {
                 let value = brush.get_at(position).map(|h| (brush.transformation(), h));
                 self.insert(position, value);
+                written += 1;
 
                 // Continue on neighbours.
-                for neighbour_position in [
-                    Vector2::new(position.x - 1, position.y),
-                    Vector2::new(position.x + 1, position.y),
-                    Vector2::new(position.x, position.y - 1),
-                    Vector2::new(position.x, position.y + 1),
-                ] {
+                for offset in neighbours {
+                    let neighbour_position = position + offset;
                     if bounds.contains(neighbour_position) {
                         stack.push(neighbour_position);
                     }
                 }
             }
         }
+        written
+    }
+    /// The positions and handles of the tiles that this update writes, excluding erased positions.
+    fn written_tiles(&self) -> impl Iterator<Item = (Vector2<i32>, TileDefinitionHandle)> {
+        self.iter()
+            .filter_map(|(position, value)| Some((*position, value.as_ref()?.1)))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+    /// The positions and handles of the tiles that [`Self::draw_tiles`] would write, for showing
+    /// a preview of the operation without modifying any update.
+    pub fn preview_draw_tiles(
+        origin: Vector2<i32>,
+        brush: &Stamp,
+    ) -> impl Iterator<Item = (Vector2<i32>, TileDefinitionHandle)> {
+        let mut update = Self::default();
+        update.draw_tiles(origin, brush);
+        update.written_tiles()
+    }
+    /// The positions and handles of the tiles that [`Self::rect_fill`] would write, for showing
+    /// a preview of the operation without modifying any update.
+    pub fn preview_rect_fill(
+        start: Vector2<i32>,
+        end: Vector2<i32>,
+        stamp: &Stamp,
+    ) -> impl Iterator<Item = (Vector2<i32>, TileDefinitionHandle)> {
+        let mut update = Self::default();
+        update.rect_fill(start, end, stamp);
+        update.written_tiles()
+    }
+    /// The positions and handles of the tiles that [`Self::draw_line`] would write, for showing
+    /// a preview of the operation without modifying any update.
+    ///
+    /// Random tile sources choose new tiles each time they are asked, so the preview of a line
+    /// drawn with a random source is only indicative of the tiles that will actually be drawn.
+    pub fn preview_draw_line<S: TileSource>(
+        from: Vector2<i32>,
+        to: Vector2<i32>,
+        brush: &S,
+    ) -> impl Iterator<Item = (Vector2<i32>, TileDefinitionHandle)> {
+        let mut update = Self::default();
+        update.draw_line(from, to, brush);
+        update.written_tiles()
     }
     /// Draws the given tiles on the tile map
     #[inline]
@@ -679,6 +1089,49 @@ impl TransTilesUpdate {
             self.insert(origin + local_position, Some((trans, *handle)));
         }
     }
+    /// Draws the given tiles on the tile map, just like [`Self::draw_tiles`], and also draws
+    /// their reflections across the given axes. The axes pass through the center of the cell at
+    /// `axis_position`: the x coordinate of it is used for [`MirrorAxis::X`] and the y coordinate
+    /// for [`MirrorAxis::Y`]. Reflected tiles are flipped accordingly. The copies are drawn in
+    /// order: original, x-reflected, y-reflected, reflected across both axes, and where copies
+    /// overlap near an axis the last one wins.
+    pub fn draw_tiles_mirrored(
+        &mut self,
+        origin: Vector2<i32>,
+        brush: &Stamp,
+        axis: MirrorAxis,
+        axis_position: Vector2<i32>,
+    ) {
+        let (mirror_x, mirror_y) = match axis {
+            MirrorAxis::None => (false, false),
+            MirrorAxis::X => (true, false),
+            MirrorAxis::Y => (false, true),
+            MirrorAxis::Both => (true, true),
+        };
+        let trans = brush.transformation();
+        for (flip_x, flip_y) in [(false, false), (true, false), (false, true), (true, true)] {
+            if (flip_x && !mirror_x) || (flip_y && !mirror_y) {
+                continue;
+            }
+            let mut copy_trans = trans;
+            if flip_x {
+                copy_trans = copy_trans.x_flipped();
+            }
+            if flip_y {
+                copy_trans = copy_trans.y_flipped();
+            }
+            for (local_position, handle) in brush.iter() {
+                let mut position = origin + local_position;
+                if flip_x {
+                    position.x = 2 * axis_position.x - position.x;
+                }
+                if flip_y {
+                    position.y = 2 * axis_position.y - position.y;
+                }
+                self.insert(position, Some((copy_trans, *handle)));
+            }
+        }
+    }
     /// Erases the tiles under the given brush.
     #[inline]
     pub fn erase_stamp(&mut self, origin: Vector2<i32>, brush: &Stamp) {
@@ -686,6 +1139,75 @@ impl TransTilesUpdate {
             self.insert(origin + local_position, None);
         }
     }
+    /// Erases only the tiles within the given region of `tiles` that are equal to the given handle,
+    /// leaving all other tiles untouched.
+    pub fn erase_matching(
+        &mut self,
+        tiles: &Tiles,
+        region: OptionTileRect,
+        handle: TileDefinitionHandle,
+    ) {
+        for position in region.iter() {
+            if tiles.get_at(position) == Some(handle) {
+                self.insert(position, None);
+            }
+        }
+    }
+    /// Moves the tiles within the given region of `tiles` by the given offset. The original positions
+    /// are erased and the tiles are written at their new positions, so applying the update moves
+    /// the tiles. The source and destination may overlap.
+    pub fn translate_region(
+        &mut self,
+        tiles: &Tiles,
+        region: OptionTileRect,
+        offset: Vector2<i32>,
+    ) {
+        self.translate_region_transformed(tiles, region, offset, OrthoTransformation::identity());
+    }
+    /// Moves the tiles within the given region of `tiles` by the given offset, like
+    /// [`translate_region`](Self::translate_region), and gives each moved tile the given
+    /// transformation. The positions of the tiles are not transformed.
+    pub fn translate_region_transformed(
+        &mut self,
+        tiles: &Tiles,
+        region: OptionTileRect,
+        offset: Vector2<i32>,
+        transform: OrthoTransformation,
+    ) {
+        // Read every tile before writing anything, since the destination may overlap the source.
+        let moved = region
+            .iter()
+            .filter_map(|position| Some((position, tiles.get_at(position)?)))
+            .collect::<Vec<_>>();
+        for (position, _) in moved.iter() {
+            self.insert(*position, None);
+        }
+        for (position, handle) in moved {
+            self.insert(position + offset, Some((transform, handle)));
+        }
+    }
+    /// Rotates the tiles within the given region of `tiles` counter-clockwise by the given number
+    /// of 90-degree turns around the center of the region. The original positions are erased and
+    /// the rotated tiles are written with their transformations rotated as well, in the same way
+    /// that [`Stamp::rotate`] rotates a stamp.
+    pub fn rotate_region(&mut self, tiles: &Tiles, region: OptionTileRect, turns: i8) {
+        let Some(rect) = *region else {
+            return;
+        };
+        let center = rect.center();
+        let mut stamp = Stamp::default();
+        for position in rect.iter() {
+            if let Some(handle) = tiles.get_at(position) {
+                stamp.insert(position - center, handle);
+                self.insert(position, None);
+            }
+        }
+        stamp.rotate(turns);
+        let trans = stamp.transformation();
+        for (position, handle) in stamp.iter() {
+            self.insert(center + position, Some((trans, *handle)));
+        }
+    }
     /// Erases the given tile.
     pub fn erase(&mut self, position: Vector2<i32>) {
         self.insert(position, None);
@@ -701,6 +1223,18 @@ impl TransTilesUpdate {
         let region = TileRegion::from_points(start, end);
         self.rect_fill_inner(region, &RandomTileSource(stamp));
     }
+    /// Fills the given rectangle using random tiles from the given stamp, chosen according to
+    /// the given weights, see [`WeightedRandomTileSource`].
+    pub fn rect_fill_weighted(
+        &mut self,
+        start: Vector2<i32>,
+        end: Vector2<i32>,
+        stamp: &Stamp,
+        weights: &FxHashMap<TileDefinitionHandle, f32>,
+    ) {
+        let region = TileRegion::from_points(start, end);
+        self.rect_fill_inner(region, &WeightedRandomTileSource(stamp, weights));
+    }
     /// Fills the given rectangle using the given tiles.
     fn rect_fill_inner<S: TileSource>(&mut self, region: TileRegion, brush: &S) {
         let trans = brush.transformation();
@@ -710,6 +1244,161 @@ impl TransTilesUpdate {
             }
         }
     }
+    /// Fills the given rectangle with a gradient from the `from` tile to the `to` tile along the
+    /// given axis. Since there's nothing in between two tiles, the gradient is produced by
+    /// Floyd-Steinberg error diffusion: the rectangle is visited in serpentine order (each row
+    /// is visited in the direction opposite to the previous row) and the error of choosing one
+    /// of the two tiles is propagated to the neighbours that are not visited yet. This produces
+    /// smoother gradients than ordered dithering.
+    pub fn rect_fill_dithered(
+        &mut self,
+        start: Vector2<i32>,
+        end: Vector2<i32>,
+        from: TileDefinitionHandle,
+        to: TileDefinitionHandle,
+        axis: GradientAxis,
+    ) {
+        let Some(rect) = *OptionTileRect::from_points(start, end) else {
+            return;
+        };
+        let w = rect.w() as usize;
+        let h = rect.h() as usize;
+        let length = match axis {
+            GradientAxis::X => w,
+            GradientAxis::Y => h,
+        };
+        // Desired amount of the `to` tile at the given offset along the axis.
+        let intensity = |offset: usize| {
+            if length > 1 {
+                offset as f32 / (length - 1) as f32
+            } else {
+                0.5
+            }
+        };
+        let mut errors = vec![0.0f32; w * h];
+        let trans = OrthoTransformation::identity();
+        for y in 0..h {
+            let forward = y % 2 == 0;
+            for i in 0..w {
+                let x = if forward { i } else { w - 1 - i };
+                let offset = match axis {
+                    GradientAxis::X => x,
+                    GradientAxis::Y => y,
+                };
+                let value = intensity(offset) + errors[y * w + x];
+                let (handle, quantized) = if value >= 0.5 { (to, 1.0) } else { (from, 0.0) };
+                let error = value - quantized;
+                let position = rect.position + Vector2::new(x as i32, y as i32);
+                self.insert(position, Some((trans, handle)));
+
+                // Distribute the error to the neighbours that are not visited yet, in the
+                // direction of the traversal.
+                let dx: isize = if forward { 1 } else { -1 };
+                for (nx, ny, weight) in [
+                    (x as isize + dx, y, 7.0 / 16.0),
+                    (x as isize - dx, y + 1, 3.0 / 16.0),
+                    (x as isize, y + 1, 5.0 / 16.0),
+                    (x as isize + dx, y + 1, 1.0 / 16.0),
+                ] {
+                    if nx >= 0 && (nx as usize) < w && ny < h {
+                        errors[ny * w + nx as usize] += error * weight;
+                    }
+                }
+            }
+        }
+    }
+    /// Draw a line from a point to point with the given thickness, repeating the given stamp.
+    /// The line is widened by shifting it across its minor axis, which keeps the band free of
+    /// gaps at any angle. The amount of shifted copies is chosen so that the width of the band
+    /// measured perpendicular to the line is close to the thickness. Thickness of 1 (or 0)
+    /// draws the same line as [`Self::draw_line`] with [`Stamp::repeat`].
+    pub fn draw_thick_line(
+        &mut self,
+        from: Vector2<i32>,
+        to: Vector2<i32>,
+        stamp: &Stamp,
+        thickness: u32,
+    ) {
+        let brush = stamp.repeat(from, to);
+        if thickness <= 1 {
+            self.draw_line(from, to, &brush);
+            return;
+        }
+        let delta = to - from;
+        let is_steep = delta.y.abs() > delta.x.abs();
+        let major = delta.x.abs().max(delta.y.abs()).max(1) as f32;
+        let length = (delta.cast::<f32>().norm()).max(1.0);
+        let copies = ((thickness as f32 * length / major).round() as i32).max(1);
+        let first = -(copies - 1) / 2;
+
+        let trans = brush.transformation();
+        let mut cells = FxHashSet::default();
+        for position in BresenhamLineIter::new(from, to) {
+            for shift in first..first + copies {
+                let offset = if is_steep {
+                    Vector2::new(shift, 0)
+                } else {
+                    Vector2::new(0, shift)
+                };
+                cells.insert(position + offset);
+            }
+        }
+        for position in cells {
+            if let Some(handle) = brush.get_at(position - from) {
+                self.insert(position, Some((trans, handle)));
+            }
+        }
+    }
+    /// Draws the outline of the ellipse that is inscribed in the rectangle with the given corners,
+    /// repeating the given stamp along the outline. The corners may be given in any order.
+    /// If the rectangle is a single cell wide or tall, a straight line is drawn instead.
+    pub fn draw_ellipse(&mut self, start: Vector2<i32>, end: Vector2<i32>, stamp: &Stamp) {
+        let stamp_source = stamp.repeat(start, end);
+        let trans = stamp_source.transformation();
+        for position in ellipse_outline(start, end) {
+            if let Some(handle) = stamp_source.get_at(position - start) {
+                self.insert(position, Some((trans, handle)));
+            }
+        }
+    }
+    /// Fills the ellipse that is inscribed in the rectangle with the given corners using the
+    /// given stamp, just like [`Self::rect_fill`] fills a rectangle.
+    pub fn fill_ellipse(&mut self, start: Vector2<i32>, end: Vector2<i32>, stamp: &Stamp) {
+        let stamp_source = stamp.repeat(start, end);
+        self.fill_ellipse_inner(start, end, &stamp_source);
+    }
+    /// Fills the ellipse that is inscribed in the rectangle with the given corners using random
+    /// tiles from the given stamp.
+    pub fn fill_ellipse_random(&mut self, start: Vector2<i32>, end: Vector2<i32>, stamp: &Stamp) {
+        self.fill_ellipse_inner(start, end, &RandomTileSource(stamp));
+    }
+    /// Fills the ellipse using the given tiles, row by row between the cells of its outline.
+    fn fill_ellipse_inner<S: TileSource>(
+        &mut self,
+        start: Vector2<i32>,
+        end: Vector2<i32>,
+        brush: &S,
+    ) {
+        let mut spans = FxHashMap::<i32, (i32, i32)>::default();
+        for position in ellipse_outline(start, end) {
+            spans
+                .entry(position.y)
+                .and_modify(|(min, max)| {
+                    *min = (*min).min(position.x);
+                    *max = (*max).max(position.x);
+                })
+                .or_insert((position.x, position.x));
+        }
+        let trans = brush.transformation();
+        for (y, (min, max)) in spans {
+            for x in min..=max {
+                let position = Vector2::new(x, y);
+                if let Some(handle) = brush.get_at(position - start) {
+                    self.insert(position, Some((trans, handle)));
+                }
+            }
+        }
+    }
     /// Draw a line from a point to point.
     pub fn draw_line<S: TileSource>(&mut self, from: Vector2<i32>, to: Vector2<i32>, brush: &S) {
         let trans = brush.transformation();
@@ -723,11 +1412,40 @@ impl TransTilesUpdate {
     /// Fills in a rectangle using special brush with 3x3 tiles. It puts
     /// corner tiles in the respective corners of the target rectangle and draws lines between each
     /// corner using middle tiles.
+    ///
+    /// Brushes smaller than 3x3 are handled one axis at a time. If the brush is one tile wide
+    /// along an axis, then that tile is also used for the edges and the middle along that axis,
+    /// so a 1x1 brush fills the whole rectangle. If the brush is two tiles wide along an axis,
+    /// then it has no middle along that axis and the cells between the corners are left empty,
+    /// so a 2x2 brush only draws the four corners.
     pub fn nine_slice(&mut self, start: Vector2<i32>, end: Vector2<i32>, brush: &Stamp) {
         self.nine_slice_inner(
             start,
             end,
             brush,
+            true,
+            |update, target_region, source, source_region| {
+                update.rect_fill_inner(
+                    target_region,
+                    &RepeatTileSource {
+                        source,
+                        region: source_region,
+                    },
+                )
+            },
+        );
+    }
+    /// Draws the outline of a rectangle using special brush with 3x3 tiles, the same brush that
+    /// is used by [`Self::nine_slice`]. It puts corner tiles in the respective corners of
+    /// the target rectangle and draws lines between each corner using edge tiles repeated along
+    /// the edges, but leaves the inside of the rectangle untouched. A rectangle that is one tile
+    /// wide or tall is drawn as a single line.
+    pub fn draw_rect_outline(&mut self, start: Vector2<i32>, end: Vector2<i32>, brush: &Stamp) {
+        self.nine_slice_inner(
+            start,
+            end,
+            brush,
+            false,
             |update, target_region, source, source_region| {
                 update.rect_fill_inner(
                     target_region,
@@ -741,12 +1459,14 @@ impl TransTilesUpdate {
     }
     /// Fills in a rectangle using special brush with 3x3 tiles. It puts
     /// corner tiles in the respective corners of the target rectangle and draws lines between each
-    /// corner using middle tiles shuffled into random order.
+    /// corner using middle tiles shuffled into random order. Brushes smaller than 3x3
+    /// are handled as in [`Self::nine_slice`].
     pub fn nine_slice_random(&mut self, start: Vector2<i32>, end: Vector2<i32>, brush: &Stamp) {
         self.nine_slice_inner(
             start,
             end,
             brush,
+            true,
             |update, target_region, source, source_region| {
                 update.rect_fill_inner(
                     target_region,
@@ -756,15 +1476,58 @@ impl TransTilesUpdate {
         );
     }
 
+    /// Frames the region of occupied cells of the given tile source using special brush with
+    /// 3x3 tiles, the same brush that is used by [`Self::nine_slice`]. Every cell of the region
+    /// gets a tile of the brush that is chosen by its neighbours: cells on the perimeter get
+    /// edge or corner tiles facing the outside of the region, and the rest get middle tiles.
+    ///
+    /// There are no special tiles for concave corners, so a cell whose orthogonal neighbours are
+    /// all inside but a single diagonal neighbour is outside gets the corner tile that faces
+    /// that diagonal. A part of the region that is one cell wide gets middle tiles across its
+    /// width, so a one-cell-wide strip is drawn with edge tiles at its ends only.
+    pub fn auto_border<T: BoundedTileSource>(&mut self, region: &T, brush: &Stamp) {
+        let Some(stamp_rect) = *brush.bounding_rect() else {
+            return;
+        };
+        let inside = |position: Vector2<i32>| region.get_at(position).is_some();
+        let trans = brush.transformation();
+        for position in region.bounding_rect().iter() {
+            if !inside(position) {
+                continue;
+            }
+            let outside = |dx: i32, dy: i32| !inside(position + Vector2::new(dx, dy));
+            let mut slot_x = BorderSlot::of(outside(-1, 0), outside(1, 0));
+            let mut slot_y = BorderSlot::of(outside(0, -1), outside(0, 1));
+            if slot_x == BorderSlot::Middle && slot_y == BorderSlot::Middle {
+                // Concave corner, pick the corner that faces the only outside diagonal.
+                let mut diagonals = [(-1, -1), (1, -1), (-1, 1), (1, 1)]
+                    .into_iter()
+                    .filter(|(dx, dy)| outside(*dx, *dy));
+                if let (Some((dx, dy)), None) = (diagonals.next(), diagonals.next()) {
+                    slot_x = BorderSlot::of(dx < 0, dx > 0);
+                    slot_y = BorderSlot::of(dy < 0, dy > 0);
+                }
+            }
+            let source = Vector2::new(
+                slot_x.select(stamp_rect.position.x, stamp_rect.size.x, position.x),
+                slot_y.select(stamp_rect.position.y, stamp_rect.size.y, position.y),
+            );
+            if let Some(tile) = brush.get(source) {
+                self.insert(position, Some((trans, *tile)));
+            }
+        }
+    }
+
     /// Fills in a rectangle using special brush with 3x3 tiles. It puts
     /// corner tiles in the respective corners of the target rectangle and draws lines between each
-    /// corner using middle tiles.
+    /// corner using middle tiles. The inside of the rectangle is only filled if `fill_interior` is true.
     #[inline]
     fn nine_slice_inner<F>(
         &mut self,
         start: Vector2<i32>,
         end: Vector2<i32>,
         stamp: &Stamp,
+        fill_interior: bool,
         fill: F,
     ) where
         F: Fn(&mut TransTilesUpdate, TileRegion, &Stamp, TileRegion),
@@ -780,7 +1543,18 @@ impl TransTilesUpdate {
         let inner_region = region.clone().deflate(1, 1);
 
         let stamp_region = TileRegion::from_bounds_and_direction(stamp_rect.into(), start - end);
-        let inner_stamp_region = stamp_region.clone().deflate(1, 1);
+        let middle_x = nine_slice_middle(stamp_rect.position.x, stamp_rect.size.x);
+        let middle_y = nine_slice_middle(stamp_rect.position.y, stamp_rect.size.y);
+        let stamp_slice = |x: Option<(i32, i32)>, y: Option<(i32, i32)>| {
+            let ((x0, x1), (y0, y1)) = (x?, y?);
+            Some(stamp_region.clone().with_bounds(
+                TileRect::from_points(Vector2::new(x0, y0), Vector2::new(x1, y1)).into(),
+            ))
+        };
+        let (stamp_min, stamp_max) = (
+            stamp_rect.left_bottom_corner(),
+            stamp_rect.right_top_corner(),
+        );
 
         // Place corners first.
         let trans = stamp.transformation();
@@ -823,43 +1597,844 @@ impl TransTilesUpdate {
             )
             .into(),
         );
-        let stamp_top = stamp_region.clone().with_bounds(
-            TileRect::from_points(
-                stamp_rect.left_top_corner() + Vector2::new(1, 0),
-                stamp_rect.right_top_corner() + Vector2::new(-1, 0),
-            )
-            .into(),
+        let stamp_top = stamp_slice(middle_x, Some((stamp_max.y, stamp_max.y)));
+        let stamp_bottom = stamp_slice(middle_x, Some((stamp_min.y, stamp_min.y)));
+        let stamp_left = stamp_slice(Some((stamp_min.x, stamp_min.x)), middle_y);
+        let stamp_right = stamp_slice(Some((stamp_max.x, stamp_max.x)), middle_y);
+
+        if rect.size.x > 2 {
+            if let (Some(stamp_top), Some(stamp_bottom)) = (stamp_top, stamp_bottom) {
+                fill(self, top, stamp, stamp_top);
+                fill(self, bottom, stamp, stamp_bottom);
+            }
+        }
+        if rect.size.y > 2 {
+            if let (Some(stamp_left), Some(stamp_right)) = (stamp_left, stamp_right) {
+                fill(self, left, stamp, stamp_left);
+                fill(self, right, stamp, stamp_right);
+            }
+        }
+        if !fill_interior {
+            return;
+        }
+        if let Some(inner_stamp_region) = stamp_slice(middle_x, middle_y) {
+            fill(self, inner_region, stamp, inner_stamp_region);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 0);
+        let mut stamp = Stamp::default();
+        stamp.insert(Vector2::new(0, 0), a);
+        let mut preview =
+            TransTilesUpdate::preview_draw_tiles(Vector2::new(2, 3), &stamp).collect::<Vec<_>>();
+        assert_eq!(preview, [(Vector2::new(2, 3), a)]);
+        preview =
+            TransTilesUpdate::preview_rect_fill(Vector2::new(0, 0), Vector2::new(1, 1), &stamp)
+                .collect();
+        assert_eq!(preview.len(), 4);
+        assert!(preview.iter().all(|(_, h)| *h == a));
+        // The line preview shows the same tiles in the same cells as the drawn line.
+        let b = TileDefinitionHandle::new(0, 0, 2, 0);
+        stamp.insert(Vector2::new(1, 0), b);
+        let preview =
+            TransTilesUpdate::preview_draw_line(Vector2::new(0, 0), Vector2::new(3, 0), &stamp)
+                .collect::<FxHashMap<_, _>>();
+        let mut update = TransTilesUpdate::default();
+        update.draw_line(Vector2::new(0, 0), Vector2::new(3, 0), &stamp);
+        let drawn = update
+            .iter()
+            .map(|(p, v)| (*p, v.expect("draw_line does not erase").1))
+            .collect::<FxHashMap<_, _>>();
+        assert_eq!(preview.len(), update.len());
+        assert_eq!(preview, drawn);
+        assert!(drawn.values().any(|h| *h == a));
+        assert!(drawn.values().any(|h| *h == b));
+    }
+
+    #[test]
+    fn trans_tiles_update_capacity() {
+        const N: usize = 100;
+        let value = Some((
+            OrthoTransformation::identity(),
+            TileDefinitionHandle::new(0, 0, 0, 0),
+        ));
+        let mut with_capacity = TransTilesUpdate::with_capacity(N);
+        let mut reserved = TransTilesUpdate::default();
+        reserved.reserve(N);
+        for update in [&mut with_capacity, &mut reserved] {
+            let capacity = update.capacity();
+            assert!(capacity >= N);
+            for x in 0..N as i32 {
+                update.insert(Vector2::new(x, 0), value);
+            }
+            // No reallocation happened.
+            assert_eq!(update.capacity(), capacity);
+        }
+    }
+
+    #[test]
+    fn draw_rect_outline() {
+        let brush = grid_stamp(3, 3);
+        let mut update = TransTilesUpdate::default();
+        update.draw_rect_outline(Vector2::new(0, 0), Vector2::new(4, 3), &brush);
+        assert_eq!(update.len(), 14);
+        let tile = |x, y| {
+            update
+                .get(&Vector2::new(x, y))
+                .and_then(|v| v.as_ref())
+                .map(|(_, h)| (h.tile().x, h.tile().y))
+        };
+        assert_eq!(tile(0, 0), Some((0, 0)));
+        assert_eq!(tile(4, 3), Some((2, 2)));
+        assert_eq!(tile(2, 0), Some((1, 0)));
+        assert_eq!(tile(0, 2), Some((0, 1)));
+        assert_eq!(tile(2, 2), None);
+
+        let mut update = TransTilesUpdate::default();
+        update.draw_rect_outline(Vector2::new(0, 0), Vector2::new(4, 0), &brush);
+        assert_eq!(update.len(), 5);
+    }
+
+    #[test]
+    fn tile_set_update_from_diff() {
+        let handle = TileDefinitionHandle::new(0, 0, 1, 0);
+        let property = Uuid::new_v4();
+        let collider = Uuid::new_v4();
+        let old = TileData::default();
+        assert!(TileSetUpdate::from_diff(&old, &old, handle).is_empty());
+
+        let mut new = old.clone();
+        new.color = Color::RED;
+        let update = TileSetUpdate::from_diff(&old, &new, handle);
+        assert!(matches!(
+            update.get(&handle),
+            Some(TileDataUpdate::Color(Color::RED))
+        ));
+
+        let mut new = old.clone();
+        new.properties
+            .insert(property, TileSetPropertyValue::I32(5));
+        let update = TileSetUpdate::from_diff(&old, &new, handle);
+        assert!(matches!(
+            update.get(&handle),
+            Some(TileDataUpdate::Property(id, Some(TileSetPropertyValue::I32(5)))) if *id == property
+        ));
+        // Removing a property is also a single property change.
+        let update = TileSetUpdate::from_diff(&new, &old, handle);
+        assert!(matches!(
+            update.get(&handle),
+            Some(TileDataUpdate::Property(id, None)) if *id == property
+        ));
+
+        let mut new = old.clone();
+        new.colliders.insert(collider, TileCollider::Rectangle);
+        let update = TileSetUpdate::from_diff(&new, &old, handle);
+        match update.get(&handle) {
+            Some(TileDataUpdate::Collider(map)) => {
+                assert_eq!(map.len(), 1);
+                assert!(matches!(map.get(&collider), Some(TileCollider::None)));
+            }
+            other => panic!("{other:?}"),
+        }
+
+        new.color = Color::RED;
+        let update = TileSetUpdate::from_diff(&old, &new, handle);
+        assert!(
+            matches!(update.get(&handle), Some(TileDataUpdate::MaterialTile(data)) if *data == new)
         );
-        let stamp_bottom = stamp_region.clone().with_bounds(
-            TileRect::from_points(
-                stamp_rect.left_bottom_corner() + Vector2::new(1, 0),
-                stamp_rect.right_bottom_corner() + Vector2::new(-1, 0),
-            )
-            .into(),
+    }
+
+    #[test]
+    fn rotate_region() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 0);
+        let b = TileDefinitionHandle::new(0, 0, 2, 0);
+        let mut tiles = Tiles::default();
+        tiles.insert(Vector2::new(0, 0), a);
+        tiles.insert(Vector2::new(1, 0), b);
+        let mut update = TransTilesUpdate::default();
+        update.rotate_region(
+            &tiles,
+            OptionTileRect::from_points(Vector2::new(0, 0), Vector2::new(1, 0)),
+            1,
         );
-        let stamp_left = stamp_region.clone().with_bounds(
-            TileRect::from_points(
-                stamp_rect.left_bottom_corner() + Vector2::new(0, 1),
-                stamp_rect.left_top_corner() + Vector2::new(0, -1),
-            )
-            .into(),
+        let turn = OrthoTransformation::new(false, 1);
+        assert_eq!(update.len(), 3);
+        assert_eq!(update.get(&Vector2::new(0, 0)), Some(&None));
+        assert_eq!(update.get(&Vector2::new(1, -1)), Some(&Some((turn, a))));
+        assert_eq!(update.get(&Vector2::new(1, 0)), Some(&Some((turn, b))));
+    }
+
+    #[test]
+    fn translate_region() {
+        let a = TileDefinitionHandle::new(0, 0, 1, 0);
+        let b = TileDefinitionHandle::new(0, 0, 2, 0);
+        let mut tiles = Tiles::default();
+        tiles.insert(Vector2::new(0, 0), a);
+        tiles.insert(Vector2::new(1, 0), b);
+        let mut update = TransTilesUpdate::default();
+        update.translate_region(
+            &tiles,
+            OptionTileRect::from_points(Vector2::new(0, 0), Vector2::new(1, 0)),
+            Vector2::new(1, 0),
         );
-        let stamp_right = stamp_region.clone().with_bounds(
-            TileRect::from_points(
-                stamp_rect.right_bottom_corner() + Vector2::new(0, 1),
-                stamp_rect.right_top_corner() + Vector2::new(0, -1),
-            )
-            .into(),
+        let identity = OrthoTransformation::identity();
+        assert_eq!(update.len(), 3);
+        assert_eq!(update.get(&Vector2::new(0, 0)), Some(&None));
+        assert_eq!(update.get(&Vector2::new(1, 0)), Some(&Some((identity, a))));
+        assert_eq!(update.get(&Vector2::new(2, 0)), Some(&Some((identity, b))));
+    }
+
+    #[test]
+    fn erase_matching() {
+        let target = TileDefinitionHandle::new(0, 0, 1, 0);
+        let other = TileDefinitionHandle::new(0, 0, 2, 0);
+        let mut tiles = Tiles::default();
+        tiles.insert(Vector2::new(0, 0), target);
+        tiles.insert(Vector2::new(1, 0), other);
+        tiles.insert(Vector2::new(2, 0), target);
+        tiles.insert(Vector2::new(5, 0), target);
+        let mut update = TransTilesUpdate::default();
+        update.erase_matching(
+            &tiles,
+            OptionTileRect::from_points(Vector2::new(0, 0), Vector2::new(3, 3)),
+            target,
         );
+        assert_eq!(update.len(), 2);
+        assert_eq!(update.get(&Vector2::new(0, 0)), Some(&None));
+        assert_eq!(update.get(&Vector2::new(2, 0)), Some(&None));
+    }
+
+    #[test]
+    fn rect_fill_dithered() {
+        let from = TileDefinitionHandle::new(0, 0, 0, 0);
+        let to = TileDefinitionHandle::new(0, 0, 1, 0);
+        let mut update = TransTilesUpdate::default();
+        update.rect_fill_dithered(
+            Vector2::new(0, 0),
+            Vector2::new(19, 9),
+            from,
+            to,
+            GradientAxis::X,
+        );
+        assert_eq!(update.len(), 200);
+        let count_to = |columns: std::ops::Range<i32>| {
+            update
+                .iter()
+                .filter(|(p, v)| columns.contains(&p.x) && **v == Some((Default::default(), to)))
+                .count()
+        };
+        // Roughly balanced in total, but mostly `from` on the left and mostly `to` on the right.
+        let total = count_to(0..20);
+        assert!((90..=110).contains(&total), "{total}");
+        assert!(count_to(0..5) < 15);
+        assert!(count_to(15..20) > 35);
+    }
+
+    #[test]
+    fn auto_border() {
+        let brush = grid_stamp(3, 3);
+        let slot =
+            |x: i16, y: i16| Some((Default::default(), TileDefinitionHandle::new(0, 0, x, y)));
 
-        if rect.size.x > 2 && stamp_rect.size.x > 2 {
-            fill(self, top, stamp, stamp_top);
-            fill(self, bottom, stamp, stamp_bottom);
+        // A 4x4 square without its 2x2 top right quarter and a one-cell-wide vertical strip.
+        let mut region = Tiles::default();
+        let handle = TileDefinitionHandle::new(1, 1, 0, 0);
+        for x in 0..4 {
+            for y in 0..4 {
+                if x < 2 || y < 2 {
+                    region.insert(Vector2::new(x, y), handle);
+                }
+            }
+        }
+        for y in 0..3 {
+            region.insert(Vector2::new(10, y), handle);
+        }
+
+        let mut update = TransTilesUpdate::default();
+        update.auto_border(&region, &brush);
+        assert_eq!(update.len(), region.len());
+        let at = |x, y| update.get(&Vector2::new(x, y)).copied().flatten();
+        // Outer corners.
+        assert_eq!(at(0, 0), slot(0, 0));
+        assert_eq!(at(3, 0), slot(2, 0));
+        assert_eq!(at(0, 3), slot(0, 2));
+        assert_eq!(at(1, 3), slot(2, 2));
+        assert_eq!(at(3, 1), slot(2, 2));
+        // Edges.
+        assert_eq!(at(1, 0), slot(1, 0));
+        assert_eq!(at(0, 1), slot(0, 1));
+        assert_eq!(at(2, 1), slot(1, 2));
+        assert_eq!(at(1, 2), slot(2, 1));
+        // Concave corner.
+        assert_eq!(at(1, 1), slot(2, 2));
+        // One-cell-wide strip.
+        assert_eq!(at(10, 0), slot(1, 0));
+        assert_eq!(at(10, 1), slot(1, 1));
+        assert_eq!(at(10, 2), slot(1, 2));
+    }
+
+    #[test]
+    fn nine_slice_small_stamps() {
+        let nine_slice = |w: i16, h: i16| {
+            let stamp = grid_stamp(w, h);
+            let mut update = TransTilesUpdate::default();
+            update.nine_slice(Vector2::new(0, 0), Vector2::new(4, 4), &stamp);
+            let mut result = update
+                .iter()
+                .map(|(p, v)| {
+                    let tile = v.unwrap().1.tile();
+                    ((p.x, p.y), (tile.x, tile.y))
+                })
+                .collect::<Vec<_>>();
+            result.sort();
+            result
+        };
+        // A single tile fills the whole rectangle.
+        let result = nine_slice(1, 1);
+        assert_eq!(result.len(), 25);
+        assert!(result.iter().all(|(_, tile)| *tile == (0, 0)));
+        // A single row is used for the whole height, and two columns have no middle.
+        let mut expected = Vec::new();
+        for y in 0..5 {
+            expected.push(((0, y), (0, 0)));
+            expected.push(((4, y), (1, 0)));
+        }
+        expected.sort();
+        assert_eq!(nine_slice(2, 1), expected);
+        // Only the corners.
+        assert_eq!(
+            nine_slice(2, 2),
+            vec![
+                ((0, 0), (0, 0)),
+                ((0, 4), (0, 1)),
+                ((4, 0), (1, 0)),
+                ((4, 4), (1, 1)),
+            ]
+        );
+        // The complete brush still fills everything.
+        let result = nine_slice(3, 3);
+        assert_eq!(result.len(), 25);
+        for (position, tile) in [((0, 0), (0, 0)), ((2, 0), (1, 0)), ((0, 2), (0, 1))] {
+            assert!(result.contains(&(position, tile)), "{position:?}");
+        }
+        for (position, tile) in [((2, 2), (1, 1)), ((4, 2), (2, 1)), ((4, 4), (2, 2))] {
+            assert!(result.contains(&(position, tile)), "{position:?}");
+        }
+    }
+
+    /// A stamp of the given size where each tile is on page (0, 0) at the position of the tile
+    /// within the stamp.
+    fn grid_stamp(width: i16, height: i16) -> Stamp {
+        let mut stamp = Stamp::default();
+        for x in 0..width {
+            for y in 0..height {
+                stamp.insert(
+                    Vector2::new(x as i32, y as i32),
+                    TileDefinitionHandle::new(0, 0, x, y),
+                );
+            }
+        }
+        stamp
+    }
+
+    fn single_tile_stamp() -> Stamp {
+        let mut stamp = Stamp::default();
+        stamp.insert(Vector2::new(0, 0), TileDefinitionHandle::new(0, 0, 0, 0));
+        stamp
+    }
+
+    fn sorted_keys(update: &TransTilesUpdate) -> Vec<Vector2<i32>> {
+        let mut keys = update.keys().copied().collect::<Vec<_>>();
+        keys.sort_by_key(|p| (p.y, p.x));
+        keys
+    }
+
+    #[test]
+    fn draw_ellipse() {
+        let stamp = single_tile_stamp();
+        let mut update = TransTilesUpdate::default();
+        update.draw_ellipse(Vector2::new(0, 0), Vector2::new(6, 4), &stamp);
+        let rect = TileRect::new(0, 0, 7, 5);
+        assert!(update.keys().all(|p| rect.contains(*p)));
+        // Touches every side of the rectangle, but not the corners or the center.
+        for p in [(3, 0), (3, 4), (0, 2), (6, 2)] {
+            assert!(update.contains_key(&Vector2::new(p.0, p.1)), "{p:?}");
+        }
+        for p in [(0, 0), (6, 0), (0, 4), (6, 4), (3, 2)] {
+            assert!(!update.contains_key(&Vector2::new(p.0, p.1)), "{p:?}");
+        }
+        // Symmetric.
+        for p in update.keys() {
+            assert!(update.contains_key(&Vector2::new(6 - p.x, p.y)));
+            assert!(update.contains_key(&Vector2::new(p.x, 4 - p.y)));
+        }
+
+        // Swapped corners produce the same ellipse.
+        let mut swapped = TransTilesUpdate::default();
+        swapped.draw_ellipse(Vector2::new(6, 4), Vector2::new(0, 0), &stamp);
+        assert_eq!(sorted_keys(&swapped), sorted_keys(&update));
+    }
+
+    #[test]
+    fn draw_ellipse_even_size() {
+        let mut update = TransTilesUpdate::default();
+        update.draw_ellipse(Vector2::new(0, 0), Vector2::new(3, 3), &single_tile_stamp());
+        let rect = TileRect::new(0, 0, 4, 4);
+        assert!(update.keys().all(|p| rect.contains(*p)));
+        for p in update.keys() {
+            assert!(update.contains_key(&Vector2::new(3 - p.x, p.y)));
+            assert!(update.contains_key(&Vector2::new(p.x, 3 - p.y)));
+        }
+        for p in [(1, 0), (2, 0), (0, 1), (0, 2)] {
+            assert!(update.contains_key(&Vector2::new(p.0, p.1)), "{p:?}");
+        }
+    }
+
+    #[test]
+    fn draw_ellipse_degenerate() {
+        let mut update = TransTilesUpdate::default();
+        update.draw_ellipse(Vector2::new(2, 5), Vector2::new(2, 1), &single_tile_stamp());
+        assert_eq!(
+            sorted_keys(&update),
+            (1..=5).map(|y| Vector2::new(2, y)).collect::<Vec<_>>()
+        );
+        let mut update = TransTilesUpdate::default();
+        update.fill_ellipse(Vector2::new(0, 0), Vector2::new(3, 0), &single_tile_stamp());
+        assert_eq!(
+            sorted_keys(&update),
+            (0..=3).map(|x| Vector2::new(x, 0)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn fill_ellipse() {
+        let stamp = single_tile_stamp();
+        let mut outline = TransTilesUpdate::default();
+        outline.draw_ellipse(Vector2::new(0, 0), Vector2::new(8, 6), &stamp);
+        let mut update = TransTilesUpdate::default();
+        update.fill_ellipse(Vector2::new(8, 6), Vector2::new(0, 0), &stamp);
+        assert!(outline.keys().all(|p| update.contains_key(p)));
+        assert!(update.contains_key(&Vector2::new(4, 3)));
+        assert!(!update.contains_key(&Vector2::new(0, 0)));
+        assert!(!update.contains_key(&Vector2::new(8, 6)));
+        // Every row is a single span.
+        for y in 0..=6 {
+            let row = update.keys().filter(|p| p.y == y).map(|p| p.x);
+            let (min, max, count) = row.fold((i32::MAX, i32::MIN, 0), |(a, b, c), x| {
+                (a.min(x), b.max(x), c + 1)
+            });
+            assert_eq!(max - min + 1, count);
+        }
+
+        let mut random = TransTilesUpdate::default();
+        random.fill_ellipse_random(Vector2::new(0, 0), Vector2::new(8, 6), &stamp);
+        assert_eq!(sorted_keys(&random), sorted_keys(&update));
+    }
+
+    #[test]
+    fn flood_fill_diagonal() {
+        // An X-shaped region of `a` tiles in a 5x5 square of `b` tiles.
+        let a = TileDefinitionHandle::new(0, 0, 0, 0);
+        let b = TileDefinitionHandle::new(0, 0, 1, 0);
+        let mut tiles = Tiles::default();
+        for x in 0..5 {
+            for y in 0..5 {
+                let handle = if x == y || x == 4 - y { a } else { b };
+                tiles.insert(Vector2::new(x, y), handle);
+            }
+        }
+        let brush = SingleTileSource(Default::default(), TileDefinitionHandle::new(0, 0, 2, 0));
+
+        let mut update = TransTilesUpdate::default();
+        update.flood_fill(&tiles, Vector2::new(2, 2), &brush);
+        assert_eq!(sorted_keys(&update), vec![Vector2::new(2, 2)]);
+
+        let mut update = TransTilesUpdate::default();
+        update.flood_fill_diagonal(&tiles, Vector2::new(2, 2), &brush);
+        let mut expected = tiles
+            .iter()
+            .filter(|(_, h)| **h == a)
+            .map(|(p, _)| *p)
+            .collect::<Vec<_>>();
+        expected.sort_by_key(|p| (p.y, p.x));
+        assert_eq!(expected.len(), 9);
+        assert_eq!(sorted_keys(&update), expected);
+
+        // Free cells spread through corners too, but stay within the bounds.
+        let mut update = TransTilesUpdate::default();
+        update.flood_fill_diagonal(&Tiles::default(), Vector2::new(7, 7), &brush);
+        assert_eq!(sorted_keys(&update), vec![Vector2::new(7, 7)]);
+    }
+
+    #[test]
+    fn flood_fill_bounded() {
+        let brush = SingleTileSource(Default::default(), TileDefinitionHandle::new(0, 0, 2, 0));
+        let mut tiles = Tiles::default();
+        tiles.insert(Vector2::new(0, 0), TileDefinitionHandle::new(0, 0, 0, 0));
+        tiles.insert(Vector2::new(9, 9), TileDefinitionHandle::new(0, 0, 0, 0));
+
+        // Limited by the amount of cells.
+        let mut update = TransTilesUpdate::default();
+        let written = update.flood_fill_bounded(
+            &tiles,
+            Vector2::new(5, 5),
+            &brush,
+            10,
+            OptionTileRect::default(),
+        );
+        assert_eq!(written, 10);
+        assert_eq!(update.len(), 10);
+
+        // Limited by the clip rectangle.
+        let clip = TileRect::new(2, 2, 3, 4);
+        let mut update = TransTilesUpdate::default();
+        let written =
+            update.flood_fill_bounded(&tiles, Vector2::new(3, 3), &brush, usize::MAX, clip.into());
+        assert_eq!(written, 12);
+        assert!(update.keys().all(|p| clip.contains(*p)));
+
+        // Start point outside of the clip rectangle.
+        let mut update = TransTilesUpdate::default();
+        let written =
+            update.flood_fill_bounded(&tiles, Vector2::new(7, 7), &brush, usize::MAX, clip.into());
+        assert_eq!(written, 0);
+        assert!(update.is_empty());
+
+        // Unbounded.
+        let mut update = TransTilesUpdate::default();
+        update.flood_fill(&tiles, Vector2::new(5, 5), &brush);
+        assert_eq!(update.len(), 98);
+
+        // A partial fill can be applied and undone.
+        let mut update = TransTilesUpdate::default();
+        update.flood_fill_bounded(
+            &tiles,
+            Vector2::new(5, 5),
+            &brush,
+            7,
+            OptionTileRect::default(),
+        );
+        let tile_set = TileSetResource::new_ok(ResourceKind::Embedded, TileSet::default());
+        let mut tile_set = TileSetRef::new(&tile_set);
+        let mut tiles_update = update.build_tiles_update(&tile_set.as_loaded());
+        let original = tiles.clone();
+        tiles.swap_tiles(&mut tiles_update);
+        assert_eq!(tiles.len(), 9);
+        tiles.swap_tiles(&mut tiles_update);
+        assert_eq!(tiles, original);
+    }
+
+    #[test]
+    fn tile_set_update_changed_cells() {
+        let mut update = TileSetUpdate::default();
+        for handle in [
+            TileDefinitionHandle::new(0, 0, 1, 2),
+            TileDefinitionHandle::new(0, 0, 3, 4),
+            TileDefinitionHandle::new(-1, 5, 0, 0),
+        ] {
+            update.insert(handle, TileDataUpdate::Erase);
         }
-        if rect.size.y > 2 && stamp_rect.size.y > 2 {
-            fill(self, left, stamp, stamp_left);
-            fill(self, right, stamp, stamp_right);
+        let mut cells = update.changed_cells().collect::<Vec<_>>();
+        cells.sort_by_key(|(page, tile)| (page.x, page.y, tile.x, tile.y));
+        assert_eq!(
+            cells,
+            vec![
+                (Vector2::new(-1, 5), Vector2::new(0, 0)),
+                (Vector2::new(0, 0), Vector2::new(1, 2)),
+                (Vector2::new(0, 0), Vector2::new(3, 4)),
+            ]
+        );
+        let mut pages = update.changed_pages().collect::<Vec<_>>();
+        pages.sort_by_key(|page| (page.x, page.y));
+        assert_eq!(pages, vec![Vector2::new(-1, 5), Vector2::new(0, 0)]);
+    }
+
+    #[test]
+    fn set_material_with_properties() {
+        let is_water = Uuid::new_v4();
+        let depth = Uuid::new_v4();
+        let page = Vector2::new(0, 0);
+        let position = Vector2::new(2, 3);
+        let bounds = TileMaterialBounds::default();
+        let mut properties = FxHashMap::default();
+        properties.insert(is_water, TileSetPropertyValue::I32(1));
+        let mut update = TileSetUpdate::default();
+        update.set_material_with_properties(page, position, bounds.clone(), properties);
+        assert_eq!(update.len(), 1);
+        assert_eq!(
+            update.get_property(page, position, is_water),
+            Some(Some(TileSetPropertyValue::I32(1)))
+        );
+        assert_eq!(update.get_property(page, position, depth), Some(None));
+        assert_eq!(update.get_tile_bounds(page, position), Some(bounds.bounds));
+    }
+
+    #[test]
+    fn swap_with_tile_set() {
+        let page = Vector2::new(0, 0);
+        let icon = TileDefinitionHandle::new(0, 0, 0, 0);
+        let property = Uuid::new_v4();
+        let mut tile_set = TileSet::default();
+        tile_set.insert_page(
+            page,
+            TileSetPage {
+                icon,
+                source: TileSetPageSource::new_free(),
+            },
+        );
+        tile_set.insert_page(
+            Vector2::new(1, 0),
+            TileSetPage {
+                icon,
+                source: TileSetPageSource::new_transform(),
+            },
+        );
+        let a = TileDefinitionHandle::new(0, 0, 0, 0);
+        let b = TileDefinitionHandle::new(0, 0, 1, 0);
+        let transform = TileDefinitionHandle::new(1, 0, 0, 0);
+
+        let mut update = TileSetUpdate::default();
+        update.set_material_with_properties(
+            page,
+            a.tile(),
+            TileMaterialBounds::default(),
+            [(property, TileSetPropertyValue::I32(1))]
+                .into_iter()
+                .collect(),
+        );
+        update.insert(b, TileDataUpdate::Material(TileMaterialBounds::default()));
+        update.insert(transform, TileDataUpdate::TransformSet(Some(a)));
+        update.swap_with_tile_set(&mut tile_set);
+        assert_eq!(
+            tile_set.property_value(a, property),
+            Some(TileSetPropertyValue::I32(1))
+        );
+        assert!(tile_set.is_valid_tile(b));
+        assert!(matches!(update.get(&a), Some(TileDataUpdate::Erase)));
+
+        // Color changes are swapped with the live data.
+        let mut color_update = TileSetUpdate::default();
+        color_update.set_color(page, a.tile(), Color::RED);
+        color_update.swap_with_tile_set(&mut tile_set);
+        assert_eq!(tile_set.tile_color(a), Some(Color::RED));
+        color_update.swap_with_tile_set(&mut tile_set);
+        assert_eq!(tile_set.tile_color(a), Some(Color::WHITE));
+
+        // Erasing restores everything on the second swap.
+        let mut erase = TileSetUpdate::default();
+        erase.insert(a, TileDataUpdate::Erase);
+        erase.insert(transform, TileDataUpdate::Erase);
+        erase.swap_with_tile_set(&mut tile_set);
+        assert!(!tile_set.is_valid_tile(a));
+        assert!(matches!(
+            erase.get(&transform),
+            Some(TileDataUpdate::TransformSet(Some(_)))
+        ));
+        erase.swap_with_tile_set(&mut tile_set);
+        assert_eq!(
+            tile_set.property_value(a, property),
+            Some(TileSetPropertyValue::I32(1))
+        );
+        assert!(matches!(
+            erase.get(&transform),
+            Some(TileDataUpdate::TransformSet(None))
+        ));
+
+        // Undo the first update.
+        update.swap_with_tile_set(&mut tile_set);
+        assert!(!tile_set.is_valid_tile(a));
+        assert!(!tile_set.is_valid_tile(b));
+    }
+
+    #[test]
+    fn tile_set_update_merge() {
+        use TileSetPropertyValue as PropValue;
+        let page = Vector2::new(0, 0);
+        let tile = Vector2::new(1, 1);
+        let handle = TileDefinitionHandle::try_new(page, tile).unwrap();
+        let other_handle = TileDefinitionHandle::new(0, 0, 2, 2);
+        let property = Uuid::new_v4();
+
+        let mut update = TileSetUpdate::default();
+        update.set_property_slice(page, tile, Vector2::new(0, 0), property, 1);
+        update.set_color(page, Vector2::new(2, 2), Color::RED);
+
+        let mut second = TileSetUpdate::default();
+        second.set_property_slice(page, tile, Vector2::new(2, 2), property, 2);
+        second.set_color(page, Vector2::new(2, 2), Color::GREEN);
+
+        // Slices are composed, other entries are overridden.
+        update.merge(second);
+        let mut expected = [None; 9];
+        expected[PropValue::nine_position_to_index(Vector2::new(0, 0))] = Some(1);
+        expected[PropValue::nine_position_to_index(Vector2::new(2, 2))] = Some(2);
+        assert!(matches!(
+            update.get(&handle),
+            Some(TileDataUpdate::PropertySlice(id, slices)) if *id == property && *slices == expected
+        ));
+        assert!(matches!(
+            update.get(&other_handle),
+            Some(TileDataUpdate::Color(color)) if *color == Color::GREEN
+        ));
+
+        // Slices are written into a whole nine-slice value.
+        let mut update = TileSetUpdate::default();
+        update.set_property(
+            page,
+            tile,
+            property,
+            Some(PropValue::NineSlice(NineI8([5; 9]))),
+        );
+        let mut third = TileSetUpdate::default();
+        third.set_property_slice(page, tile, Vector2::new(1, 1), property, 7);
+        update.merge(third);
+        let mut expected = [5; 9];
+        expected[PropValue::nine_position_to_index(Vector2::new(1, 1))] = 7;
+        assert!(matches!(
+            update.get(&handle),
+            Some(TileDataUpdate::Property(id, Some(PropValue::NineSlice(NineI8(data)))))
+                if *id == property && *data == expected
+        ));
+
+        // A whole property value overrides slices.
+        let mut fourth = TileSetUpdate::default();
+        fourth.set_property(page, tile, property, None);
+        update.merge(fourth);
+        assert!(matches!(
+            update.get(&handle),
+            Some(TileDataUpdate::Property(id, None)) if *id == property
+        ));
+    }
+
+    #[test]
+    fn try_take_and_swap() {
+        let mut update = TileDataUpdate::Color(Color::RED);
+        assert!(update.try_take_data().is_none());
+        assert!(update.try_take_definition().is_none());
+        assert!(matches!(update, TileDataUpdate::Color(Color::RED)));
+
+        let mut update = TileDataUpdate::MaterialTile(TileData::default());
+        assert!(update.try_take_definition().is_none());
+        assert!(update.try_take_data().is_some());
+        assert!(matches!(update, TileDataUpdate::Erase));
+
+        let mut data = TileData::default();
+        assert_eq!(
+            update.try_swap_with_data(&mut data),
+            Err(TileDataSwapError::Erase)
+        );
+        assert!(matches!(update, TileDataUpdate::Erase));
+
+        let mut update = TileDataUpdate::Color(Color::RED);
+        assert_eq!(update.try_swap_with_data(&mut data), Ok(()));
+        assert_eq!(data.color, Color::RED);
+    }
+
+    #[test]
+    fn draw_tiles_mirrored() {
+        let a = TileDefinitionHandle::new(0, 0, 0, 0);
+        let b = TileDefinitionHandle::new(0, 0, 1, 0);
+        let mut stamp = Stamp::default();
+        stamp.insert(Vector2::new(0, 0), a);
+        stamp.insert(Vector2::new(1, 0), b);
+        let identity = OrthoTransformation::identity();
+        let x_flip = identity.x_flipped();
+        let y_flip = identity.y_flipped();
+
+        let mut plain = TransTilesUpdate::default();
+        plain.draw_tiles(Vector2::new(2, 3), &stamp);
+        let mut update = TransTilesUpdate::default();
+        update.draw_tiles_mirrored(
+            Vector2::new(2, 3),
+            &stamp,
+            MirrorAxis::None,
+            Vector2::new(0, 0),
+        );
+        assert_eq!(sorted_keys(&update), sorted_keys(&plain));
+
+        let mut update = TransTilesUpdate::default();
+        update.draw_tiles_mirrored(
+            Vector2::new(2, 3),
+            &stamp,
+            MirrorAxis::X,
+            Vector2::new(0, 0),
+        );
+        assert_eq!(update.len(), 4);
+        assert_eq!(update.get(&Vector2::new(2, 3)), Some(&Some((identity, a))));
+        assert_eq!(update.get(&Vector2::new(-2, 3)), Some(&Some((x_flip, a))));
+        assert_eq!(update.get(&Vector2::new(-3, 3)), Some(&Some((x_flip, b))));
+
+        let mut update = TransTilesUpdate::default();
+        update.draw_tiles_mirrored(
+            Vector2::new(2, 3),
+            &stamp,
+            MirrorAxis::Both,
+            Vector2::new(0, 1),
+        );
+        assert_eq!(update.len(), 8);
+        assert_eq!(update.get(&Vector2::new(3, -1)), Some(&Some((y_flip, b))));
+        assert_eq!(
+            update.get(&Vector2::new(-3, -1)),
+            Some(&Some((x_flip.y_flipped(), b)))
+        );
+
+        // The reflection of a tile on the axis overlaps it, and the reflection wins.
+        let mut update = TransTilesUpdate::default();
+        update.draw_tiles_mirrored(
+            Vector2::new(0, 0),
+            &stamp,
+            MirrorAxis::X,
+            Vector2::new(0, 0),
+        );
+        assert_eq!(update.len(), 3);
+        assert_eq!(update.get(&Vector2::new(0, 0)), Some(&Some((x_flip, a))));
+    }
+
+    #[test]
+    fn draw_thick_line() {
+        let stamp = single_tile_stamp();
+        let (from, to) = (Vector2::new(-3, 2), Vector2::new(9, 6));
+
+        // Thickness 1 is a regular line.
+        let mut line = TransTilesUpdate::default();
+        line.draw_line(from, to, &stamp.repeat(from, to));
+        let mut update = TransTilesUpdate::default();
+        update.draw_thick_line(from, to, &stamp, 1);
+        assert_eq!(sorted_keys(&update), sorted_keys(&line));
+
+        // A horizontal band.
+        let mut update = TransTilesUpdate::default();
+        update.draw_thick_line(Vector2::new(0, 0), Vector2::new(4, 0), &stamp, 3);
+        assert_eq!(update.len(), 15);
+        assert!(update.keys().all(|p| (-1..=1).contains(&p.y)));
+
+        // Diagonal lines have no gaps: every column of the band is contiguous, and the band is
+        // roughly as wide as requested.
+        for thickness in 2..6 {
+            let mut update = TransTilesUpdate::default();
+            update.draw_thick_line(Vector2::new(0, 0), Vector2::new(20, 21), &stamp, thickness);
+            for y in 2..19 {
+                let xs = update
+                    .keys()
+                    .filter(|p| p.y == y)
+                    .map(|p| p.x)
+                    .collect::<Vec<_>>();
+                let min = *xs.iter().min().unwrap();
+                let max = *xs.iter().max().unwrap();
+                assert_eq!((max - min + 1) as usize, xs.len());
+                let width = xs.len() as f32 / std::f32::consts::SQRT_2;
+                assert!(
+                    (width - thickness as f32).abs() <= 1.0,
+                    "{thickness} {width}"
+                );
+            }
         }
-        fill(self, inner_region, stamp, inner_stamp_region);
     }
 }
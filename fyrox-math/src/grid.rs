@@ -0,0 +1,202 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Traversal of integer grids.
+
+use nalgebra::Vector2;
+
+/// An iterator over the cells of a grid that lie on a straight line between two cells, produced
+/// by the Bresenham's line algorithm. Both end cells are included, and every two consecutive
+/// cells are neighbours (possibly diagonal ones). The cells are produced in the order of
+/// the major axis of the line (the one with the larger difference between the end points),
+/// from the lower coordinate to the higher one; use [`Iterator::rev`] to walk the line
+/// in the opposite direction.
+///
+/// Every cell is calculated independently of the others, so the iterator knows its exact
+/// length and can be consumed from both ends.
+#[derive(Clone, Debug)]
+pub struct BresenhamLineIter {
+    dx: i32,
+    dy: i32,
+    x: i32,
+    y: i32,
+    error: i32,
+    is_steep: bool,
+    y_step: i32,
+    front: i32,
+    back: i32,
+}
+
+impl BresenhamLineIter {
+    /// Creates an iterator over the cells of the line from `start` to `end`.
+    pub fn new(start: Vector2<i32>, end: Vector2<i32>) -> BresenhamLineIter {
+        let (mut x0, mut y0) = (start.x, start.y);
+        let (mut x1, mut y1) = (end.x, end.y);
+
+        let is_steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if is_steep {
+            std::mem::swap(&mut x0, &mut y0);
+            std::mem::swap(&mut x1, &mut y1);
+        }
+
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+        }
+
+        let dx = x1 - x0;
+
+        BresenhamLineIter {
+            dx,
+            dy: (y1 - y0).abs(),
+            x: x0,
+            y: y0,
+            error: dx / 2,
+            is_steep,
+            y_step: if y0 < y1 { 1 } else { -1 },
+            front: 0,
+            back: dx + 1,
+        }
+    }
+
+    /// Calculates the cell with the given index along the line.
+    fn cell(&self, index: i32) -> Vector2<i32> {
+        // The error is decreased by `dy` on every step and increased by `dx` on every step
+        // along the minor axis, staying in the `0..dx` range, so the amount of the minor steps
+        // is the ceiling of `(index * dy - error) / dx`.
+        let minor_steps = if self.dx == 0 {
+            0
+        } else {
+            let numerator = index as i64 * self.dy as i64 - self.error as i64;
+            -((-numerator).div_euclid(self.dx as i64)) as i32
+        };
+        let x = self.x + index;
+        let y = self.y + self.y_step * minor_steps;
+        if self.is_steep {
+            Vector2::new(y, x)
+        } else {
+            Vector2::new(x, y)
+        }
+    }
+}
+
+impl Iterator for BresenhamLineIter {
+    type Item = Vector2<i32>;
+
+    fn next(&mut self) -> Option<Vector2<i32>> {
+        if self.front >= self.back {
+            None
+        } else {
+            let cell = self.cell(self.front);
+            self.front += 1;
+            Some(cell)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.back - self.front).max(0) as usize;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for BresenhamLineIter {
+    fn next_back(&mut self) -> Option<Vector2<i32>> {
+        if self.front >= self.back {
+            None
+        } else {
+            self.back -= 1;
+            Some(self.cell(self.back))
+        }
+    }
+}
+
+impl ExactSizeIterator for BresenhamLineIter {}
+
+#[cfg(test)]
+mod test {
+    use crate::grid::BresenhamLineIter;
+    use nalgebra::Vector2;
+
+    // Step-by-step version of the algorithm.
+    fn reference_line(start: Vector2<i32>, end: Vector2<i32>) -> Vec<Vector2<i32>> {
+        let (mut x0, mut y0) = (start.x, start.y);
+        let (mut x1, mut y1) = (end.x, end.y);
+        let is_steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if is_steep {
+            std::mem::swap(&mut x0, &mut y0);
+            std::mem::swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+        }
+        let (dx, dy) = (x1 - x0, (y1 - y0).abs());
+        let y_step = if y0 < y1 { 1 } else { -1 };
+        let mut error = dx / 2;
+        let mut y = y0;
+        let mut result = Vec::new();
+        for x in x0..=x1 {
+            result.push(if is_steep {
+                Vector2::new(y, x)
+            } else {
+                Vector2::new(x, y)
+            });
+            error -= dy;
+            if error < 0 {
+                y += y_step;
+                error += dx;
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_bresenham_line_iter() {
+        for start in [Vector2::new(0, 0), Vector2::new(-3, 5)] {
+            for x in -7..=7 {
+                for y in -7..=7 {
+                    let end = start + Vector2::new(x, y);
+                    let iter = BresenhamLineIter::new(start, end);
+                    let expected = reference_line(start, end);
+                    assert_eq!(iter.len(), expected.len());
+                    assert_eq!(iter.clone().collect::<Vec<_>>(), expected);
+                    let mut reversed = expected.clone();
+                    reversed.reverse();
+                    assert_eq!(iter.rev().collect::<Vec<_>>(), reversed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bresenham_line_iter_both_ends() {
+        let mut iter = BresenhamLineIter::new(Vector2::new(0, 0), Vector2::new(4, 2));
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next(), Some(Vector2::new(0, 0)));
+        assert_eq!(iter.next_back(), Some(Vector2::new(4, 2)));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next_back(), Some(Vector2::new(3, 1)));
+        assert_eq!(iter.next(), Some(Vector2::new(1, 0)));
+        assert_eq!(iter.next(), Some(Vector2::new(2, 1)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(iter.len(), 0);
+    }
+}
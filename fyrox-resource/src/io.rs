@@ -53,6 +53,21 @@ pub trait ResourceIo: Send + Sync + 'static {
         dest: &'a Path,
     ) -> ResourceIoFuture<'a, Result<(), FileLoadError>>;
 
+    /// Attempts to write the given bytes to the file at the given path, replacing the file if
+    /// it already exists.
+    ///
+    /// Default implementation returns an error, for read-only implementations.
+    fn write_file<'a>(
+        &'a self,
+        path: &'a Path,
+        #[allow(unused)] data: Vec<u8>,
+    ) -> ResourceIoFuture<'a, Result<(), FileLoadError>> {
+        Box::pin(ready(Err(FileLoadError::Custom(format!(
+            "Unable to write {}, writing files is not supported.",
+            path.display()
+        )))))
+    }
+
     /// Tries to convert the path to its canonical form (normalize it in other terms). This method
     /// should guarantee correct behaviour for relative paths. Symlinks aren't mandatory to
     /// follow.
@@ -151,6 +166,17 @@ impl ResourceIo for FsResourceIo {
         })
     }
 
+    fn write_file<'a>(
+        &'a self,
+        path: &'a Path,
+        data: Vec<u8>,
+    ) -> ResourceIoFuture<'a, Result<(), FileLoadError>> {
+        Box::pin(async move {
+            std::fs::write(path, data)?;
+            Ok(())
+        })
+    }
+
     fn canonicalize_path<'a>(
         &'a self,
         path: &'a Path,